@@ -5,15 +5,20 @@ use winit::event::{DeviceEvent, DeviceId, StartCause, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, EventLoop};
 use winit::window::WindowId;
 
-use core::PlatformBackend;
+use core::{PlatformBackend, State};
 
 pub const WIDTH: u32 = 1920;
 pub const HEIGHT: u32 = 1080;
 pub const FPS: u32 = 60;
 
+/// Runs once after the backend is created, e.g. to load a model into the view port.
+pub type SetupFn = Box<dyn FnOnce(&mut State) -> Result<(), String>>;
+
 /// Generic application struct that works with any platform backend.
 pub struct App<P: PlatformBackend> {
     backend: Option<P>,
+    setup: Option<SetupFn>,
+    error: Option<String>,
     _marker: PhantomData<P>,
 }
 
@@ -21,6 +26,8 @@ impl<P: PlatformBackend> App<P> {
     pub fn new() -> Self {
         Self {
             backend: None,
+            setup: None,
+            error: None,
             _marker: PhantomData,
         }
     }
@@ -30,6 +37,23 @@ impl<P: PlatformBackend> App<P> {
             .run_app(&mut Self::new())
             .expect("Failed to run event loop");
     }
+
+    /// Like [`App::run`], but calls `setup` once the backend exists.
+    ///
+    /// Returns the error if the backend or `setup` failed, after the event loop exits.
+    pub fn run_with(event_loop: EventLoop<()>, setup: SetupFn) -> Result<(), String> {
+        let mut app = Self::new();
+        app.setup = Some(setup);
+
+        event_loop
+            .run_app(&mut app)
+            .map_err(|e| format!("Failed to run event loop: {}", e))?;
+
+        match app.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
 }
 
 impl<P: PlatformBackend> Default for App<P> {
@@ -51,12 +75,21 @@ impl<P: PlatformBackend> ApplicationHandler for App<P> {
         if self.backend.is_none() {
             match P::new(event_loop) {
                 Ok(mut backend) => {
+                    if let Some(setup) = self.setup.take()
+                        && let Err(e) = setup(backend.state())
+                    {
+                        self.error = Some(e);
+                        event_loop.exit();
+                        return;
+                    }
+
                     backend.state().window.request_redraw();
                     self.backend = Some(backend);
                     info!("App state created");
                 }
                 Err(e) => {
                     error!("Error creating AppState: {}", e);
+                    self.error = Some(e.to_string());
                     event_loop.exit();
                 }
             }
@@ -73,6 +106,13 @@ impl<P: PlatformBackend> ApplicationHandler for App<P> {
                 WindowEvent::Resized(size) => {
                     backend.resize(size);
                 }
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::DroppedFile(path) => {
+                    info!("Opening dropped file '{}'", path.display());
+                    if let Err(e) = backend.state().view_port.open(&path) {
+                        error!("Failed to open '{}': {}", path.display(), e);
+                    }
+                }
                 WindowEvent::CloseRequested => {
                     info!("The close button was pressed; stopping");
                     event_loop.exit();
@@ -106,7 +146,10 @@ impl<P: PlatformBackend> ApplicationHandler for App<P> {
             state.input.end_step();
 
             let input = state.input.clone();
-            state.view_port.handle_input(dt, &input, event_loop);
+            state.view_port.handle_input(dt, &input);
+            if state.view_port.exit_requested() {
+                event_loop.exit();
+            }
 
             let request_redraw = state.request_redraw;
             let wait_cancelled = state.wait_cancelled;
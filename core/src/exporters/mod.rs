@@ -0,0 +1,246 @@
+//! Model exporters.
+//!
+//! The counterpart of [`loaders`](crate::loaders): an [`ExportScene`] collects CPU-side
+//! meshes with their materials and model matrices, and each writer serializes it to a
+//! file format. Only [`Mesh::vertices`] and [`Mesh::indices`] are read, so nothing in
+//! here needs a GL context.
+
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use glam::{Mat3, Mat4};
+
+use crate::game::Renderable;
+use crate::graphics::{Mesh, Vertex};
+use crate::loaders::{MaterialData, ModelData};
+
+pub use obj::{write_mtl, write_obj};
+pub use ply::write_ply;
+pub use stl::write_stl;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use obj::save_obj;
+#[cfg(not(target_arch = "wasm32"))]
+pub use ply::save_ply;
+#[cfg(not(target_arch = "wasm32"))]
+pub use stl::save_stl;
+
+/// Extensions [`save_scene`] knows how to write, in lower case.
+pub const EXPORT_EXTENSIONS: &[&str] = &["obj", "stl", "ply"];
+
+#[derive(Debug)]
+pub enum ExportError {
+    /// The file could not be written.
+    Io { path: String, message: String },
+    /// The scene has no triangles to write.
+    NoGeometry,
+    /// The file extension isn't one of the supported export formats.
+    UnsupportedFormat(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io { path, message } => {
+                write!(f, "Failed to write '{}': {}", path, message)
+            }
+            ExportError::NoGeometry => write!(f, "Nothing to export, the scene has no triangles"),
+            ExportError::UnsupportedFormat(path) => {
+                write!(f, "Unsupported export format: '{}'", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ExportOptions {
+    /// Transform positions and normals by each object's matrix. Without it every
+    /// mesh is written in its own model space.
+    pub bake_transforms: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            bake_transforms: true,
+        }
+    }
+}
+
+/// One mesh to export.
+pub struct ExportObject {
+    pub name: String,
+    pub mesh: Mesh,
+    pub material: Option<MaterialData>,
+    /// Usually [`Renderable::model_matrix`].
+    pub matrix: Mat4,
+}
+
+/// Everything an exporter writes, in order.
+#[derive(Default)]
+pub struct ExportScene {
+    pub objects: Vec<ExportObject>,
+}
+
+impl ExportScene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A scene holding just `mesh`, untransformed.
+    pub fn from_mesh(name: &str, mesh: &Mesh) -> Self {
+        let mut scene = Self::new();
+        scene.add_mesh(name, mesh, None, Mat4::IDENTITY);
+        scene
+    }
+
+    /// Every part of an imported model, placed by its node.
+    pub fn from_model(model: &ModelData) -> Self {
+        let mut scene = Self::new();
+        for part in &model.parts {
+            let material = part.material.and_then(|i| model.materials.get(i)).cloned();
+            let matrix = part.node.map_or(Mat4::IDENTITY, |n| model.node_matrix(n));
+            scene.add_mesh(&part.name, &part.mesh, material, matrix);
+        }
+        scene
+    }
+
+    pub fn add_mesh(
+        &mut self,
+        name: &str,
+        mesh: &Mesh,
+        material: Option<MaterialData>,
+        matrix: Mat4,
+    ) {
+        self.objects.push(ExportObject {
+            name: name.to_string(),
+            mesh: Mesh::new(mesh.vertices.clone(), mesh.indices.clone()),
+            material,
+            matrix,
+        });
+    }
+
+    /// Add a scene object with its material, placed by its current model matrix
+    /// below `node_matrix`, the world matrix of the node it hangs from.
+    pub fn add_renderable(&mut self, name: &str, renderable: &dyn Renderable, node_matrix: Mat4) {
+        self.add_mesh(
+            name,
+            renderable.mesh(),
+            Some(MaterialData::from_material(renderable.material())),
+            node_matrix * renderable.model_matrix(),
+        );
+    }
+
+    /// Whether there is at least one triangle to write.
+    pub fn has_geometry(&self) -> bool {
+        self.objects.iter().any(|o| triangle_count(&o.mesh) > 0)
+    }
+}
+
+/// Write `scene` to `path`, picking the format from its extension.
+///
+/// OBJ files get their material library written next to them.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_scene(
+    path: impl AsRef<Path>,
+    scene: &ExportScene,
+    options: &ExportOptions,
+) -> Result<(), ExportError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "obj" => save_obj(path, scene, options),
+        "stl" => save_stl(path, scene, options),
+        "ply" => save_ply(path, scene, options),
+        _ => Err(ExportError::UnsupportedFormat(path.display().to_string())),
+    }
+}
+
+/// Create `path` and hand a buffered writer to `write`.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_file(
+    path: &Path,
+    write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> std::io::Result<()>,
+) -> Result<(), ExportError> {
+    use std::io::Write;
+
+    let io_error = |e: std::io::Error| ExportError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    };
+
+    let file = std::fs::File::create(path).map_err(io_error)?;
+    let mut out = std::io::BufWriter::new(file);
+    write(&mut out).map_err(io_error)?;
+    out.flush().map_err(io_error)
+}
+
+/// Triangles of `mesh`, which draws its vertices in order when it has no indices.
+fn triangles(mesh: &Mesh) -> impl Iterator<Item = [u32; 3]> + '_ {
+    let count = triangle_count(mesh) as u32;
+    (0..count).map(move |t| {
+        let corner = |i: u32| {
+            let i = t * 3 + i;
+            if mesh.indices.is_empty() {
+                i
+            } else {
+                mesh.indices[i as usize]
+            }
+        };
+        [corner(0), corner(1), corner(2)]
+    })
+}
+
+fn triangle_count(mesh: &Mesh) -> usize {
+    if mesh.indices.is_empty() {
+        mesh.vertices.len() / 3
+    } else {
+        mesh.indices.len() / 3
+    }
+}
+
+/// An object's vertices and triangles as they should be written.
+struct Baked {
+    vertices: Vec<Vertex>,
+    triangles: Vec<[u32; 3]>,
+}
+
+impl Baked {
+    fn new(object: &ExportObject, options: &ExportOptions) -> Self {
+        let mut vertices = object.mesh.vertices.clone();
+        let mut triangles: Vec<[u32; 3]> = triangles(&object.mesh)
+            .filter(|t| t.iter().all(|&i| (i as usize) < vertices.len()))
+            .collect();
+
+        if options.bake_transforms && object.matrix != Mat4::IDENTITY {
+            let normal_matrix = Mat3::from_mat4(object.matrix).inverse().transpose();
+            for vertex in &mut vertices {
+                vertex.position = object.matrix.transform_point3(vertex.position);
+                vertex.normal = (normal_matrix * vertex.normal).normalize_or_zero();
+            }
+
+            // A mirroring matrix turns the triangles inside out, flip them back
+            if object.matrix.determinant() < 0.0 {
+                for triangle in &mut triangles {
+                    triangle.swap(1, 2);
+                }
+            }
+        }
+
+        Self {
+            vertices,
+            triangles,
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Wavefront OBJ and MTL writer.
+//!
+//! Every object becomes an `o` group with its own `usemtl`. Equal materials with the
+//! same name are written once, so parts of one imported model keep sharing theirs.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use glam::Vec3;
+use log::warn;
+
+use crate::exporters::{Baked, ExportOptions, ExportScene};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::exporters::{ExportError, write_file};
+use crate::loaders::{MaterialData, TextureSource};
+
+/// Write the geometry of `scene` as OBJ, referencing `mtllib` if given.
+pub fn write_obj(
+    out: &mut impl Write,
+    scene: &ExportScene,
+    options: &ExportOptions,
+    mtllib: Option<&str>,
+) -> io::Result<()> {
+    let (materials, assigned) = collect_materials(scene);
+    // `v x y z r g b` is an extension, so only use it when there is color to keep
+    let colored = scene
+        .objects
+        .iter()
+        .any(|o| o.mesh.vertices.iter().any(|v| v.color != Vec3::ONE));
+
+    writeln!(out, "# Exported by ObjViewer")?;
+    if let Some(mtllib) = mtllib
+        && !materials.is_empty()
+    {
+        writeln!(out, "mtllib {}", mtllib)?;
+    }
+
+    // OBJ indices are 1-based and shared by the whole file
+    let mut offset = 1;
+    for (object, material) in scene.objects.iter().zip(assigned) {
+        let baked = Baked::new(object, options);
+        if baked.triangles.is_empty() {
+            continue;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "o {}", object_name(&object.name))?;
+
+        for v in &baked.vertices {
+            let p = v.position;
+            if colored {
+                let c = v.color;
+                writeln!(out, "v {} {} {} {} {} {}", p.x, p.y, p.z, c.x, c.y, c.z)?;
+            } else {
+                writeln!(out, "v {} {} {}", p.x, p.y, p.z)?;
+            }
+        }
+        for v in &baked.vertices {
+            // The viewer flips v on import, so undo it
+            writeln!(out, "vt {} {}", v.tex_coords.x, 1.0 - v.tex_coords.y)?;
+        }
+        for v in &baked.vertices {
+            writeln!(out, "vn {} {} {}", v.normal.x, v.normal.y, v.normal.z)?;
+        }
+
+        if let Some(index) = material {
+            writeln!(out, "usemtl {}", materials[index].0)?;
+        }
+        for triangle in &baked.triangles {
+            let [a, b, c] = triangle.map(|i| i as usize + offset);
+            writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+        }
+
+        offset += baked.vertices.len();
+    }
+
+    Ok(())
+}
+
+/// Write the materials of `scene` as an MTL library.
+///
+/// Texture files are written relative to `base_dir` when they are inside it.
+pub fn write_mtl(out: &mut impl Write, scene: &ExportScene, base_dir: &Path) -> io::Result<()> {
+    let (materials, _) = collect_materials(scene);
+
+    writeln!(out, "# Exported by ObjViewer")?;
+    for (name, material) in materials {
+        writeln!(out)?;
+        writeln!(out, "newmtl {}", name)?;
+
+        let color = |out: &mut dyn Write, key: &str, c: Vec3| {
+            writeln!(out, "{} {} {} {}", key, c.x, c.y, c.z)
+        };
+        color(out, "Ka", material.ambient)?;
+        color(out, "Kd", material.diffuse)?;
+        color(out, "Ks", material.specular)?;
+        if material.emissive != Vec3::ZERO {
+            color(out, "Ke", material.emissive)?;
+        }
+        writeln!(out, "Ns {}", material.shininess)?;
+        writeln!(out, "d {}", material.opacity)?;
+        writeln!(out, "illum {}", material.illum)?;
+
+        let maps = [
+            ("map_Kd", &material.diffuse_map),
+            ("map_Ks", &material.specular_map),
+            ("map_Bump", &material.normal_map),
+            ("map_Ke", &material.emissive_map),
+        ];
+        for (key, source) in maps {
+            match source {
+                Some(TextureSource::File(path)) => {
+                    let path = path.strip_prefix(base_dir).unwrap_or(path);
+                    writeln!(out, "{} {}", key, path.display())?;
+                }
+                Some(TextureSource::Bytes(_)) => {
+                    warn!("Skipping embedded {} of material '{}'", key, name)
+                }
+                None => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `scene` to an OBJ file, with its materials in an MTL file next to it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_obj(
+    path: impl AsRef<Path>,
+    scene: &ExportScene,
+    options: &ExportOptions,
+) -> Result<(), ExportError> {
+    let path = path.as_ref();
+    if !scene.has_geometry() {
+        return Err(ExportError::NoGeometry);
+    }
+
+    let (materials, _) = collect_materials(scene);
+    let mtl_path = path.with_extension("mtl");
+    let mtllib = mtl_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string());
+
+    if !materials.is_empty() {
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        write_file(&mtl_path, |out| write_mtl(out, scene, base_dir))?;
+    }
+
+    write_file(path, |out| {
+        write_obj(out, scene, options, mtllib.as_deref())
+    })
+}
+
+/// The unique materials of `scene` with their MTL names, and which one each object uses.
+///
+/// Equal materials with the same name are written once. Different ones that
+/// share a name get a numbered suffix, `Material.001`, as Blender does.
+fn collect_materials(scene: &ExportScene) -> (Vec<(String, &MaterialData)>, Vec<Option<usize>>) {
+    let mut materials: Vec<(String, &MaterialData)> = Vec::new();
+
+    let assigned = scene
+        .objects
+        .iter()
+        .map(|object| {
+            let material = object.material.as_ref()?;
+            if let Some(index) = materials
+                .iter()
+                .position(|(_, m)| m.name == material.name && *m == material)
+            {
+                return Some(index);
+            }
+
+            let base = match material.name.split_whitespace().collect::<Vec<_>>() {
+                // `newmtl` names end at the first space
+                words if !words.is_empty() => words.join("_"),
+                _ => format!("material_{}", materials.len()),
+            };
+            let taken = |name: &str| materials.iter().any(|(n, _)| n == name);
+            let mut name = base.clone();
+            let mut suffix = 0;
+            while taken(&name) {
+                suffix += 1;
+                name = format!("{}.{:03}", base, suffix);
+            }
+
+            materials.push((name, material));
+            Some(materials.len() - 1)
+        })
+        .collect();
+
+    (materials, assigned)
+}
+
+fn object_name(name: &str) -> &str {
+    if name.trim().is_empty() {
+        "object"
+    } else {
+        name.trim()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use super::*;
+    use crate::graphics::Mesh;
+    use crate::loaders::parse_mtl;
+
+    #[test]
+    fn materials_with_the_same_name() {
+        let red = MaterialData {
+            diffuse: Vec3::X,
+            ..MaterialData::new("Paint")
+        };
+        let blue = MaterialData {
+            diffuse: Vec3::Z,
+            ..MaterialData::new("Paint")
+        };
+
+        let mesh = Mesh::new(Vec::new(), Vec::new());
+        let mut scene = ExportScene::new();
+        for material in [red.clone(), red, blue, MaterialData::new("Paint.001")] {
+            scene.add_mesh("part", &mesh, Some(material), Mat4::IDENTITY);
+        }
+        scene.add_mesh("bare", &mesh, None, Mat4::IDENTITY);
+
+        let (materials, assigned) = collect_materials(&scene);
+        let names: Vec<&str> = materials.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["Paint", "Paint.001", "Paint.001.001"]);
+        assert_eq!(assigned, [Some(0), Some(0), Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn texture_maps_survive_the_mtl() {
+        let base = Path::new("/scenes/garden");
+        let material = MaterialData {
+            diffuse_map: Some(TextureSource::File(base.join("textures/bark.png"))),
+            specular_map: Some(TextureSource::File("/shared/shine.png".into())),
+            normal_map: Some(TextureSource::File(base.join("bark_normal.png"))),
+            ..MaterialData::new("Bark")
+        };
+        let mut scene = ExportScene::new();
+        let mesh = Mesh::new(Vec::new(), Vec::new());
+        scene.add_mesh("trunk", &mesh, Some(material.clone()), Mat4::IDENTITY);
+
+        let mut out = Vec::new();
+        write_mtl(&mut out, &scene, base).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        // Maps next to the scene are written relative to it
+        assert!(text.contains("map_Kd textures/bark.png\n"), "{}", text);
+
+        let loaded = parse_mtl(&text).unwrap();
+        let loaded = MaterialData::from_mtl(&loaded[0], base);
+        assert_eq!(loaded.diffuse_map, material.diffuse_map);
+        assert_eq!(loaded.specular_map, material.specular_map);
+        assert_eq!(loaded.normal_map, material.normal_map);
+    }
+}
@@ -0,0 +1,97 @@
+//! Binary little-endian PLY writer.
+//!
+//! All objects are merged into one vertex and one face element. Positions,
+//! normals, vertex colors and texture coordinates are kept; materials are not.
+
+use std::io::{self, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use crate::exporters::{Baked, ExportOptions, ExportScene};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::exporters::{ExportError, write_file};
+
+/// Write `scene` as a binary little-endian PLY.
+pub fn write_ply(
+    out: &mut impl Write,
+    scene: &ExportScene,
+    options: &ExportOptions,
+) -> io::Result<()> {
+    let objects: Vec<Baked> = scene
+        .objects
+        .iter()
+        .map(|o| Baked::new(o, options))
+        .collect();
+    let vertex_count: usize = objects.iter().map(|o| o.vertices.len()).sum();
+    let face_count: usize = objects.iter().map(|o| o.triangles.len()).sum();
+    if u32::try_from(vertex_count).is_err() {
+        return Err(io::Error::other("Too many vertices for 32-bit indices"));
+    }
+
+    write!(
+        out,
+        "ply\n\
+         format binary_little_endian 1.0\n\
+         comment Exported by ObjViewer\n\
+         element vertex {}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         property float nx\n\
+         property float ny\n\
+         property float nz\n\
+         property uchar red\n\
+         property uchar green\n\
+         property uchar blue\n\
+         property float s\n\
+         property float t\n\
+         element face {}\n\
+         property list uchar uint vertex_indices\n\
+         end_header\n",
+        vertex_count, face_count
+    )?;
+
+    for object in &objects {
+        for vertex in &object.vertices {
+            for c in vertex.position.to_array() {
+                out.write_all(&c.to_le_bytes())?;
+            }
+            for c in vertex.normal.to_array() {
+                out.write_all(&c.to_le_bytes())?;
+            }
+            for c in vertex.color.to_array() {
+                out.write_all(&[(c.clamp(0.0, 1.0) * 255.0).round() as u8])?;
+            }
+            // The viewer flips v on import, so undo it
+            out.write_all(&vertex.tex_coords.x.to_le_bytes())?;
+            out.write_all(&(1.0 - vertex.tex_coords.y).to_le_bytes())?;
+        }
+    }
+
+    let mut offset = 0u32;
+    for object in &objects {
+        for triangle in &object.triangles {
+            out.write_all(&[3])?;
+            for index in triangle {
+                out.write_all(&(index + offset).to_le_bytes())?;
+            }
+        }
+        offset += object.vertices.len() as u32;
+    }
+
+    Ok(())
+}
+
+/// Write `scene` to a binary PLY file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_ply(
+    path: impl AsRef<Path>,
+    scene: &ExportScene,
+    options: &ExportOptions,
+) -> Result<(), ExportError> {
+    if !scene.has_geometry() {
+        return Err(ExportError::NoGeometry);
+    }
+
+    write_file(path.as_ref(), |out| write_ply(out, scene, options))
+}
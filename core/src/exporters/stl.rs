@@ -0,0 +1,71 @@
+//! Binary STL writer.
+//!
+//! STL only stores triangles, so every object is flattened into one solid and
+//! materials, colors and texture coordinates are dropped.
+
+use std::io::{self, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use glam::Vec3;
+
+use crate::exporters::{Baked, ExportOptions, ExportScene};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::exporters::{ExportError, write_file};
+
+/// Write `scene` as a binary STL.
+pub fn write_stl(
+    out: &mut impl Write,
+    scene: &ExportScene,
+    options: &ExportOptions,
+) -> io::Result<()> {
+    let objects: Vec<Baked> = scene
+        .objects
+        .iter()
+        .map(|o| Baked::new(o, options))
+        .collect();
+    let count: usize = objects.iter().map(|o| o.triangles.len()).sum();
+    let count = u32::try_from(count)
+        .map_err(|_| io::Error::other("Too many triangles for a binary STL"))?;
+
+    let mut header = [0u8; 80];
+    let label = b"Exported by ObjViewer";
+    header[..label.len()].copy_from_slice(label);
+    out.write_all(&header)?;
+    out.write_all(&count.to_le_bytes())?;
+
+    let write_vec3 = |out: &mut dyn Write, v: Vec3| -> io::Result<()> {
+        for c in v.to_array() {
+            out.write_all(&c.to_le_bytes())?;
+        }
+        Ok(())
+    };
+
+    for object in &objects {
+        for triangle in &object.triangles {
+            let [a, b, c] = triangle.map(|i| object.vertices[i as usize].position);
+            write_vec3(out, (b - a).cross(c - a).normalize_or_zero())?;
+            write_vec3(out, a)?;
+            write_vec3(out, b)?;
+            write_vec3(out, c)?;
+            // Attribute byte count, unused
+            out.write_all(&[0, 0])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `scene` to a binary STL file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_stl(
+    path: impl AsRef<Path>,
+    scene: &ExportScene,
+    options: &ExportOptions,
+) -> Result<(), ExportError> {
+    if !scene.has_geometry() {
+        return Err(ExportError::NoGeometry);
+    }
+
+    write_file(path.as_ref(), |out| write_stl(out, scene, options))
+}
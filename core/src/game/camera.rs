@@ -1,17 +1,30 @@
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat4, Quat, Vec2, Vec3};
 
-use crate::game::Transform;
+use crate::game::{Aabb, Transform};
+use crate::graphics::depth::DepthMode;
 
 #[derive(Default)]
 pub struct Frustum {
     pub near: f32,
     pub far: f32,
     pub fov: f32,
+    /// World units from the bottom to the top of an orthographic view.
+    pub ortho_height: f32,
+    pub depth: DepthMode,
+    /// Leave out the far plane of perspective views, so nothing is cut off
+    /// however far away. Best with [`DepthMode::Reversed`].
+    pub infinite_far: bool,
 }
 
 impl Frustum {
     fn new(fov: f32, near: f32, far: f32) -> Self {
-        Self { near, far, fov }
+        Self {
+            near,
+            far,
+            fov,
+            ortho_height: 5.0,
+            ..Default::default()
+        }
     }
 
     pub fn zoom(&mut self, yoffset: f32) {
@@ -25,12 +38,47 @@ pub enum Projection {
     Orthographic(f32),
 }
 
+/// How mouse movement steers a [`Camera`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Turn on the spot, like a first person game.
+    #[default]
+    Fps,
+    /// Turn around [`Camera::target`] and pan and dolly towards it.
+    Orbit,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 2] = [CameraMode::Fps, CameraMode::Orbit];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraMode::Fps => "FPS",
+            CameraMode::Orbit => "Orbit",
+        }
+    }
+}
+
+/// Degrees the orbit controls turn for each pixel the mouse is dragged.
+const ORBIT_SENSITIVITY: f32 = 0.3;
+
+/// Closest the orbit controls dolly to the target.
+const MIN_ORBIT_DISTANCE: f32 = 1e-3;
+
+/// Smallest near to far ratio [`Camera::clip_to_bounds`] goes down to. Reverse-Z
+/// keeps its precision with a far smaller one.
+const MIN_NEAR_RATIO: f32 = 1e-3;
+const MIN_NEAR_RATIO_REVERSED: f32 = 1e-6;
+
 #[derive(Default)]
 pub struct Camera {
     pub frustum: Frustum,
     pub transform: Transform,
     pub pitch: f32,
     pub yaw: f32,
+    pub mode: CameraMode,
+    /// The point orbiting turns around, kept in front of the camera.
+    pub target: Vec3,
     sensitivity: f32,
     constrain_pitch: f32,
 }
@@ -54,7 +102,7 @@ impl Camera {
         self.pitch -= yoffset * self.sensitivity;
 
         // Constrain and normalize angles
-        self.yaw = self.yaw % 360.0;
+        self.yaw %= 360.0;
         self.pitch = self
             .pitch
             .clamp(-self.constrain_pitch, self.constrain_pitch);
@@ -77,6 +125,175 @@ impl Camera {
             .normalize();
     }
 
+    /// Switch to `mode`. Orbiting starts around the point in front of the
+    /// camera as far away as the last target, so the view doesn't jump.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == CameraMode::Orbit && self.mode != mode {
+            let distance = self.orbit_distance().max(MIN_ORBIT_DISTANCE);
+            self.target = self.transform.position + self.forward() * distance;
+        }
+        self.mode = mode;
+    }
+
+    /// Where the camera looks, as a unit vector.
+    pub fn forward(&self) -> Vec3 {
+        self.orientation() * Vec3::NEG_Z
+    }
+
+    /// The camera's right, as a unit vector in the world.
+    pub fn right(&self) -> Vec3 {
+        self.orientation() * Vec3::X
+    }
+
+    /// The camera's up, as a unit vector in the world.
+    pub fn up(&self) -> Vec3 {
+        self.orientation() * Vec3::Y
+    }
+
+    pub fn orbit_distance(&self) -> f32 {
+        self.transform.position.distance(self.target)
+    }
+
+    /// Turn around the target by a mouse drag of `xoffset`, `yoffset` pixels.
+    pub fn orbit(&mut self, xoffset: f32, yoffset: f32) {
+        self.orbit_degrees(xoffset * ORBIT_SENSITIVITY, yoffset * ORBIT_SENSITIVITY);
+    }
+
+    /// Move `yaw` degrees to the left around the target and `pitch` degrees up.
+    pub fn orbit_degrees(&mut self, yaw: f32, pitch: f32) {
+        let distance = self.orbit_distance();
+
+        self.yaw += yaw;
+        self.pitch += pitch;
+        self.yaw %= 360.0;
+        self.pitch = self
+            .pitch
+            .clamp(-self.constrain_pitch, self.constrain_pitch);
+        self.update_local_vectors();
+
+        self.transform.position = self.target - self.forward() * distance;
+    }
+
+    /// Slide the camera and target sideways so a point at the target's depth
+    /// follows a mouse drag of `xoffset`, `yoffset` pixels, each `pixel_size`
+    /// world units across there.
+    pub fn pan(&mut self, xoffset: f32, yoffset: f32, pixel_size: f32) {
+        let offset = (self.up() * yoffset - self.right() * xoffset) * pixel_size;
+        self.target += offset;
+        self.transform.position += offset;
+    }
+
+    /// World units from the bottom to the top of the perspective view at the
+    /// target's distance. An orthographic view this tall shows the target the same size.
+    pub fn focus_height(&self) -> f32 {
+        2.0 * self.orbit_distance() * (self.frustum.fov * 0.5).to_radians().tan()
+    }
+
+    /// Shrink the orthographic view for positive `amount`, grow it for negative,
+    /// keeping what's under `cursor` there. `cursor` is in normalized device
+    /// coordinates of a view of `aspect`.
+    pub fn zoom_ortho(&mut self, amount: f32, cursor: Vec2, aspect: f32) {
+        let height = self.frustum.ortho_height;
+        let zoomed = (height * 0.9f32.powf(amount)).clamp(1e-4, 1e5);
+
+        let offset =
+            (self.right() * cursor.x * aspect + self.up() * cursor.y) * (height - zoomed) * 0.5;
+        self.transform.position += offset;
+        self.target += offset;
+        self.frustum.ortho_height = zoomed;
+    }
+
+    /// Move towards the target, or away for negative `amount`. Each step
+    /// covers a tenth of the distance left, so the target is never reached.
+    pub fn dolly(&mut self, amount: f32) {
+        let distance = (self.orbit_distance() * 0.9f32.powf(amount)).max(MIN_ORBIT_DISTANCE);
+        self.transform.position = self.target - self.forward() * distance;
+    }
+
+    /// Back away from `bounds` along the view direction until it fills a view
+    /// of `aspect`, and orbit around its center. The camera keeps its heading.
+    pub fn fit(&mut self, bounds: &Aabb, aspect: f32) {
+        let center = bounds.center();
+        let radius = bounds.radius().max(1e-3);
+
+        self.target = center;
+        self.transform.position = center - self.forward() * self.fit_distance(radius, aspect);
+        self.fit_clip_planes(center, radius);
+        self.fit_ortho_height(radius, aspect);
+    }
+
+    /// Make an orthographic view of `aspect` fit a sphere of `radius`, with the
+    /// same margin the perspective one gets.
+    fn fit_ortho_height(&mut self, radius: f32, aspect: f32) {
+        self.frustum.ortho_height = radius * 2.2 / aspect.min(1.0);
+    }
+
+    /// How far from a sphere of `radius` it fills the narrower of the two
+    /// fields of view of a view of `aspect`.
+    fn fit_distance(&self, radius: f32, aspect: f32) -> f32 {
+        let half_fov_y = (self.frustum.fov * 0.5).to_radians();
+        let half_fov_x = (half_fov_y.tan() * aspect).atan();
+        radius / half_fov_y.min(half_fov_x).sin() * 1.1
+    }
+
+    fn fit_clip_planes(&mut self, center: Vec3, radius: f32) {
+        let reach = (self.transform.position - center).length() + radius;
+        self.frustum.near = (radius * 0.01).clamp(0.001, 0.1);
+        self.frustum.far = (reach * 2.0).max(100.0);
+    }
+
+    /// Pull the clip planes in as close around `bounds` as they go without
+    /// cutting into it, for the best depth precision. Perspective views keep
+    /// the near plane in front of the camera.
+    pub fn clip_to_bounds(&mut self, bounds: &Aabb, orthographic: bool) {
+        let radius = bounds.radius().max(1e-3);
+        let distance = (bounds.center() - self.transform.position).dot(self.forward());
+        let far = (distance + radius * 1.01).max(radius * 0.01);
+        let mut near = distance - radius * 1.01;
+
+        if !orthographic {
+            let ratio = match self.frustum.depth {
+                DepthMode::Standard => MIN_NEAR_RATIO,
+                DepthMode::Reversed => MIN_NEAR_RATIO_REVERSED,
+            };
+            near = near.max(far * ratio);
+        }
+        self.frustum.near = near;
+        self.frustum.far = far;
+    }
+
+    /// Point the camera at `target` from its current position.
+    pub fn look_at(&mut self, target: Vec3) {
+        let dir = (target - self.transform.position).normalize_or_zero();
+        if dir == Vec3::ZERO {
+            return;
+        }
+
+        self.pitch = (-dir.y)
+            .asin()
+            .to_degrees()
+            .clamp(-self.constrain_pitch, self.constrain_pitch);
+        self.yaw = dir.x.atan2(-dir.z).to_degrees();
+        self.update_local_vectors();
+    }
+
+    /// Move the camera so `bounds` fills a view of `aspect`, or to `position` looking
+    /// at its center. The clip planes are fitted around the bounds as well.
+    pub fn frame(&mut self, bounds: &Aabb, aspect: f32, position: Option<Vec3>) {
+        let center = bounds.center();
+        let radius = bounds.radius().max(1e-3);
+
+        let distance = self.fit_distance(radius, aspect);
+        self.transform.position = position.unwrap_or(center + Vec3::new(0.0, 0.0, distance));
+        self.pitch = 0.0;
+        self.yaw = 0.0;
+        self.look_at(center);
+        self.target = center;
+
+        self.fit_clip_planes(center, radius);
+        self.fit_ortho_height(radius, aspect);
+    }
+
     fn angle_front(&self) -> Quat {
         Quat::from_axis_angle(Vec3::X, self.pitch.to_radians())
     }
@@ -85,6 +302,11 @@ impl Camera {
         Quat::from_axis_angle(Vec3::Y, self.yaw.to_radians())
     }
 
+    /// Turns camera directions into world directions.
+    fn orientation(&self) -> Quat {
+        (self.angle_front() * self.angle_up()).inverse()
+    }
+
     pub fn get_camera_rotation_matrix(&self) -> Mat4 {
         Mat4::from_quat(self.angle_front() * self.angle_up())
     }
@@ -97,28 +319,27 @@ impl Camera {
         self.transform.get_position_matrix() * self.get_camera_rotation_matrix()
     }
 
+    /// The projection for drawing in [`Frustum::depth`]. Reversed depth puts
+    /// the near plane at 1 and the far plane, if any, at 0.
     pub fn get_camera_projection_matrix(&self, projection: Projection) -> Mat4 {
-        let fov = self.frustum.fov;
+        let Frustum { near, far, fov, .. } = self.frustum;
+        let reversed = self.frustum.depth == DepthMode::Reversed;
+        let infinite = self.frustum.infinite_far;
+        let fov = fov.to_radians();
 
         match projection {
-            Projection::Perspective(aspect) => Mat4::perspective_rh(
-                fov.to_radians(),
-                aspect,
-                self.frustum.near,
-                self.frustum.far,
-            ),
+            Projection::Perspective(aspect) => match (reversed, infinite) {
+                (false, false) => Mat4::perspective_rh(fov, aspect, near, far),
+                (false, true) => Mat4::perspective_infinite_rh(fov, aspect, near),
+                (true, false) => Mat4::perspective_rh(fov, aspect, far, near),
+                (true, true) => Mat4::perspective_infinite_reverse_rh(fov, aspect, near),
+            },
             Projection::Orthographic(aspect) => {
-                let vertical = fov / 16.0;
+                let vertical = self.frustum.ortho_height * 0.5;
                 let horizontal = vertical * aspect;
+                let (near, far) = if reversed { (far, near) } else { (near, far) };
 
-                Mat4::orthographic_rh(
-                    -horizontal,
-                    horizontal,
-                    -vertical,
-                    vertical,
-                    self.frustum.near,
-                    self.frustum.far,
-                )
+                Mat4::orthographic_rh(-horizontal, horizontal, -vertical, vertical, near, far)
             }
         }
     }
@@ -0,0 +1,196 @@
+//! Moving the camera on its own: easing between viewpoints and turntables.
+
+use crate::game::Camera;
+use crate::scene::CameraDesc;
+
+/// Seconds a [`CameraTransition`] takes unless told otherwise.
+pub const TRANSITION_TIME: f32 = 0.6;
+
+/// Slow at both ends, `t` and the result run from 0 to 1.
+pub fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+/// Narrowest field of view, in degrees, a switch to or from orthographic goes
+/// through before it snaps.
+const MIN_SWITCH_FOV: f32 = 0.5;
+
+/// The view `t` of the way from `from` to `to`, turning around their focus
+/// points. Yaw takes the short way around and the view's size at the focus
+/// scales evenly.
+///
+/// A switch to or from orthographic narrows or widens the field of view while
+/// backing away or coming closer, like a dolly zoom, so the focus stays the
+/// same size.
+pub fn interpolate(from: &CameraDesc, to: &CameraDesc, t: f32) -> CameraDesc {
+    if t <= 0.0 {
+        return from.clone();
+    }
+    if t >= 1.0 {
+        return to.clone();
+    }
+
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    // Sizes scale evenly; anything not positive, which no size should be, goes linearly
+    let scale = |a: f32, b: f32| {
+        if a > 0.0 && b > 0.0 {
+            a * (b / a).powf(t)
+        } else {
+            lerp(a, b)
+        }
+    };
+    let yaw_change = (to.yaw - from.yaw + 180.0).rem_euclid(360.0) - 180.0;
+
+    let focus = from.focus().lerp(to.focus(), t);
+    let focus_distance = scale(from.focus_distance(), to.focus_distance());
+    let height = scale(from.view_height(), to.view_height());
+
+    let mut desc = CameraDesc {
+        position: focus,
+        yaw: from.yaw + yaw_change * t,
+        pitch: lerp(from.pitch, to.pitch),
+        fov: lerp(from.fov, to.fov),
+        near: scale(from.near, to.near),
+        far: scale(from.far, to.far),
+        orthographic: from.orthographic && to.orthographic,
+        ortho_height: height,
+        target: focus,
+    };
+
+    if desc.orthographic {
+        desc.position = focus - desc.forward() * focus_distance;
+        return desc;
+    }
+
+    // Orthographic ends count as a field of view of 0
+    if from.orthographic || to.orthographic {
+        let lens = |d: &CameraDesc| if d.orthographic { 0.0 } else { d.fov };
+        desc.fov = lerp(lens(from), lens(to)).max(MIN_SWITCH_FOV);
+    }
+    let distance = height * 0.5 / (desc.fov * 0.5).to_radians().tan();
+    desc.position = focus - desc.forward() * distance;
+
+    // Keep the scene between the clip planes while backed away
+    let extra = distance / focus_distance;
+    if extra > 1.0 {
+        desc.near *= extra;
+        desc.far += distance - focus_distance;
+    }
+    desc
+}
+
+/// An eased move from one view to another.
+pub struct CameraTransition {
+    from: CameraDesc,
+    to: CameraDesc,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl CameraTransition {
+    pub fn new(from: CameraDesc, to: CameraDesc, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.0,
+            duration: duration.max(1e-3),
+        }
+    }
+
+    /// Move `dt` seconds further and return where the camera is now.
+    pub fn update(&mut self, dt: f32) -> CameraDesc {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        interpolate(
+            &self.from,
+            &self.to,
+            ease_in_out(self.elapsed / self.duration),
+        )
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// Spins the camera around its target, like a model on a turntable.
+pub struct Turntable {
+    /// Degrees per second, negative turns the other way.
+    pub speed: f32,
+    pub playing: bool,
+}
+
+impl Default for Turntable {
+    fn default() -> Self {
+        Self {
+            speed: 30.0,
+            playing: false,
+        }
+    }
+}
+
+impl Turntable {
+    /// Turn `camera` as far as it goes in `dt` seconds, if playing.
+    pub fn update(&self, camera: &mut Camera, dt: f32) {
+        if self.playing {
+            camera.orbit_degrees(self.speed * dt, 0.0);
+        }
+    }
+
+    /// Turn `camera` one frame of a full turn taking `frames` frames, playing
+    /// or not. Captured sequences step this way, whatever the frame rate.
+    pub fn step(&self, camera: &mut Camera, frames: u32) {
+        let turn = 360.0 / frames.max(1) as f32;
+        camera.orbit_degrees(turn.copysign(self.speed), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    fn view(yaw: f32, orthographic: bool) -> CameraDesc {
+        CameraDesc {
+            orthographic,
+            ..CameraDesc::default()
+        }
+        .looking_from(yaw, 0.0)
+    }
+
+    #[test]
+    fn ends_are_the_views() {
+        let (from, to) = (view(0.0, false), view(90.0, true));
+        assert_eq!(interpolate(&from, &to, 0.0).yaw, from.yaw);
+        assert_eq!(interpolate(&from, &to, 1.0).position, to.position);
+        assert!(interpolate(&from, &to, 1.0).orthographic);
+    }
+
+    #[test]
+    fn yaw_takes_the_short_way() {
+        let half = interpolate(&view(170.0, false), &view(-170.0, false), 0.5);
+        assert!((half.yaw.rem_euclid(360.0) - 180.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn focus_stays_put_while_turning() {
+        let (from, to) = (view(0.0, false), view(90.0, false));
+        let half = interpolate(&from, &to, 0.5);
+        assert!(half.focus().abs_diff_eq(from.focus(), 1e-4));
+        assert!((half.position.distance(half.focus()) - from.focus_distance()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bad_clip_planes_stay_finite() {
+        let mut from = view(0.0, true);
+        from.near = -2.0;
+        let half = interpolate(&from, &view(0.0, true), 0.5);
+        assert!(half.near.is_finite() && half.far.is_finite());
+        assert_eq!(half.position, Vec3::new(0.0, 0.0, 5.0));
+    }
+}
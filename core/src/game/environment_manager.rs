@@ -0,0 +1,146 @@
+use glam::{Mat3, Mat4};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use crate::game::Skybox;
+use crate::graphics::depth::DepthMode;
+use crate::graphics::environment::PREFILTERED_LEVELS;
+use crate::graphics::material::{BRDF_LUT_UNIT, IRRADIANCE_UNIT, PREFILTERED_UNIT};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::texture::{CubemapLayout, FilterMode};
+use crate::graphics::{Environment, GlRef, Shader, TextureRef};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::{TextureBuilder, new_texture_ref};
+
+/// The HDR environment around the scene.
+///
+/// It's drawn behind everything as a skybox, unless a background cubemap
+/// replaces it there, and lights metallic-roughness materials in place of the
+/// lights' ambient term. Phong materials keep using the ambient term.
+pub struct EnvironmentManager {
+    gl: GlRef,
+    /// Brightness of the environment in stops, 0 leaves the image as it is.
+    pub exposure: f32,
+    /// Turn of the environment around the world's Y axis, in degrees.
+    pub rotation: f32,
+    /// Draw the environment behind the scene.
+    pub show_skybox: bool,
+    /// Light metallic-roughness materials with the environment.
+    pub lighting: bool,
+    /// An LDR cubemap shown instead of the environment. It doesn't light anything.
+    pub background: Option<TextureRef>,
+    environment: Option<Environment>,
+    skybox: Skybox,
+}
+
+impl EnvironmentManager {
+    pub fn new(gl: GlRef) -> Result<Self, String> {
+        Ok(Self {
+            skybox: Skybox::new(gl.clone())?,
+            gl,
+            exposure: 0.0,
+            rotation: 0.0,
+            show_skybox: true,
+            lighting: true,
+            background: None,
+            environment: None,
+        })
+    }
+
+    /// The skybox's shader. The ones that bake environments are only used while loading.
+    pub fn shaders(&self) -> [&Shader; 1] {
+        [self.skybox.shader()]
+    }
+
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    /// Replace the environment, `None` goes back to the clear color and the lights' ambient term.
+    pub fn set_environment(&mut self, environment: Option<Environment>) {
+        self.environment = environment;
+    }
+
+    /// Load an equirectangular `.hdr` in place of the current environment,
+    /// which is kept if it fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        self.environment = Some(Environment::load_file(self.gl.clone(), path)?);
+        Ok(())
+    }
+
+    /// Like [`EnvironmentManager::load_file`], from embedded bytes.
+    pub fn load_bytes(&mut self, data: &[u8]) -> Result<(), String> {
+        self.environment = Some(Environment::load_bytes(self.gl.clone(), data)?);
+        Ok(())
+    }
+
+    /// Load a cubemap for [`EnvironmentManager::background`] from a directory of
+    /// faces, a cross or an equirectangular image, see [`CubemapLayout::from_path`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_background(&mut self, path: &Path) -> Result<(), String> {
+        let texture = TextureBuilder::new(self.gl.clone())
+            .filter(FilterMode::Linear)
+            .load_cubemap(&CubemapLayout::from_path(path)?)?;
+        self.background = Some(new_texture_ref(texture));
+        Ok(())
+    }
+
+    /// Turns world directions into directions in the environment image.
+    fn rotation_matrix(&self) -> Mat3 {
+        Mat3::from_rotation_y(-self.rotation.to_radians())
+    }
+
+    fn intensity(&self) -> f32 {
+        self.exposure.exp2()
+    }
+
+    /// Draw the background, or the environment if there's none, behind the
+    /// opaque objects drawn so far. See [`Skybox::draw`].
+    pub fn draw_skybox(&self, view: &Mat4, projection: &Mat4, depth: DepthMode) {
+        if !self.show_skybox {
+            return;
+        }
+
+        let (cubemap, srgb) = match (&self.background, &self.environment) {
+            (Some(background), _) => (&**background, true),
+            (None, Some(environment)) => (environment.cubemap(), false),
+            (None, None) => return,
+        };
+
+        self.skybox.draw(
+            cubemap,
+            view,
+            projection,
+            &self.rotation_matrix(),
+            self.intensity(),
+            srgb,
+            depth,
+        );
+    }
+
+    /// Bind the lighting maps and tell `shader`, which has to be bound, whether
+    /// to use them.
+    pub fn apply(&self, shader: &Shader) {
+        // Phong shaders keep to the ambient term
+        if !shader.has_uniform("u_ibl") {
+            return;
+        }
+
+        // Cube samplers can't share a unit with the 2D ones, even when they're unused
+        shader.setUniform1i("u_irradiance_map", IRRADIANCE_UNIT);
+        shader.setUniform1i("u_prefiltered_map", PREFILTERED_UNIT);
+        shader.setUniform1i("u_brdf_lut", BRDF_LUT_UNIT);
+
+        let environment = self.environment.as_ref().filter(|_| self.lighting);
+        shader.setUniform1i("u_ibl", environment.is_some() as i32);
+        let Some(environment) = environment else {
+            return;
+        };
+
+        environment.bind_lighting(IRRADIANCE_UNIT, PREFILTERED_UNIT, BRDF_LUT_UNIT);
+        shader.setUniform1f("u_prefiltered_lod", (PREFILTERED_LEVELS - 1) as f32);
+        shader.setUniform3fm("u_environment_rotation", &self.rotation_matrix());
+        shader.setUniform1f("u_environment_intensity", self.intensity());
+    }
+}
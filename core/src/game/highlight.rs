@@ -0,0 +1,90 @@
+use glam::{Mat4, Vec4};
+use glow::HasContext;
+
+use crate::game::{NodeId, SceneGraph};
+use crate::gl_check_error;
+use crate::graphics::depth::DepthMode;
+use crate::graphics::{
+    GlRef, HIGHLIGHT_FRAG_PATH, HIGHLIGHT_FRAG_SRC, HIGHLIGHT_VERT_PATH, HIGHLIGHT_VERT_SRC, Shader,
+};
+
+/// Tints the selected node and everything below it.
+///
+/// The tint is drawn over the finished frame, depth tested against it and
+/// pulled a little toward the camera, so only the visible surfaces light up.
+pub struct Highlight {
+    gl: GlRef,
+    shader: Shader,
+    /// What gets tinted, nothing when `None`.
+    pub node: Option<NodeId>,
+    /// Color of the tint, with how strongly it's blended in as alpha.
+    pub color: Vec4,
+}
+
+impl Highlight {
+    pub fn new(gl: GlRef) -> Result<Self, String> {
+        let mut shader = Shader::new(gl.clone());
+        shader.add(glow::VERTEX_SHADER, HIGHLIGHT_VERT_SRC, HIGHLIGHT_VERT_PATH)?;
+        shader.add(
+            glow::FRAGMENT_SHADER,
+            HIGHLIGHT_FRAG_SRC,
+            HIGHLIGHT_FRAG_PATH,
+        )?;
+        shader.link()?;
+
+        Ok(Self {
+            gl,
+            shader,
+            node: None,
+            color: Vec4::new(1.0, 0.6, 0.1, 0.35),
+        })
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// Tint the renderables at and below [`Highlight::node`]. `model` is
+    /// projection times view.
+    pub fn draw(&self, graph: &SceneGraph, model: &Mat4, depth: DepthMode) {
+        let Some(id) = self.node.filter(|id| graph.contains(*id)) else {
+            return;
+        };
+
+        unsafe {
+            self.gl.enable(glow::BLEND);
+            // The tint's alpha says how much to blend, the image's alpha stays
+            self.gl.blend_func_separate(
+                glow::SRC_ALPHA,
+                glow::ONE_MINUS_SRC_ALPHA,
+                glow::ZERO,
+                glow::ONE,
+            );
+            self.gl.depth_func(depth.nearer_or_equal());
+            self.gl.depth_mask(false);
+            self.gl.enable(glow::POLYGON_OFFSET_FILL);
+            let offset = depth.toward_viewer();
+            self.gl.polygon_offset(offset, offset);
+
+            self.shader.bind();
+            self.shader.setUniform4fm("pv", model);
+            self.shader.setUniform4fv("u_color", &self.color);
+
+            for (id, node) in graph.subtree(id) {
+                let Some(renderable) = &node.renderable else {
+                    continue;
+                };
+                let obj = renderable.borrow();
+                self.shader
+                    .setUniform4fm("model", &(graph.world_matrix(id) * obj.model_matrix()));
+                obj.mesh().draw(&self.gl);
+            }
+
+            self.gl.disable(glow::POLYGON_OFFSET_FILL);
+            self.gl.depth_mask(true);
+            self.gl.depth_func(depth.nearer());
+            self.gl.disable(glow::BLEND);
+            gl_check_error!(self.gl);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use glam::Vec3;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::graphics::Shader;
+use crate::graphics::types::LightObjectRef;
+
+/// Lights a shader takes unless [`set_max_lights`] says otherwise.
+pub const DEFAULT_MAX_LIGHTS: usize = 8;
+
+/// Upper bound for [`set_max_lights`]. Each light costs about nine uniform
+/// vectors, and WebGL2 only promises 224 of them to a fragment shader.
+pub const MAX_LIGHTS_LIMIT: usize = 16;
+
+static MAX_LIGHTS: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_LIGHTS);
+
+/// Size of the light array in shaders built from now on, clamped to
+/// 1..=[`MAX_LIGHTS_LIMIT`]. Set it before any shader is created.
+pub fn set_max_lights(max: usize) {
+    MAX_LIGHTS.store(max.clamp(1, MAX_LIGHTS_LIMIT), Ordering::Relaxed);
+}
+
+/// Most lights a shader takes; any further lights are ignored when drawing.
+pub fn max_lights() -> usize {
+    MAX_LIGHTS.load(Ordering::Relaxed)
+}
+
+/// Names of the uniforms of one `u_lights` entry, built once instead of on
+/// every draw.
+pub(crate) struct LightUniforms {
+    pub kind: String,
+    pub position: String,
+    pub direction: String,
+    pub color: String,
+    pub intensity: String,
+    pub ambient: String,
+    pub specular: String,
+    pub attenuation: String,
+    pub inner_cone: String,
+    pub outer_cone: String,
+    pub shadow: String,
+    pub shadow_bias: String,
+}
+
+impl LightUniforms {
+    fn new(index: usize) -> Self {
+        let uniform = |field: &str| format!("u_lights[{}].{}", index, field);
+        Self {
+            kind: uniform("kind"),
+            position: uniform("position"),
+            direction: uniform("direction"),
+            color: uniform("color"),
+            intensity: uniform("intensity"),
+            ambient: uniform("ambient"),
+            specular: uniform("specular"),
+            attenuation: uniform("attenuation"),
+            inner_cone: uniform("inner_cone"),
+            outer_cone: uniform("outer_cone"),
+            shadow: uniform("shadow"),
+            shadow_bias: uniform("shadow_bias"),
+        }
+    }
+}
+
+/// The uniform names of light `index`, below [`MAX_LIGHTS_LIMIT`].
+pub(crate) fn light_uniforms(index: usize) -> &'static LightUniforms {
+    static NAMES: OnceLock<Vec<LightUniforms>> = OnceLock::new();
+    &NAMES.get_or_init(|| (0..MAX_LIGHTS_LIMIT).map(LightUniforms::new).collect())[index]
+}
+
+/// Matches the `LIGHT_*` constants in `loaded_obj.frag`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LightKind {
+    /// Light from far away, e.g. the sun. Only the direction matters.
+    Directional = 0,
+    /// Shines in every direction from its position.
+    #[default]
+    Point = 1,
+    /// A cone of light from its position along its direction.
+    Spot = 2,
+}
+
+impl LightKind {
+    pub const ALL: [LightKind; 3] = [LightKind::Directional, LightKind::Point, LightKind::Spot];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightKind::Directional => "Directional",
+            LightKind::Point => "Point",
+            LightKind::Spot => "Spot",
+        }
+    }
+}
+
+/// How a light shines. Its position comes from the object carrying it.
+#[derive(Clone, Debug)]
+pub struct LightSource {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Share of the light that reaches everything, regardless of direction.
+    pub ambient: f32,
+    /// Strength of the highlights.
+    pub specular: f32,
+    /// The way directional and spot lights shine.
+    pub direction: Vec3,
+    /// Constant, linear and quadratic falloff over distance, for point and spot lights.
+    pub attenuation: Vec3,
+    /// Angle in degrees up to which a spot light is at full strength.
+    pub inner_cone: f32,
+    /// Angle in degrees beyond which a spot light is dark.
+    pub outer_cone: f32,
+    /// Whether directional and spot lights get a shadow map. Point lights never do.
+    pub cast_shadows: bool,
+    /// Depth offset against shadow acne, in shadow map depth units.
+    pub shadow_bias: f32,
+    /// Width and height of the shadow map in texels.
+    pub shadow_resolution: u32,
+}
+
+impl Default for LightSource {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            color: Vec3::ONE,
+            intensity: 1.0,
+            ambient: 0.2,
+            specular: 0.5,
+            direction: Vec3::NEG_Y,
+            attenuation: Vec3::new(1.0, 0.0, 0.0),
+            inner_cone: 20.0,
+            outer_cone: 30.0,
+            cast_shadows: true,
+            shadow_bias: 0.002,
+            shadow_resolution: 1024,
+        }
+    }
+}
+
+/// The lights of a scene, uploaded to every shader that is drawn.
+#[derive(Default)]
+pub struct LightManager {
+    pub lights: Vec<LightObjectRef>,
+    warned: bool,
+}
+
+impl LightManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_light(&mut self, light: LightObjectRef) {
+        self.lights.push(light);
+        self.warned = false;
+    }
+
+    pub fn remove_light(&mut self, light: &LightObjectRef) {
+        self.lights.retain(|l| !Rc::ptr_eq(l, light));
+    }
+
+    pub fn clear(&mut self) {
+        self.lights.clear();
+    }
+
+    /// Set the `u_lights` array and `u_light_count` of `shader`, which has to be bound.
+    pub fn apply(&mut self, shader: &Shader) {
+        // Unlit shaders, like the lamp's
+        if !shader.has_uniform("u_light_count") {
+            return;
+        }
+
+        let max = max_lights();
+        if self.lights.len() > max && !self.warned {
+            warn!(
+                "{} lights in the scene, only the first {} are drawn",
+                self.lights.len(),
+                max
+            );
+            self.warned = true;
+        }
+
+        let count = self.lights.len().min(max);
+        shader.setUniform1i("u_light_count", count as i32);
+
+        for (index, light) in self.lights.iter().take(count).enumerate() {
+            let light = light.borrow();
+            let source = light.source();
+            let uniform = light_uniforms(index);
+
+            shader.setUniform1i(&uniform.kind, source.kind as i32);
+            shader.setUniform3fv(&uniform.position, &light.transform().position);
+            shader.setUniform3fv(
+                &uniform.direction,
+                &source.direction.normalize_or(Vec3::NEG_Y),
+            );
+            shader.setUniform3fv(&uniform.color, &source.color);
+            shader.setUniform1f(&uniform.intensity, source.intensity);
+            shader.setUniform1f(&uniform.ambient, source.ambient);
+            // Metallic-roughness shaders work out their own highlights
+            if shader.has_uniform(&uniform.specular) {
+                shader.setUniform1f(&uniform.specular, source.specular);
+            }
+            shader.setUniform3fv(&uniform.attenuation, &source.attenuation);
+            // The shader compares against the cosine of the angle to the axis
+            shader.setUniform1f(&uniform.inner_cone, source.inner_cone.to_radians().cos());
+            shader.setUniform1f(&uniform.outer_cone, source.outer_cone.to_radians().cos());
+        }
+    }
+}
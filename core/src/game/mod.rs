@@ -1,34 +1,66 @@
+pub mod bounds;
 pub mod camera;
+pub mod camera_animation;
+pub mod environment_manager;
+pub mod highlight;
+pub mod light_manager;
 pub mod physics;
 pub mod physics_manager;
+pub mod picking;
 pub mod render;
 pub mod render_manager;
+pub mod scene_graph;
+pub mod shadow_manager;
+pub mod skybox;
 pub mod transform;
 
+pub use bounds::Aabb;
 pub use camera::Camera;
+pub use camera::CameraMode;
 pub use camera::Frustum;
 pub use camera::Projection;
+pub use camera_animation::{CameraTransition, Turntable};
+pub use environment_manager::EnvironmentManager;
+pub use highlight::Highlight;
+pub use light_manager::{LightKind, LightManager, LightSource};
 pub use physics::Physical;
 pub use physics_manager::PhysicsManager;
+pub use picking::{Hit, Ray};
 pub use render::Renderable;
 pub use render_manager::RenderManager;
+pub use scene_graph::{NodeId, SceneGraph, SceneNode};
+pub use shadow_manager::ShadowManager;
+pub use skybox::Skybox;
 pub use transform::Transform;
 
 // Re-export type aliases from graphics for convenience
 pub use crate::graphics::{GlRef, PhysicalRef, RenderableRef};
 
-pub trait GameObject: Renderable + Physical {}
+pub trait GameObject: Renderable + Physical {
+    /// Shown in the UI and used for exported object names.
+    fn name(&self) -> &str {
+        "object"
+    }
+}
 
 pub trait GlobalLight: Renderable + Physical {
+    fn source(&self) -> &LightSource;
+
+    fn source_mut(&mut self) -> &mut LightSource;
+
     fn ambient(&self) -> f32 {
-        1.0
+        self.source().ambient
     }
 
     fn specular(&self) -> f32 {
-        0.0
+        self.source().specular
     }
 
-    fn ambient_mut(&mut self) -> &mut f32;
+    fn ambient_mut(&mut self) -> &mut f32 {
+        &mut self.source_mut().ambient
+    }
 
-    fn specular_mut(&mut self) -> &mut f32;
+    fn specular_mut(&mut self) -> &mut f32 {
+        &mut self.source_mut().specular
+    }
 }
@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use crate::graphics::PhysicalRef;
 
 pub struct PhysicsManager {
@@ -15,6 +17,10 @@ impl PhysicsManager {
         self.physical_targets.push(physical);
     }
 
+    pub fn remove_physical(&mut self, physical: &PhysicalRef) {
+        self.physical_targets.retain(|p| !Rc::ptr_eq(p, physical));
+    }
+
     pub fn update(&mut self, dt: f32) {
         for physical in &self.physical_targets {
             physical.borrow_mut().update(dt);
@@ -25,3 +31,9 @@ impl PhysicsManager {
     // pub fn check_collisions(&self) -> Vec<CollisionEvent> { ... }
     // pub fn resolve_collisions(&mut self, events: &[CollisionEvent]) { ... }
 }
+
+impl Default for PhysicsManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
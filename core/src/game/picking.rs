@@ -0,0 +1,222 @@
+//! Finding what's under the cursor by casting a ray into the scene graph.
+
+use glam::{Mat4, Vec3};
+
+use crate::game::{Aabb, NodeId, SceneGraph};
+
+/// A half line from `origin` along `direction`. Distances along it are in
+/// lengths of `direction`, which needn't be a unit vector.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// The same ray in the space `matrix` maps to. Distances along it stay
+    /// the same, since the direction is transformed with it.
+    pub fn transformed(&self, matrix: &Mat4) -> Ray {
+        Ray {
+            origin: matrix.transform_point3(self.origin),
+            direction: matrix.transform_vector3(self.direction),
+        }
+    }
+
+    /// Distance to where the ray enters `bounds`, 0 when it starts inside.
+    pub fn intersect_aabb(&self, bounds: &Aabb) -> Option<f32> {
+        // Slabs: the ray is inside the box where it's between all three pairs of planes
+        let inverse = self.direction.recip();
+        let a = (bounds.min - self.origin) * inverse;
+        let b = (bounds.max - self.origin) * inverse;
+        let enter = a.min(b).max_element().max(0.0);
+        let exit = a.max(b).min_element();
+
+        (enter <= exit).then_some(enter)
+    }
+
+    /// Distance to where the ray crosses the triangle `a`, `b`, `c` from either
+    /// side, by Möller and Trumbore's method.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        // Parallel to the triangle's plane, or the triangle has no area
+        if determinant.abs() < f32::EPSILON * edge1.length() * edge2.length() {
+            return None;
+        }
+
+        let inverse = 1.0 / determinant;
+        let s = self.origin - a;
+        let u = s.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse;
+        (distance >= 0.0).then_some(distance)
+    }
+}
+
+/// Where a [`Ray`] first hit something.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    /// The node of the renderable that was hit.
+    pub node: NodeId,
+    pub distance: f32,
+    pub point: Vec3,
+}
+
+/// The nearest triangle `ray` hits among the renderables at and below `roots`.
+/// Renderables whose bounds the ray misses, or only hits beyond a nearer hit,
+/// aren't looked at any closer.
+pub fn pick(graph: &SceneGraph, roots: impl IntoIterator<Item = NodeId>, ray: &Ray) -> Option<Hit> {
+    let mut nearest: Option<Hit> = None;
+
+    for root in roots {
+        for (id, node) in graph.subtree(root) {
+            let Some(renderable) = &node.renderable else {
+                continue;
+            };
+            let renderable = renderable.borrow();
+            let mesh = renderable.mesh();
+            let matrix = graph.world_matrix(id) * renderable.model_matrix();
+            let Some(bounds) = mesh.bounds() else {
+                continue;
+            };
+
+            match ray.intersect_aabb(&bounds.transformed(&matrix)) {
+                Some(distance) if is_nearer(&nearest, distance) => {}
+                _ => continue,
+            }
+
+            // Test the triangles where the mesh is, rather than moving all of them
+            let local = ray.transformed(&matrix.inverse());
+            let position = |index: u32| mesh.vertices[index as usize].position;
+            let triangles: Box<dyn Iterator<Item = [u32; 3]>> = if mesh.indices.is_empty() {
+                Box::new((0..mesh.vertices.len() as u32 / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]))
+            } else {
+                Box::new(mesh.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]))
+            };
+
+            for [a, b, c] in triangles {
+                let Some(distance) =
+                    local.intersect_triangle(position(a), position(b), position(c))
+                else {
+                    continue;
+                };
+                if is_nearer(&nearest, distance) {
+                    nearest = Some(Hit {
+                        node: id,
+                        distance,
+                        point: ray.at(distance),
+                    });
+                }
+            }
+        }
+    }
+
+    nearest
+}
+
+fn is_nearer(nearest: &Option<Hit>, distance: f32) -> bool {
+    nearest.is_none_or(|hit| distance < hit.distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb {
+            min: Vec3::splat(-1.0),
+            max: Vec3::ONE,
+        }
+    }
+
+    #[test]
+    fn ray_enters_box() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(4.0));
+        assert_eq!(ray.at(4.0), Vec3::Z);
+    }
+
+    #[test]
+    fn ray_inside_box() {
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(0.0));
+    }
+
+    #[test]
+    fn ray_misses_box() {
+        // Beside it, and pointing away from it
+        let beside = Ray::new(Vec3::new(2.0, 0.0, 5.0), Vec3::NEG_Z);
+        let away = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::Z);
+        assert_eq!(beside.intersect_aabb(&unit_box()), None);
+        assert_eq!(away.intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn axis_parallel_ray_along_a_face() {
+        // Zero direction components divide to infinities, which the slabs handle
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::NEG_Z);
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(4.0));
+        let outside = Ray::new(Vec3::new(1.5, 0.5, 5.0), Vec3::NEG_Z);
+        assert_eq!(outside.intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn ray_hits_triangle_from_both_sides() {
+        let (a, b, c) = (Vec3::ZERO, Vec3::X, Vec3::Y);
+        let front = Ray::new(Vec3::new(0.25, 0.25, 2.0), Vec3::NEG_Z);
+        let back = Ray::new(Vec3::new(0.25, 0.25, -3.0), Vec3::Z);
+
+        let distance = front.intersect_triangle(a, b, c).unwrap();
+        assert!((distance - 2.0).abs() < 1e-6);
+        let distance = back.intersect_triangle(a, b, c).unwrap();
+        assert!((distance - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ray_misses_triangle() {
+        let (a, b, c) = (Vec3::ZERO, Vec3::X, Vec3::Y);
+        // Past the long edge, behind the ray and parallel to the plane
+        let outside = Ray::new(Vec3::new(0.75, 0.75, 2.0), Vec3::NEG_Z);
+        let behind = Ray::new(Vec3::new(0.25, 0.25, -2.0), Vec3::NEG_Z);
+        let parallel = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::X);
+
+        assert_eq!(outside.intersect_triangle(a, b, c), None);
+        assert_eq!(behind.intersect_triangle(a, b, c), None);
+        assert_eq!(parallel.intersect_triangle(a, b, c), None);
+        // A triangle without area can't be hit
+        assert_eq!(
+            Ray::new(Vec3::Z, Vec3::NEG_Z).intersect_triangle(a, a, a),
+            None
+        );
+    }
+
+    #[test]
+    fn transformed_ray_keeps_distances() {
+        let matrix = Mat4::from_scale(Vec3::splat(2.0)).inverse();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 10.0), Vec3::NEG_Z);
+        let local = ray.transformed(&matrix);
+
+        // The box scaled by 2 is hit at world z = 2, local z = 1
+        let distance = local.intersect_aabb(&unit_box()).unwrap();
+        assert!((distance - 8.0).abs() < 1e-6);
+        assert!(ray.at(distance).abs_diff_eq(Vec3::new(0.0, 0.0, 2.0), 1e-6));
+    }
+}
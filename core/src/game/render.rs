@@ -15,5 +15,10 @@ pub trait Renderable {
         Mat4::IDENTITY
     }
 
+    /// Whether the object is drawn into shadow maps.
+    fn casts_shadows(&self) -> bool {
+        true
+    }
+
     fn animate(&mut self, _dt: f32);
 }
@@ -1,58 +1,139 @@
 use glam::Mat4;
+use glow::HasContext;
+use std::rc::Rc;
 
-use crate::game::Camera;
-use crate::graphics::types::LightObjectRef;
-use crate::graphics::{GlRef, RenderableRef};
+use crate::game::{
+    Camera, EnvironmentManager, Highlight, LightManager, NodeId, SceneGraph, ShadowManager,
+    Transform,
+};
+use crate::graphics::{GlRef, RenderableRef, Shader, ShaderRef};
 
 pub struct RenderManager {
     gl: GlRef,
-    pub render_targets: Vec<RenderableRef>,
+    /// Everything that is drawn, placed by the node it hangs from.
+    pub graph: SceneGraph,
+    /// Lights every object is drawn with. Their lamps are in `graph` like any other renderable.
+    pub lights: LightManager,
+    pub shadows: ShadowManager,
+    pub environment: EnvironmentManager,
+    /// Marks the selected object.
+    pub highlight: Highlight,
 }
 
 impl RenderManager {
     pub fn new(gl: GlRef) -> Result<Self, String> {
         Ok(Self {
+            shadows: ShadowManager::new(gl.clone())?,
+            environment: EnvironmentManager::new(gl.clone())?,
+            highlight: Highlight::new(gl.clone())?,
             gl,
-            render_targets: Vec::new(),
+            graph: SceneGraph::new(),
+            lights: LightManager::new(),
         })
     }
 
-    pub fn add_renderable(&mut self, renderable: RenderableRef) {
-        self.render_targets.push(renderable);
+    /// Add `renderable` at the root of the scene graph.
+    pub fn add_renderable(&mut self, renderable: RenderableRef) -> NodeId {
+        self.graph
+            .add_renderable("", renderable, Transform::default(), None)
+    }
+
+    pub fn remove_renderable(&mut self, renderable: &RenderableRef) {
+        self.graph.remove_renderable(renderable);
+    }
+
+    pub fn renderables(&self) -> impl Iterator<Item = &RenderableRef> {
+        self.graph
+            .iter()
+            .filter_map(|(_, node)| node.renderable.as_ref())
+    }
+
+    /// Call `f` with every shader drawing uses, once each even when materials share it.
+    pub fn for_each_shader(&self, mut f: impl FnMut(&Shader)) {
+        let mut shared: Vec<ShaderRef> = Vec::new();
+        for renderable in self.renderables() {
+            let shader = renderable.borrow().material().shader.clone();
+            if !shared.iter().any(|s| Rc::ptr_eq(s, &shader)) {
+                shared.push(shader);
+            }
+        }
+
+        for shader in &shared {
+            f(shader);
+        }
+        for shader in self.shadows.shaders() {
+            f(shader);
+        }
+        for shader in self.environment.shaders() {
+            f(shader);
+        }
+        f(self.highlight.shader());
     }
 
     // Animation and other updates
     pub fn update(&mut self, dt: f32) {
-        for renderable in &self.render_targets {
+        for renderable in self.renderables() {
             renderable.borrow_mut().animate(dt);
         }
     }
 
-    pub fn draw(&mut self, model: &Mat4, camera: &Camera, sun: &LightObjectRef) {
-        for renderable in &self.render_targets {
+    pub fn draw(&mut self, model: &Mat4, camera: &Camera) {
+        self.graph.update();
+        self.shadows.render(&self.graph, &self.lights);
+
+        // Shadow maps keep to the standard depth range
+        let depth = camera.frustum.depth;
+        depth.begin(&self.gl);
+
+        // The skybox fills whatever the opaque objects left uncovered, and
+        // transparent objects blend over both
+        self.draw_objects(model, camera, false);
+        let view = camera.get_camera_view_matrix();
+        self.environment
+            .draw_skybox(&view, &(*model * view.inverse()), depth);
+        self.draw_objects(model, camera, true);
+        self.highlight.draw(&self.graph, model, depth);
+
+        depth.end(&self.gl);
+        self.shadows.draw_debug();
+    }
+
+    /// Draw either the opaque or the transparent objects. `model` is projection times view.
+    fn draw_objects(&mut self, model: &Mat4, camera: &Camera, transparent: bool) {
+        for (renderable, world) in self.graph.renderables() {
             let obj = renderable.borrow();
             let material = obj.material();
+            if material.is_transparent() != transparent {
+                continue;
+            }
             let mesh = obj.mesh();
 
             material.apply(&self.gl);
 
+            unsafe {
+                if material.is_transparent() {
+                    self.gl.enable(glow::BLEND);
+                    self.gl
+                        .blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+                } else {
+                    self.gl.disable(glow::BLEND);
+                }
+            }
+
             // Set uniforms
             material.shader.setUniform4fm("pv", model);
-            material.shader.setUniform4fm("model", &obj.model_matrix());
-            material.shader.setUniform1i("u_texture", 0); // Replace in the future with tex.unit for PBR
-
-            material
-                .shader
-                .setUniform1f("u_ambient", sun.borrow().ambient());
-            material
-                .shader
-                .setUniform1f("u_specular", sun.borrow().specular());
             material
                 .shader
-                .setUniform3fv("u_light_pos", &sun.borrow().transform().position);
-            material
-                .shader
-                .setUniform3fv("u_view_pos", &camera.transform.position);
+                .setUniform4fm("model", &(world * obj.model_matrix()));
+
+            self.lights.apply(&material.shader);
+            self.shadows.apply(&material.shader, &self.lights);
+            self.environment.apply(&material.shader);
+            if material.shader.has_uniform("u_view_pos") {
+                material
+                    .shader
+                    .setUniform3fv("u_view_pos", &camera.transform.position);
+            }
 
             // Draw mesh
             mesh.draw(&self.gl);
@@ -0,0 +1,312 @@
+use glam::Mat4;
+use std::rc::Rc;
+
+use crate::game::{Aabb, Transform};
+use crate::graphics::RenderableRef;
+
+/// Handle to a node of a [`SceneGraph`]. It stays invalid once the node is
+/// removed, even if a new node takes its place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<SceneNode>,
+}
+
+pub struct SceneNode {
+    pub name: String,
+    /// Drawn with this node's world matrix times its own model matrix.
+    pub renderable: Option<RenderableRef>,
+    /// Relative to the parent. Change it through [`SceneGraph::transform_mut`] so
+    /// the world matrices follow.
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Mat4,
+    /// `world` is stale. Set for a node means set for everything below it as well.
+    dirty: bool,
+}
+
+impl SceneNode {
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// A hierarchy of transforms, with renderables hanging from some of the nodes.
+///
+/// World matrices are cached and only recomputed for nodes whose transform, or
+/// whose parent's transform, changed since the last [`SceneGraph::update`].
+#[derive(Default)]
+pub struct SceneGraph {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node below `parent`, or a new root for `None`.
+    pub fn add_node(&mut self, name: &str, transform: Transform, parent: Option<NodeId>) -> NodeId {
+        let node = SceneNode {
+            name: name.to_string(),
+            renderable: None,
+            transform,
+            parent,
+            children: Vec::new(),
+            world: Mat4::IDENTITY,
+            dirty: true,
+        };
+
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.generation += 1;
+                slot.node = Some(node);
+                NodeId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                NodeId {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        };
+
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        id
+    }
+
+    /// Add a node holding `renderable`, placed by `transform` below `parent`.
+    pub fn add_renderable(
+        &mut self,
+        name: &str,
+        renderable: RenderableRef,
+        transform: Transform,
+        parent: Option<NodeId>,
+    ) -> NodeId {
+        let id = self.add_node(name, transform, parent);
+        self.node_mut(id).renderable = Some(renderable);
+        id
+    }
+
+    /// Remove `id` and everything below it.
+    pub fn remove(&mut self, id: NodeId) {
+        let Some(node) = self.node(id) else {
+            return;
+        };
+
+        match node.parent {
+            Some(parent) => self.node_mut(parent).children.retain(|c| *c != id),
+            None => self.roots.retain(|r| *r != id),
+        }
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.slots[id.index].node.take() {
+                stack.extend(node.children);
+                self.free.push(id.index);
+            }
+        }
+    }
+
+    /// Remove every node holding `renderable`, along with its children.
+    pub fn remove_renderable(&mut self, renderable: &RenderableRef) {
+        let found: Vec<NodeId> = self
+            .iter()
+            .filter(|(_, node)| {
+                node.renderable
+                    .as_ref()
+                    .is_some_and(|r| Rc::ptr_eq(r, renderable))
+            })
+            .map(|(id, _)| id)
+            .collect();
+
+        for id in found {
+            self.remove(id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // Slots are kept so ids of the removed nodes stay invalid
+        for root in std::mem::take(&mut self.roots) {
+            self.remove(root);
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&SceneNode> {
+        self.slots
+            .get(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.node(id).is_some()
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// Local transform of `id`, which has to be in the graph.
+    pub fn transform(&self, id: NodeId) -> &Transform {
+        &self.node_ref(id).transform
+    }
+
+    /// Change the local transform of `id`. Its world matrix and those of everything
+    /// below it are recomputed on the next update.
+    pub fn transform_mut(&mut self, id: NodeId) -> &mut Transform {
+        self.mark_dirty(id);
+        &mut self.node_mut(id).transform
+    }
+
+    /// Move `id` below `parent`, keeping its local transform.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        // A node can't become its own ancestor
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a == id {
+                return;
+            }
+            ancestor = self.node_ref(a).parent;
+        }
+
+        match self.node_ref(id).parent {
+            Some(old) => self.node_mut(old).children.retain(|c| *c != id),
+            None => self.roots.retain(|r| *r != id),
+        }
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        self.node_mut(id).parent = parent;
+        self.mark_dirty(id);
+    }
+
+    /// World matrix of `id`. Cached unless something above it changed since the
+    /// last update, then it's worked out from the parents.
+    pub fn world_matrix(&self, id: NodeId) -> Mat4 {
+        let node = self.node_ref(id);
+        if !node.dirty {
+            return node.world;
+        }
+
+        let local = node.transform.get_model_matrix();
+        match node.parent {
+            Some(parent) => self.world_matrix(parent) * local,
+            None => local,
+        }
+    }
+
+    /// Recompute the world matrices that went stale.
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, Mat4)> =
+            self.roots.iter().map(|r| (*r, Mat4::IDENTITY)).collect();
+
+        while let Some((id, parent_world)) = stack.pop() {
+            let node = self.node_mut(id);
+            if node.dirty {
+                node.world = parent_world * node.transform.get_model_matrix();
+                node.dirty = false;
+            }
+
+            let world = node.world;
+            stack.extend(node.children.iter().map(|c| (*c, world)));
+        }
+    }
+
+    /// Every node, parents before their children.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &SceneNode)> {
+        self.walk(self.roots.iter().rev().copied().collect())
+    }
+
+    /// `id` and everything below it, parents before their children.
+    pub fn subtree(&self, id: NodeId) -> impl Iterator<Item = (NodeId, &SceneNode)> {
+        self.walk(if self.contains(id) {
+            vec![id]
+        } else {
+            Vec::new()
+        })
+    }
+
+    /// World-space bounds of the renderables at and below `id`.
+    pub fn bounds(&self, id: NodeId) -> Option<Aabb> {
+        self.subtree(id)
+            .filter_map(|(id, node)| {
+                let renderable = node.renderable.as_ref()?.borrow();
+                let matrix = self.world_matrix(id) * renderable.model_matrix();
+                renderable.mesh().bounds().map(|b| b.transformed(&matrix))
+            })
+            .reduce(|a, b| a.union(&b))
+    }
+
+    fn walk(&self, mut stack: Vec<NodeId>) -> impl Iterator<Item = (NodeId, &SceneNode)> {
+        std::iter::from_fn(move || {
+            let id = stack.pop()?;
+            let node = self.node_ref(id);
+            stack.extend(node.children.iter().rev());
+            Some((id, node))
+        })
+    }
+
+    /// Every renderable with the world matrix of its node.
+    pub fn renderables(&self) -> impl Iterator<Item = (&RenderableRef, Mat4)> {
+        self.iter()
+            .filter_map(|(id, node)| node.renderable.as_ref().map(|r| (r, self.world_matrix(id))))
+    }
+
+    fn mark_dirty(&mut self, id: NodeId) {
+        let node = self.node_mut(id);
+        node.dirty = true;
+        let mut stack = node.children.clone();
+
+        while let Some(id) = stack.pop() {
+            let node = self.node_mut(id);
+            // Everything below a dirty node is dirty already
+            if !node.dirty {
+                node.dirty = true;
+                stack.extend(node.children.iter().copied());
+            }
+        }
+    }
+
+    fn node_ref(&self, id: NodeId) -> &SceneNode {
+        self.node(id).expect("Node is not in the scene graph")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut SceneNode {
+        self.slots
+            .get_mut(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+            .expect("Node is not in the scene graph")
+    }
+}
@@ -0,0 +1,351 @@
+use std::sync::OnceLock;
+
+use glam::{Mat4, Vec3};
+use glow::{HasContext, VertexArray};
+use log::error;
+
+use crate::game::light_manager::{light_uniforms, max_lights};
+use crate::game::{Aabb, LightKind, LightManager, LightSource, SceneGraph};
+use crate::gl_check_error;
+use crate::graphics::material::SHADOW_UNIT;
+use crate::graphics::{
+    GlRef, SHADOW_DEBUG_FRAG_PATH, SHADOW_DEBUG_FRAG_SRC, SHADOW_DEBUG_VERT_PATH,
+    SHADOW_DEBUG_VERT_SRC, SHADOW_DEPTH_FRAG_PATH, SHADOW_DEPTH_FRAG_SRC, SHADOW_DEPTH_VERT_PATH,
+    SHADOW_DEPTH_VERT_SRC, Shader, ShadowMap,
+};
+
+/// Most lights casting shadows at once. Matches `MAX_SHADOWS` in `loaded_obj.frag`.
+pub const MAX_SHADOW_MAPS: usize = 4;
+
+/// Shadow map resolutions a light may ask for.
+pub const MIN_SHADOW_RESOLUTION: u32 = 64;
+pub const MAX_SHADOW_RESOLUTION: u32 = 8192;
+
+/// Names of the `u_shadow_maps` and `u_light_space` entries of `slot`, built once.
+fn slot_uniforms(slot: usize) -> &'static (String, String) {
+    static NAMES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+    &NAMES.get_or_init(|| {
+        (0..MAX_SHADOW_MAPS)
+            .map(|slot| {
+                (
+                    format!("u_shadow_maps[{}]", slot),
+                    format!("u_light_space[{}]", slot),
+                )
+            })
+            .collect()
+    })[slot]
+}
+
+/// A light that got a shadow map this frame.
+struct Caster {
+    /// Index into the light manager's lights.
+    light: usize,
+    light_space: Mat4,
+    bias: f32,
+    resolution: u32,
+    // Depth range, for the debug view
+    near: f32,
+    far: f32,
+    perspective: bool,
+}
+
+impl Caster {
+    /// Fit the light's view around `bounds`, the shadow casting part of the scene.
+    fn new(light: usize, position: Vec3, source: &LightSource, bounds: &Aabb) -> Option<Self> {
+        let center = bounds.center();
+        let radius = bounds.radius().max(1e-3);
+        let direction = source.direction.normalize_or(Vec3::NEG_Y);
+        let up = if direction.y.abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+
+        let (view, projection, near, far) = match source.kind {
+            LightKind::Point => return None,
+            LightKind::Directional => {
+                // Far enough back that nothing in the bounds is behind the light
+                let eye = center - direction * radius * 2.0;
+                let (near, far) = (0.0, radius * 4.0);
+                let projection =
+                    Mat4::orthographic_rh_gl(-radius, radius, -radius, radius, near, far);
+                (Mat4::look_to_rh(eye, direction, up), projection, near, far)
+            }
+            LightKind::Spot => {
+                let far = position.distance(center) + radius;
+                let near = (far - 2.0 * radius).max(far * 0.01);
+                let fov = (2.0 * source.outer_cone).clamp(1.0, 170.0).to_radians();
+                let projection = Mat4::perspective_rh_gl(fov, 1.0, near, far);
+                (
+                    Mat4::look_to_rh(position, direction, up),
+                    projection,
+                    near,
+                    far,
+                )
+            }
+        };
+
+        Some(Self {
+            light,
+            light_space: projection * view,
+            bias: source.shadow_bias,
+            resolution: source
+                .shadow_resolution
+                .clamp(MIN_SHADOW_RESOLUTION, MAX_SHADOW_RESOLUTION),
+            near,
+            far,
+            perspective: source.kind == LightKind::Spot,
+        })
+    }
+}
+
+/// Shadow maps for the directional and spot lights of a [`LightManager`].
+///
+/// Every frame, [`ShadowManager::render`] draws the scene's depth from each
+/// shadow casting light, and [`ShadowManager::apply`] hands the maps to the
+/// object shader, which filters them with PCF.
+pub struct ShadowManager {
+    gl: GlRef,
+    /// Render shadows at all.
+    pub enabled: bool,
+    /// Shadow map drawn in the corner of the view, by its index in [`ShadowManager::casters`].
+    pub debug_view: Option<usize>,
+    depth_shader: Shader,
+    debug_shader: Shader,
+    // Core profiles can't draw without a vertex array, even an empty one
+    debug_vao: VertexArray,
+    maps: Vec<ShadowMap>,
+    casters: Vec<Caster>,
+}
+
+impl ShadowManager {
+    pub fn new(gl: GlRef) -> Result<Self, String> {
+        let mut depth_shader = Shader::new(gl.clone());
+        depth_shader.add(
+            glow::VERTEX_SHADER,
+            SHADOW_DEPTH_VERT_SRC,
+            SHADOW_DEPTH_VERT_PATH,
+        )?;
+        depth_shader.add(
+            glow::FRAGMENT_SHADER,
+            SHADOW_DEPTH_FRAG_SRC,
+            SHADOW_DEPTH_FRAG_PATH,
+        )?;
+        depth_shader.link()?;
+
+        let mut debug_shader = Shader::new(gl.clone());
+        debug_shader.add(
+            glow::VERTEX_SHADER,
+            SHADOW_DEBUG_VERT_SRC,
+            SHADOW_DEBUG_VERT_PATH,
+        )?;
+        debug_shader.add(
+            glow::FRAGMENT_SHADER,
+            SHADOW_DEBUG_FRAG_SRC,
+            SHADOW_DEBUG_FRAG_PATH,
+        )?;
+        debug_shader.link()?;
+
+        let debug_vao = unsafe { gl.create_vertex_array()? };
+
+        Ok(Self {
+            gl,
+            enabled: true,
+            debug_view: None,
+            depth_shader,
+            debug_shader,
+            debug_vao,
+            maps: Vec::new(),
+            casters: Vec::new(),
+        })
+    }
+
+    /// The depth pass and debug view shaders.
+    pub fn shaders(&self) -> [&Shader; 2] {
+        [&self.depth_shader, &self.debug_shader]
+    }
+
+    /// Index into the light manager's lights of each light with a shadow map.
+    pub fn casters(&self) -> impl Iterator<Item = usize> + '_ {
+        self.casters.iter().map(|c| c.light)
+    }
+
+    /// Draw the shadow maps of the first [`MAX_SHADOW_MAPS`] shadow casting lights.
+    /// The framebuffer and viewport are left as they were.
+    pub fn render(&mut self, graph: &SceneGraph, lights: &LightManager) {
+        self.casters.clear();
+        if !self.enabled {
+            return;
+        }
+
+        let casting = || {
+            graph
+                .renderables()
+                .filter(|(renderable, _)| renderable.borrow().casts_shadows())
+        };
+
+        let Some(bounds) = casting()
+            .filter_map(|(renderable, world)| {
+                let renderable = renderable.borrow();
+                let matrix = world * renderable.model_matrix();
+                renderable.mesh().bounds().map(|b| b.transformed(&matrix))
+            })
+            .reduce(|a, b| a.union(&b))
+        else {
+            return;
+        };
+
+        for (index, light) in lights.lights.iter().take(max_lights()).enumerate() {
+            if self.casters.len() == MAX_SHADOW_MAPS {
+                break;
+            }
+
+            let light = light.borrow();
+            let source = light.source();
+            if !source.cast_shadows {
+                continue;
+            }
+            if let Some(caster) = Caster::new(index, light.transform().position, source, &bounds) {
+                self.casters.push(caster);
+            }
+        }
+
+        // Making a map binds its framebuffer too, so remember what to go back to first
+        let (framebuffer, viewport) = unsafe {
+            let mut viewport = [0; 4];
+            self.gl
+                .get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+            (
+                self.gl
+                    .get_parameter_framebuffer(glow::DRAW_FRAMEBUFFER_BINDING),
+                viewport,
+            )
+        };
+
+        // Maps are kept between frames and only remade when the resolution changes
+        for (slot, caster) in self.casters.iter().enumerate() {
+            if self
+                .maps
+                .get(slot)
+                .is_some_and(|map| map.resolution == caster.resolution)
+            {
+                continue;
+            }
+
+            match ShadowMap::new(self.gl.clone(), caster.resolution) {
+                Ok(map) if slot < self.maps.len() => self.maps[slot] = map,
+                Ok(map) => self.maps.push(map),
+                Err(e) => {
+                    error!("Failed to create a shadow map, turning shadows off: {}", e);
+                    self.enabled = false;
+                    self.casters.clear();
+                    unsafe {
+                        self.gl.bind_framebuffer(glow::FRAMEBUFFER, framebuffer);
+                    }
+                    return;
+                }
+            }
+        }
+
+        unsafe {
+            self.gl.enable(glow::DEPTH_TEST);
+            self.gl.disable(glow::BLEND);
+            self.depth_shader.bind();
+
+            for (caster, map) in self.casters.iter().zip(&self.maps) {
+                map.bind();
+                self.gl.clear(glow::DEPTH_BUFFER_BIT);
+                self.depth_shader
+                    .setUniform4fm("u_light_space", &caster.light_space);
+
+                for (renderable, world) in casting() {
+                    let obj = renderable.borrow();
+                    self.depth_shader
+                        .setUniform4fm("model", &(world * obj.model_matrix()));
+                    obj.mesh().draw(&self.gl);
+                }
+            }
+
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, framebuffer);
+            self.gl
+                .viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl_check_error!(self.gl);
+        }
+    }
+
+    /// Bind the shadow maps and tell `shader`, which has to be bound, which light
+    /// uses which. Call it after [`LightManager::apply`].
+    pub fn apply(&self, shader: &Shader, lights: &LightManager) {
+        if !shader.has_uniform(&slot_uniforms(0).0) {
+            return;
+        }
+
+        // Every sampler gets its own unit, even unused ones can't share with a sampler2D
+        for slot in 0..MAX_SHADOW_MAPS {
+            shader.setUniform1i(&slot_uniforms(slot).0, SHADOW_UNIT + slot as i32);
+        }
+
+        for index in 0..lights.lights.len().min(max_lights()) {
+            shader.setUniform1i(&light_uniforms(index).shadow, -1);
+        }
+
+        for (slot, (caster, map)) in self.casters.iter().zip(&self.maps).enumerate() {
+            map.bind_unit(SHADOW_UNIT + slot as i32);
+            let uniform = light_uniforms(caster.light);
+            shader.setUniform4fm(&slot_uniforms(slot).1, &caster.light_space);
+            shader.setUniform1i(&uniform.shadow, slot as i32);
+            shader.setUniform1f(&uniform.shadow_bias, caster.bias);
+        }
+    }
+
+    /// Draw the shadow map picked by [`ShadowManager::debug_view`] over the
+    /// bottom left of the viewport, nearer depths darker.
+    pub fn draw_debug(&self) {
+        let Some(slot) = self.debug_view else {
+            return;
+        };
+        let (Some(caster), Some(map)) = (self.casters.get(slot), self.maps.get(slot)) else {
+            return;
+        };
+
+        unsafe {
+            let mut viewport = [0; 4];
+            self.gl
+                .get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+            let size = (viewport[2].min(viewport[3]) / 3).max(1);
+
+            self.gl.viewport(viewport[0], viewport[1], size, size);
+            self.gl.disable(glow::DEPTH_TEST);
+            self.gl.disable(glow::BLEND);
+
+            map.set_compare(false);
+            map.bind_unit(0);
+
+            self.debug_shader.bind();
+            self.debug_shader.setUniform1i("u_depth", 0);
+            self.debug_shader
+                .setUniform1i("u_perspective", caster.perspective as i32);
+            self.debug_shader.setUniform1f("u_near", caster.near);
+            self.debug_shader.setUniform1f("u_far", caster.far);
+
+            self.gl.bind_vertex_array(Some(self.debug_vao));
+            self.gl.draw_arrays(glow::TRIANGLE_STRIP, 0, 4);
+            self.gl.bind_vertex_array(None);
+
+            map.set_compare(true);
+
+            self.gl.enable(glow::DEPTH_TEST);
+            self.gl
+                .viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl_check_error!(self.gl);
+        }
+    }
+}
+
+impl Drop for ShadowManager {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_vertex_array(self.debug_vao);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use glam::{Mat3, Mat4};
+use glow::{HasContext, VertexArray};
+
+use crate::gl_check_error;
+use crate::graphics::depth::DepthMode;
+use crate::graphics::{
+    CUBEMAP_VERT_PATH, CUBEMAP_VERT_SRC, GlRef, SKYBOX_FRAG_PATH, SKYBOX_FRAG_SRC, Shader, Texture,
+};
+
+/// Draws a cubemap around the camera, behind everything else.
+///
+/// The cube lands on the far plane and is depth tested against what's there,
+/// so it only fills the pixels no opaque object covered.
+pub struct Skybox {
+    gl: GlRef,
+    shader: Shader,
+    // Core profiles can't draw without a vertex array, even an empty one
+    vao: VertexArray,
+}
+
+impl Skybox {
+    pub fn new(gl: GlRef) -> Result<Self, String> {
+        let mut shader = Shader::new(gl.clone());
+        shader.add(glow::VERTEX_SHADER, CUBEMAP_VERT_SRC, CUBEMAP_VERT_PATH)?;
+        shader.add(glow::FRAGMENT_SHADER, SKYBOX_FRAG_SRC, SKYBOX_FRAG_PATH)?;
+        shader.link()?;
+
+        let vao = unsafe { gl.create_vertex_array()? };
+
+        Ok(Self { gl, shader, vao })
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// Draw `cubemap` after the opaque objects and before the transparent ones.
+    ///
+    /// `rotation` turns world directions into the cubemap's and `intensity`
+    /// scales its colors, which are decoded from sRGB first if `srgb` is set.
+    /// The far plane is where `depth` puts it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        cubemap: &Texture,
+        view: &Mat4,
+        projection: &Mat4,
+        rotation: &Mat3,
+        intensity: f32,
+        srgb: bool,
+        depth: DepthMode,
+    ) {
+        // Only the camera's rotation, the sky is infinitely far away
+        let view = Mat4::from_mat3(Mat3::from_mat4(*view));
+
+        unsafe {
+            // The far plane passes where the depth buffer is still clear
+            self.gl.enable(glow::DEPTH_TEST);
+            self.gl.depth_func(depth.nearer_or_equal());
+            self.gl.depth_mask(false);
+            self.gl.disable(glow::BLEND);
+
+            cubemap.bind_unit(0);
+            self.shader.bind();
+            self.shader.setUniform1i("u_environment", 0);
+            self.shader
+                .setUniform4fm("u_view_projection", &(*projection * view));
+            self.shader
+                .setUniform3fm("u_environment_rotation", rotation);
+            self.shader
+                .setUniform1f("u_environment_intensity", intensity);
+            self.shader.setUniform1i("u_srgb", srgb as i32);
+            self.shader.setUniform1f("u_far_depth", depth.far_depth());
+
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.draw_arrays(glow::TRIANGLES, 0, 36);
+            self.gl.bind_vertex_array(None);
+
+            self.gl.depth_mask(true);
+            self.gl.depth_func(depth.nearer());
+            gl_check_error!(self.gl);
+        }
+    }
+}
+
+impl Drop for Skybox {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_vertex_array(self.vao);
+        }
+    }
+}
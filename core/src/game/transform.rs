@@ -37,6 +37,11 @@ impl Transform {
         Mat4::from_scale(self.scale)
     }
 
+    /// Scale, then rotation, then translation; places an object relative to its parent.
+    pub fn get_model_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+
     pub fn get_world_matrix(&self) -> Mat4 {
         self.get_position_matrix() * self.get_rotation_matrix()
     }
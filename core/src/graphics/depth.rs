@@ -0,0 +1,123 @@
+//! Which way depth runs in the main pass.
+//!
+//! Reverse-Z puts the near plane at depth 1 and the far plane at 0. With a
+//! floating point depth buffer that spreads precision evenly over distance,
+//! since floats are densest near 0. It needs `glClipControl` to keep GL from
+//! squeezing depth into 0.5..1 first, which glow doesn't wrap, so the platform
+//! loads it with [`load_clip_control`]. Without it reverse-Z isn't offered.
+
+use std::ffi::{CStr, c_void};
+use std::sync::OnceLock;
+
+use glow::{Context, HasContext};
+use log::info;
+
+/// `glClipControl(origin, depth)`.
+type ClipControlFn = unsafe extern "system" fn(origin: u32, depth: u32);
+
+const LOWER_LEFT: u32 = 0x8CA1;
+const NEGATIVE_ONE_TO_ONE: u32 = 0x935E;
+const ZERO_TO_ONE: u32 = 0x935F;
+
+static CLIP_CONTROL: OnceLock<ClipControlFn> = OnceLock::new();
+
+/// Look up `glClipControl` through `loader` if `gl` has it, GL 4.5 or
+/// `ARB_clip_control`. Call it once, after the context is made current.
+pub fn load_clip_control(gl: &Context, mut loader: impl FnMut(&CStr) -> *const c_void) {
+    let version = gl.version();
+    let supported = ((version.major, version.minor) >= (4, 5) && !version.is_embedded)
+        || gl.supported_extensions().contains("GL_ARB_clip_control");
+    if !supported {
+        info!("No glClipControl, reverse-Z is unavailable");
+        return;
+    }
+
+    let pointer = loader(c"glClipControl");
+    if pointer.is_null() {
+        return;
+    }
+    // SAFETY: the driver says the function exists, with this signature
+    let function = unsafe { std::mem::transmute::<*const c_void, ClipControlFn>(pointer) };
+    let _ = CLIP_CONTROL.set(function);
+}
+
+/// Whether [`DepthMode::Reversed`] can be used, see [`load_clip_control`].
+pub fn reverse_z_supported() -> bool {
+    CLIP_CONTROL.get().is_some()
+}
+
+fn clip_control(depth: u32) {
+    if let Some(function) = CLIP_CONTROL.get() {
+        unsafe { function(LOWER_LEFT, depth) };
+    }
+}
+
+/// How the main pass maps distance to depth.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Near is 0 and far is 1, what GL does out of the box.
+    #[default]
+    Standard,
+    /// Near is 1 and far is 0, see the module docs. Only use it when
+    /// [`reverse_z_supported`], and with a floating point depth buffer.
+    Reversed,
+}
+
+impl DepthMode {
+    /// Depth of the far plane, where the depth buffer is cleared to.
+    pub fn far_depth(&self) -> f32 {
+        match self {
+            DepthMode::Standard => 1.0,
+            DepthMode::Reversed => 0.0,
+        }
+    }
+
+    /// The depth test that keeps nearer fragments.
+    pub fn nearer(&self) -> u32 {
+        match self {
+            DepthMode::Standard => glow::LESS,
+            DepthMode::Reversed => glow::GREATER,
+        }
+    }
+
+    /// Like [`DepthMode::nearer`], letting equal depths through too.
+    pub fn nearer_or_equal(&self) -> u32 {
+        match self {
+            DepthMode::Standard => glow::LEQUAL,
+            DepthMode::Reversed => glow::GEQUAL,
+        }
+    }
+
+    /// Sign of a polygon offset that moves surfaces toward the viewer.
+    pub fn toward_viewer(&self) -> f32 {
+        match self {
+            DepthMode::Standard => -1.0,
+            DepthMode::Reversed => 1.0,
+        }
+    }
+
+    /// Clear the bound framebuffer's depth to the far plane.
+    pub fn clear(&self, gl: &Context) {
+        unsafe {
+            gl.clear_depth_f32(self.far_depth());
+            gl.clear(glow::DEPTH_BUFFER_BIT);
+            gl.clear_depth_f32(1.0);
+        }
+    }
+
+    /// Set up the depth test and clip range for drawing in this mode.
+    /// [`DepthMode::end`] puts GL's defaults back for the other passes.
+    pub fn begin(&self, gl: &Context) {
+        if *self == DepthMode::Reversed {
+            clip_control(ZERO_TO_ONE);
+        }
+        unsafe { gl.depth_func(self.nearer()) };
+    }
+
+    pub fn end(&self, gl: &Context) {
+        if *self == DepthMode::Reversed {
+            clip_control(NEGATIVE_ONE_TO_ONE);
+        }
+        unsafe { gl.depth_func(glow::LESS) };
+    }
+}
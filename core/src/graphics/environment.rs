@@ -0,0 +1,491 @@
+use glam::{Mat4, Vec3};
+use glow::{Framebuffer as GlowFramebuffer, HasContext, Texture as GlowTexture, VertexArray};
+#[cfg(not(target_arch = "wasm32"))]
+use image::ImageReader;
+use image::Rgb32FImage;
+use image::imageops::{self, FilterType};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use crate::gl_check_error;
+use crate::graphics::{
+    BRDF_LUT_FRAG_PATH, BRDF_LUT_FRAG_SRC, CUBEMAP_VERT_PATH, CUBEMAP_VERT_SRC,
+    EQUIRECT_TO_CUBE_FRAG_PATH, EQUIRECT_TO_CUBE_FRAG_SRC, GlRef, IRRADIANCE_FRAG_PATH,
+    IRRADIANCE_FRAG_SRC, PREFILTER_FRAG_PATH, PREFILTER_FRAG_SRC, SHADOW_DEBUG_VERT_PATH,
+    SHADOW_DEBUG_VERT_SRC, Shader, Texture, TextureKind,
+};
+
+/// Face size of the cubemap the equirectangular image is converted to.
+pub const ENVIRONMENT_SIZE: u32 = 512;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTERED_SIZE: u32 = 128;
+/// Mip levels of the prefiltered map, roughness 0 in the first and 1 in the last.
+pub const PREFILTERED_LEVELS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 128;
+
+/// Wider images are scaled down before upload, the cubemap can't show more detail anyway.
+const MAX_EQUIRECT_WIDTH: u32 = 4 * ENVIRONMENT_SIZE;
+
+/// Mip of the environment the irradiance convolution reads. Its samples are
+/// far apart, so finer levels would only alias.
+const IRRADIANCE_SOURCE_LOD: f32 = 3.0;
+
+/// An HDR environment and the maps image based lighting needs from it.
+///
+/// Everything is baked on the GPU when it's created: the equirectangular image
+/// becomes a cubemap, which is then convolved into an irradiance map for
+/// diffuse light and a prefiltered map for highlights, one mip per roughness.
+/// The BRDF lookup table doesn't depend on the image, but is cheap enough to
+/// bake along with it.
+pub struct Environment {
+    gl: GlRef,
+    cubemap: Texture,
+    irradiance: GlowTexture,
+    prefiltered: GlowTexture,
+    brdf_lut: GlowTexture,
+}
+
+impl Environment {
+    /// Load an equirectangular image, usually a `.hdr` (not supported on WASM - use load_bytes instead)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_file(gl: GlRef, path: &Path) -> Result<Self, String> {
+        let image = ImageReader::open(path)
+            .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?
+            .with_guessed_format()
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?
+            .decode()
+            .map_err(|e| format!("Failed to decode '{}': {}", path.display(), e))?
+            .to_rgb32f();
+
+        Self::from_image(gl, &image)
+    }
+
+    /// Load an equirectangular image from embedded bytes
+    pub fn load_bytes(gl: GlRef, data: &[u8]) -> Result<Self, String> {
+        let image = image::load_from_memory(data)
+            .map_err(|e| format!("Failed to decode image: {}", e))?
+            .to_rgb32f();
+
+        Self::from_image(gl, &image)
+    }
+
+    /// Bake the environment from an equirectangular image in linear color.
+    pub fn from_image(gl: GlRef, image: &Rgb32FImage) -> Result<Self, String> {
+        if image.width() == 0 || image.height() == 0 {
+            return Err("Environment image is empty".to_string());
+        }
+
+        let scaled;
+        let image = if image.width() > MAX_EQUIRECT_WIDTH {
+            let height = (image.height() * MAX_EQUIRECT_WIDTH / image.width()).max(1);
+            scaled = imageops::resize(image, MAX_EQUIRECT_WIDTH, height, FilterType::Triangle);
+            &scaled
+        } else {
+            image
+        };
+
+        let baker = Baker::new(gl.clone())?;
+
+        let environment = Self {
+            gl: gl.clone(),
+            cubemap: Texture {
+                gl: gl.clone(),
+                handle: create_cubemap(&gl, ENVIRONMENT_SIZE, 1)?,
+                kind: TextureKind::Cubemap,
+                unit: 0,
+                width: ENVIRONMENT_SIZE,
+                height: ENVIRONMENT_SIZE,
+                path: None,
+            },
+            irradiance: create_cubemap(&gl, IRRADIANCE_SIZE, 1)?,
+            prefiltered: create_cubemap(&gl, PREFILTERED_SIZE, PREFILTERED_LEVELS)?,
+            brdf_lut: create_texture_2d(&gl, glow::RG16F, glow::RG, BRDF_LUT_SIZE)?,
+        };
+
+        baker.equirect_to_cube(image, environment.cubemap.handle)?;
+        unsafe {
+            // The skybox and the convolutions read from the blurrier levels
+            gl.bind_texture(glow::TEXTURE_CUBE_MAP, Some(environment.cubemap.handle));
+            gl.tex_parameter_i32(
+                glow::TEXTURE_CUBE_MAP,
+                glow::TEXTURE_MIN_FILTER,
+                glow::LINEAR_MIPMAP_LINEAR as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_CUBE_MAP,
+                glow::TEXTURE_MAX_LEVEL,
+                ENVIRONMENT_SIZE.ilog2() as i32,
+            );
+            gl.generate_mipmap(glow::TEXTURE_CUBE_MAP);
+            gl.bind_texture(glow::TEXTURE_CUBE_MAP, None);
+        }
+
+        baker.irradiance(environment.cubemap.handle, environment.irradiance)?;
+        baker.prefilter(environment.cubemap.handle, environment.prefiltered)?;
+        baker.brdf_lut(environment.brdf_lut)?;
+
+        // Returning drops the environment, which deletes its textures
+        if gl_check_error!(gl) != glow::NO_ERROR {
+            return Err("Failed to bake the environment maps".to_string());
+        }
+
+        Ok(environment)
+    }
+
+    /// The HDR image as a cubemap with all its mips, for the skybox.
+    pub fn cubemap(&self) -> &Texture {
+        &self.cubemap
+    }
+
+    /// Bind the maps the object shaders light with to their texture units
+    pub fn bind_lighting(&self, irradiance_unit: i32, prefiltered_unit: i32, brdf_lut_unit: i32) {
+        self.bind(self.irradiance, glow::TEXTURE_CUBE_MAP, irradiance_unit);
+        self.bind(self.prefiltered, glow::TEXTURE_CUBE_MAP, prefiltered_unit);
+        self.bind(self.brdf_lut, glow::TEXTURE_2D, brdf_lut_unit);
+    }
+
+    fn bind(&self, texture: GlowTexture, target: u32, unit: i32) {
+        unsafe {
+            self.gl.active_texture(glow::TEXTURE0 + unit as u32);
+            self.gl.bind_texture(target, Some(texture));
+        }
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_texture(self.cubemap.handle);
+            self.gl.delete_texture(self.irradiance);
+            self.gl.delete_texture(self.prefiltered);
+            self.gl.delete_texture(self.brdf_lut);
+        }
+    }
+}
+
+/// An RGBA16F cubemap with `levels` mips allocated.
+fn create_cubemap(gl: &GlRef, size: u32, levels: u32) -> Result<GlowTexture, String> {
+    unsafe {
+        let texture = gl.create_texture()?;
+        gl.bind_texture(glow::TEXTURE_CUBE_MAP, Some(texture));
+
+        for level in 0..levels {
+            let size = (size >> level).max(1) as i32;
+            for face in 0..6 {
+                gl.tex_image_2d(
+                    glow::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                    level as i32,
+                    glow::RGBA16F as i32,
+                    size,
+                    size,
+                    0,
+                    glow::RGBA,
+                    glow::FLOAT,
+                    glow::PixelUnpackData::Slice(None),
+                );
+            }
+        }
+
+        let min_filter = if levels > 1 {
+            glow::LINEAR_MIPMAP_LINEAR
+        } else {
+            glow::LINEAR
+        };
+        let clamp = glow::CLAMP_TO_EDGE as i32;
+        gl.tex_parameter_i32(glow::TEXTURE_CUBE_MAP, glow::TEXTURE_WRAP_S, clamp);
+        gl.tex_parameter_i32(glow::TEXTURE_CUBE_MAP, glow::TEXTURE_WRAP_T, clamp);
+        gl.tex_parameter_i32(glow::TEXTURE_CUBE_MAP, glow::TEXTURE_WRAP_R, clamp);
+        gl.tex_parameter_i32(
+            glow::TEXTURE_CUBE_MAP,
+            glow::TEXTURE_MIN_FILTER,
+            min_filter as i32,
+        );
+        gl.tex_parameter_i32(
+            glow::TEXTURE_CUBE_MAP,
+            glow::TEXTURE_MAG_FILTER,
+            glow::LINEAR as i32,
+        );
+        gl.tex_parameter_i32(
+            glow::TEXTURE_CUBE_MAP,
+            glow::TEXTURE_MAX_LEVEL,
+            levels as i32 - 1,
+        );
+        gl.bind_texture(glow::TEXTURE_CUBE_MAP, None);
+
+        Ok(texture)
+    }
+}
+
+/// A square float texture without mips, clamped and filtered linearly.
+fn create_texture_2d(
+    gl: &GlRef,
+    internal_format: u32,
+    format: u32,
+    size: u32,
+) -> Result<GlowTexture, String> {
+    unsafe {
+        let texture = gl.create_texture()?;
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            internal_format as i32,
+            size as i32,
+            size as i32,
+            0,
+            format,
+            glow::FLOAT,
+            glow::PixelUnpackData::Slice(None),
+        );
+
+        let clamp = glow::CLAMP_TO_EDGE as i32;
+        let linear = glow::LINEAR as i32;
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, clamp);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, clamp);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, linear);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, linear);
+        gl.bind_texture(glow::TEXTURE_2D, None);
+
+        Ok(texture)
+    }
+}
+
+/// Draws the baking passes into cubemap faces and textures.
+///
+/// It owns a framebuffer to draw with, and puts back the framebuffer, viewport
+/// and depth test it found when it's dropped.
+struct Baker {
+    gl: GlRef,
+    framebuffer: GlowFramebuffer,
+    // Core profiles can't draw without a vertex array, even an empty one
+    vao: VertexArray,
+    previous_framebuffer: Option<GlowFramebuffer>,
+    previous_viewport: [i32; 4],
+}
+
+impl Baker {
+    fn new(gl: GlRef) -> Result<Self, String> {
+        // WebGL2 can only draw into float textures with this extension
+        #[cfg(target_arch = "wasm32")]
+        if !gl.supported_extensions().contains("EXT_color_buffer_float") {
+            return Err("Environment maps need the EXT_color_buffer_float extension".to_string());
+        }
+
+        unsafe {
+            let mut previous_viewport = [0; 4];
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut previous_viewport);
+            let previous_framebuffer = gl.get_parameter_framebuffer(glow::DRAW_FRAMEBUFFER_BINDING);
+
+            let framebuffer = gl.create_framebuffer()?;
+            let vao = gl.create_vertex_array()?;
+
+            gl.disable(glow::DEPTH_TEST);
+            gl.disable(glow::BLEND);
+            // Filter across cubemap faces, which GLES always does
+            #[cfg(not(target_arch = "wasm32"))]
+            gl.enable(glow::TEXTURE_CUBE_MAP_SEAMLESS);
+
+            Ok(Self {
+                gl,
+                framebuffer,
+                vao,
+                previous_framebuffer,
+                previous_viewport,
+            })
+        }
+    }
+
+    /// A shader drawing `frag_src` over every face of a cube.
+    fn cube_shader(&self, frag_src: &str, frag_path: &'static str) -> Result<Shader, String> {
+        let mut shader = Shader::new(self.gl.clone());
+        shader.add(glow::VERTEX_SHADER, CUBEMAP_VERT_SRC, CUBEMAP_VERT_PATH)?;
+        shader.add(glow::FRAGMENT_SHADER, frag_src, frag_path)?;
+        shader.link()?;
+        Ok(shader)
+    }
+
+    /// Draw the bound `shader` into each face of mip `level` of `target`.
+    fn draw_faces(
+        &self,
+        shader: &Shader,
+        target: GlowTexture,
+        size: u32,
+        level: u32,
+    ) -> Result<(), String> {
+        // Looking down each axis, with the up vectors cubemaps are laid out with
+        let faces = [
+            (Vec3::X, Vec3::NEG_Y),
+            (Vec3::NEG_X, Vec3::NEG_Y),
+            (Vec3::Y, Vec3::Z),
+            (Vec3::NEG_Y, Vec3::NEG_Z),
+            (Vec3::Z, Vec3::NEG_Y),
+            (Vec3::NEG_Z, Vec3::NEG_Y),
+        ];
+        let projection = Mat4::perspective_rh_gl(90f32.to_radians(), 1.0, 0.1, 10.0);
+        let size = (size >> level).max(1) as i32;
+
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            self.gl.viewport(0, 0, size, size);
+            self.gl.bind_vertex_array(Some(self.vao));
+
+            for (face, (forward, up)) in faces.iter().enumerate() {
+                self.gl.framebuffer_texture_2d(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                    Some(target),
+                    level as i32,
+                );
+                self.check_framebuffer()?;
+
+                let view = Mat4::look_to_rh(Vec3::ZERO, *forward, *up);
+                shader.setUniform4fm("u_view_projection", &(projection * view));
+                self.gl.draw_arrays(glow::TRIANGLES, 0, 36);
+            }
+
+            self.gl.bind_vertex_array(None);
+        }
+
+        Ok(())
+    }
+
+    fn check_framebuffer(&self) -> Result<(), String> {
+        let status = unsafe { self.gl.check_framebuffer_status(glow::FRAMEBUFFER) };
+        if status != glow::FRAMEBUFFER_COMPLETE {
+            return Err(format!(
+                "Environment framebuffer is incomplete (status 0x{:x})",
+                status
+            ));
+        }
+        Ok(())
+    }
+
+    fn equirect_to_cube(&self, image: &Rgb32FImage, target: GlowTexture) -> Result<(), String> {
+        let shader = self.cube_shader(EQUIRECT_TO_CUBE_FRAG_SRC, EQUIRECT_TO_CUBE_FRAG_PATH)?;
+
+        unsafe {
+            let equirect = self.gl.create_texture()?;
+            self.gl.active_texture(glow::TEXTURE0);
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(equirect));
+            self.gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGB16F as i32,
+                image.width() as i32,
+                image.height() as i32,
+                0,
+                glow::RGB,
+                glow::FLOAT,
+                glow::PixelUnpackData::Slice(Some(bytemuck::cast_slice(image.as_raw()))),
+            );
+            let linear = glow::LINEAR as i32;
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, linear);
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, linear);
+            // Wraps around horizontally, but the poles mustn't bleed into each other
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+            self.gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_WRAP_T,
+                glow::CLAMP_TO_EDGE as i32,
+            );
+
+            shader.bind();
+            shader.setUniform1i("u_equirect", 0);
+            let result = self.draw_faces(&shader, target, ENVIRONMENT_SIZE, 0);
+
+            self.gl.delete_texture(equirect);
+            result
+        }
+    }
+
+    fn irradiance(&self, environment: GlowTexture, target: GlowTexture) -> Result<(), String> {
+        let shader = self.cube_shader(IRRADIANCE_FRAG_SRC, IRRADIANCE_FRAG_PATH)?;
+
+        unsafe {
+            self.gl.active_texture(glow::TEXTURE0);
+            self.gl
+                .bind_texture(glow::TEXTURE_CUBE_MAP, Some(environment));
+        }
+
+        shader.bind();
+        shader.setUniform1i("u_environment", 0);
+        shader.setUniform1f("u_source_lod", IRRADIANCE_SOURCE_LOD);
+        self.draw_faces(&shader, target, IRRADIANCE_SIZE, 0)
+    }
+
+    fn prefilter(&self, environment: GlowTexture, target: GlowTexture) -> Result<(), String> {
+        let shader = self.cube_shader(PREFILTER_FRAG_SRC, PREFILTER_FRAG_PATH)?;
+
+        unsafe {
+            self.gl.active_texture(glow::TEXTURE0);
+            self.gl
+                .bind_texture(glow::TEXTURE_CUBE_MAP, Some(environment));
+        }
+
+        shader.bind();
+        shader.setUniform1i("u_environment", 0);
+        shader.setUniform1f("u_resolution", ENVIRONMENT_SIZE as f32);
+
+        for level in 0..PREFILTERED_LEVELS {
+            let roughness = level as f32 / (PREFILTERED_LEVELS - 1) as f32;
+            shader.setUniform1f("u_roughness", roughness);
+            self.draw_faces(&shader, target, PREFILTERED_SIZE, level)?;
+        }
+
+        Ok(())
+    }
+
+    fn brdf_lut(&self, target: GlowTexture) -> Result<(), String> {
+        // Any quad over the viewport will do
+        let mut shader = Shader::new(self.gl.clone());
+        shader.add(
+            glow::VERTEX_SHADER,
+            SHADOW_DEBUG_VERT_SRC,
+            SHADOW_DEBUG_VERT_PATH,
+        )?;
+        shader.add(glow::FRAGMENT_SHADER, BRDF_LUT_FRAG_SRC, BRDF_LUT_FRAG_PATH)?;
+        shader.link()?;
+
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            self.gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(target),
+                0,
+            );
+            self.check_framebuffer()?;
+
+            self.gl
+                .viewport(0, 0, BRDF_LUT_SIZE as i32, BRDF_LUT_SIZE as i32);
+            shader.bind();
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.draw_arrays(glow::TRIANGLE_STRIP, 0, 4);
+            self.gl.bind_vertex_array(None);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Baker {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, self.previous_framebuffer);
+            let [x, y, width, height] = self.previous_viewport;
+            self.gl.viewport(x, y, width, height);
+            self.gl.enable(glow::DEPTH_TEST);
+
+            self.gl.delete_framebuffer(self.framebuffer);
+            self.gl.delete_vertex_array(self.vao);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use glow::{Framebuffer as GlowFramebuffer, HasContext, Renderbuffer};
+use image::RgbaImage;
+
+use crate::gl_check_error;
+use crate::graphics::GlRef;
+
+/// An offscreen render target with an RGBA color and a depth attachment.
+pub struct Framebuffer {
+    gl: GlRef,
+    handle: GlowFramebuffer,
+    color: Renderbuffer,
+    depth: Renderbuffer,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Framebuffer {
+    pub fn new(gl: GlRef, width: u32, height: u32) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid framebuffer size {}x{}", width, height));
+        }
+
+        unsafe {
+            let handle = gl.create_framebuffer()?;
+            let color = gl.create_renderbuffer()?;
+            let depth = gl.create_renderbuffer()?;
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(handle));
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(color));
+            gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, width as i32, height as i32);
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(color),
+            );
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(depth));
+            gl.renderbuffer_storage(
+                glow::RENDERBUFFER,
+                glow::DEPTH_COMPONENT24,
+                width as i32,
+                height as i32,
+            );
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                glow::RENDERBUFFER,
+                Some(depth),
+            );
+
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            gl_check_error!(gl);
+
+            let framebuffer = Self {
+                gl,
+                handle,
+                color,
+                depth,
+                width,
+                height,
+            };
+
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                return Err(format!("Framebuffer is incomplete (status 0x{:x})", status));
+            }
+
+            Ok(framebuffer)
+        }
+    }
+
+    /// Draw into this framebuffer, covering all of it.
+    pub fn bind(&self) {
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.handle));
+            self.gl
+                .viewport(0, 0, self.width as i32, self.height as i32);
+        }
+    }
+
+    /// Go back to drawing into the default framebuffer.
+    pub fn unbind(&self) {
+        unsafe {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+    }
+
+    /// Read the color attachment back, top row first.
+    pub fn read_pixels(&self) -> RgbaImage {
+        read_pixels(&self.gl, Some(self.handle), self.width, self.height)
+    }
+}
+
+/// Read the color of `framebuffer`, or of the window's when `None`, top row first.
+pub fn read_pixels(
+    gl: &GlRef,
+    framebuffer: Option<GlowFramebuffer>,
+    width: u32,
+    height: u32,
+) -> RgbaImage {
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+    unsafe {
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, framebuffer);
+        gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+        gl.read_pixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(Some(&mut pixels)),
+        );
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        gl_check_error!(gl);
+    }
+
+    // GL's first row is the bottom of the image
+    let row = width as usize * 4;
+    let flipped: Vec<u8> = pixels.chunks_exact(row).rev().flatten().copied().collect();
+
+    RgbaImage::from_raw(width, height, flipped).expect("Pixel buffer matches the framebuffer size")
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_framebuffer(self.handle);
+            self.gl.delete_renderbuffer(self.color);
+            self.gl.delete_renderbuffer(self.depth);
+        }
+    }
+}
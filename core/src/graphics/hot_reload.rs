@@ -0,0 +1,213 @@
+//! Rebuilding shaders when their files change on disk.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::graphics::Shader;
+
+/// Where the shader files live, relative to the working directory like the
+/// paths in [`crate::graphics::shader_paths`].
+pub const SHADER_DIR: &str = "resources/shaders";
+
+/// How long a file has to stay untouched before it's reloaded. Editors often
+/// write a file in several steps.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Watches a directory of shaders for files that are written to.
+pub struct ShaderWatcher {
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    pending: HashSet<PathBuf>,
+    last_event: Instant,
+}
+
+impl ShaderWatcher {
+    /// Watch `dir` and everything below it.
+    pub fn new(dir: &Path) -> Result<Self, String> {
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)
+            .map_err(|e| format!("Failed to create a file watcher: {}", e))?;
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch '{}': {}", dir.display(), e))?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            pending: HashSet::new(),
+            last_event: Instant::now(),
+        })
+    }
+
+    /// Files that changed since the last call and have settled since. Doesn't block.
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        for event in self.events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Shader watcher: {}", e);
+                    continue;
+                }
+            };
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                self.pending.extend(event.paths);
+                self.last_event = Instant::now();
+            }
+        }
+
+        if self.pending.is_empty() || self.last_event.elapsed() < SETTLE_TIME {
+            return Vec::new();
+        }
+        self.pending.drain().collect()
+    }
+}
+
+/// Why a shader couldn't be rebuilt from its files.
+#[derive(Clone, Debug)]
+pub struct ReloadError {
+    /// The shader, see [`Shader::label`].
+    pub shader: String,
+    /// The file that couldn't be read or compiled, `None` when linking failed.
+    pub path: Option<String>,
+    /// What the compiler or linker said, or why the file couldn't be read.
+    pub message: String,
+    /// The lines of `path` the message points at.
+    pub lines: Vec<ErrorLine>,
+}
+
+/// A compiler message about one line of a shader file.
+#[derive(Clone, Debug)]
+pub struct ErrorLine {
+    /// 1-based, like the file's lines. The defines put in front of the source
+    /// are told to keep them that way with a `#line`.
+    pub line: u32,
+    pub message: String,
+    /// The line's text, empty when the file couldn't be read.
+    pub source: String,
+}
+
+impl ReloadError {
+    pub(crate) fn new(shader: &Shader, path: Option<&str>, message: &str) -> Self {
+        let text = path
+            .and_then(|p| fs::read_to_string(p).ok())
+            .unwrap_or_default();
+        let lines = message
+            .lines()
+            .filter_map(parse_log_line)
+            .map(|(line, message)| ErrorLine {
+                line,
+                message,
+                source: text
+                    .lines()
+                    .nth(line.saturating_sub(1) as usize)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            })
+            .collect();
+
+        Self {
+            shader: shader.label(),
+            path: path.map(str::to_string),
+            message: message.trim().to_string(),
+            lines,
+        }
+    }
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path, self.message),
+            None => write!(f, "{}: {}", self.shader, self.message),
+        }
+    }
+}
+
+/// The line number and message of a line of a GLSL info log, `None` if it
+/// doesn't point at a line.
+///
+/// Drivers disagree on the format:
+///
+/// ```text
+/// 0:12(5): error: `foo' undeclared         Mesa
+/// ERROR: 0:12: 'foo' : undeclared          ANGLE, AMD, Intel
+/// 0(12) : error C1008: undefined variable  NVIDIA
+/// ```
+fn parse_log_line(line: &str) -> Option<(u32, String)> {
+    let line = line.trim();
+    let rest = ["ERROR:", "WARNING:"]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))
+        .unwrap_or(line)
+        .trim_start();
+
+    // The source string number comes first, it's always 0 here
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    let rest = &rest[digits..];
+    let (number, message) = if let Some(rest) = rest.strip_prefix(':') {
+        let end = rest.find(|c: char| !c.is_ascii_digit())?;
+        let message = rest[end..].trim_start_matches(|c: char| c != ':');
+        (&rest[..end], message)
+    } else {
+        let rest = rest.strip_prefix('(')?;
+        let (number, message) = rest.split_once(')')?;
+        (number, message)
+    };
+
+    let message = message.trim_start_matches([':', ' ']).trim();
+    Some((number.parse().ok()?, message.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_lines_of_each_driver() {
+        let cases = [
+            (
+                "0:12(5): error: `foo' undeclared",
+                (12, "error: `foo' undeclared"),
+            ),
+            ("ERROR: 0:7: 'foo' : undeclared", (7, "'foo' : undeclared")),
+            (
+                "WARNING: 0:3: extension not supported",
+                (3, "extension not supported"),
+            ),
+            (
+                "0(40) : error C1008: undefined variable \"foo\"",
+                (40, "error C1008: undefined variable \"foo\""),
+            ),
+        ];
+
+        for (line, (number, message)) in cases {
+            assert_eq!(
+                parse_log_line(line),
+                Some((number, message.to_string())),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn log_lines_without_a_line_number() {
+        for line in [
+            "",
+            "Link failed.",
+            "ERROR: one or more attached shaders not successfully compiled",
+            "0:x: error",
+        ] {
+            assert_eq!(parse_log_line(line), None, "{}", line);
+        }
+    }
+}
@@ -1,13 +1,56 @@
+use glam::{Mat3, Vec3};
 use glow::{Context, HasContext};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 use std::rc::Rc;
 
+use crate::graphics::texture::{FilterMode, TextureBuilder};
 use crate::graphics::{GlRef, Shader, ShaderRef, Texture, TextureRef, new_texture_ref};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::loaders::MtlMaterial;
+use crate::loaders::{MaterialData, ShadingModel, TextureSource};
+
+/// Texture unit each material map is bound to
+pub const DIFFUSE_UNIT: i32 = 0;
+pub const SPECULAR_UNIT: i32 = 1;
+pub const NORMAL_UNIT: i32 = 2;
+pub const EMISSIVE_UNIT: i32 = 3;
+pub const METALLIC_ROUGHNESS_UNIT: i32 = 4;
+pub const OCCLUSION_UNIT: i32 = 5;
+/// First of the units shadow maps are bound to, one per map
+pub const SHADOW_UNIT: i32 = 6;
+/// Image based lighting maps, after the last shadow map
+pub const IRRADIANCE_UNIT: i32 = 10;
+pub const PREFILTERED_UNIT: i32 = 11;
+pub const BRDF_LUT_UNIT: i32 = 12;
 
 #[derive(Clone)]
 
 pub struct Material {
+    pub name: String,
     pub shader: ShaderRef,
     pub texture: Option<TextureRef>,
+    pub specular_map: Option<TextureRef>,
+    pub normal_map: Option<TextureRef>,
+    pub emissive_map: Option<TextureRef>,
+    pub metallic_roughness_map: Option<TextureRef>,
+    pub occlusion_map: Option<TextureRef>,
+
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub illum: u32,
+    pub emissive: Vec3,
+    pub uv_transform: Mat3,
+
+    pub shading: ShadingModel,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+
     default_texture: TextureRef,
 }
 
@@ -17,18 +60,91 @@ impl Material {
             Texture::white_1x1(gl).expect("Failed to create default white texture"),
         );
         Self {
+            name: String::new(),
             shader,
             texture: None,
+            specular_map: None,
+            normal_map: None,
+            emissive_map: None,
+            metallic_roughness_map: None,
+            occlusion_map: None,
+
+            ambient: Vec3::ONE,
+            diffuse: Vec3::ONE,
+            specular: Vec3::ONE,
+            shininess: 32.0,
+            opacity: 1.0,
+            illum: 2,
+            emissive: Vec3::ZERO,
+            uv_transform: Mat3::IDENTITY,
+
+            shading: ShadingModel::Phong,
+            metallic: 0.0,
+            roughness: 0.5,
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+
             default_texture,
         }
     }
 
-    pub fn shader(&self) -> &Shader {
-        &self.shader
+    /// Build a material from imported data, loading its texture maps.
+    pub fn from_data(gl: GlRef, shader: ShaderRef, data: &MaterialData) -> Result<Self, String> {
+        let load =
+            |source: &Option<TextureSource>, unit: i32| -> Result<Option<TextureRef>, String> {
+                match source {
+                    Some(source) => {
+                        let texture = TextureBuilder::new(gl.clone())
+                            .unit(unit)
+                            .filter(FilterMode::Linear)
+                            .load_source(source)?;
+                        Ok(Some(new_texture_ref(texture)))
+                    }
+                    None => Ok(None),
+                }
+            };
+
+        let mut material = Material::new(gl.clone(), shader);
+        material.name = data.name.clone();
+        material.texture = load(&data.diffuse_map, DIFFUSE_UNIT)?;
+        material.specular_map = load(&data.specular_map, SPECULAR_UNIT)?;
+        material.normal_map = load(&data.normal_map, NORMAL_UNIT)?;
+        material.emissive_map = load(&data.emissive_map, EMISSIVE_UNIT)?;
+        material.metallic_roughness_map =
+            load(&data.metallic_roughness_map, METALLIC_ROUGHNESS_UNIT)?;
+        material.occlusion_map = load(&data.occlusion_map, OCCLUSION_UNIT)?;
+
+        material.ambient = data.ambient;
+        material.diffuse = data.diffuse;
+        material.specular = data.specular;
+        material.shininess = data.shininess;
+        material.opacity = data.opacity;
+        material.illum = data.illum;
+        material.emissive = data.emissive;
+        material.uv_transform = data.uv_transform;
+
+        material.shading = data.shading;
+        material.metallic = data.metallic;
+        material.roughness = data.roughness;
+        material.occlusion_strength = data.occlusion_strength;
+        material.normal_scale = data.normal_scale;
+
+        Ok(material)
+    }
+
+    /// Build a material from an MTL entry, loading its maps relative to `base_dir`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_mtl(
+        gl: GlRef,
+        shader: ShaderRef,
+        mtl: &MtlMaterial,
+        base_dir: &Path,
+    ) -> Result<Self, String> {
+        Material::from_data(gl, shader, &MaterialData::from_mtl(mtl, base_dir))
     }
 
-    pub fn shader_mut(&mut self) -> &mut Shader {
-        Rc::get_mut(&mut self.shader).unwrap()
+    pub fn shader(&self) -> &Shader {
+        &self.shader
     }
 
     pub fn texture(&self) -> &Texture {
@@ -45,18 +161,101 @@ impl Material {
         }
     }
 
+    pub fn is_transparent(&self) -> bool {
+        self.opacity < 1.0
+    }
+
     pub fn apply(&self, gl: &Context) {
         self.shader.bind();
 
-        match &self.texture {
-            Some(tex) => tex.bind(),
-            None => self.default_texture.bind(),
+        // Missing maps are white, which leaves the factors as they are
+        let maps = [
+            (&self.texture, DIFFUSE_UNIT, "u_texture"),
+            (&self.specular_map, SPECULAR_UNIT, "u_specular_map"),
+            (&self.normal_map, NORMAL_UNIT, "u_normal_map"),
+            (&self.emissive_map, EMISSIVE_UNIT, "u_emissive_map"),
+            (
+                &self.metallic_roughness_map,
+                METALLIC_ROUGHNESS_UNIT,
+                "u_metallic_roughness_map",
+            ),
+            (&self.occlusion_map, OCCLUSION_UNIT, "u_occlusion_map"),
+        ];
+        for (map, unit, uniform) in maps {
+            // Phong and metallic-roughness shaders read different maps
+            if !self.shader.has_uniform(uniform) {
+                continue;
+            }
+            map.as_ref()
+                .unwrap_or(&self.default_texture)
+                .bind_unit(unit);
+            self.shader.setUniform1i(uniform, unit);
+        }
+
+        // Unlit shaders, like the lamp's, only take the texture
+        if self.shader.has_uniform("u_material.opacity") {
+            if self.shading == ShadingModel::MetallicRoughness {
+                self.apply_metallic_roughness();
+            } else {
+                self.apply_phong();
+            }
+            self.shader
+                .setUniform3fm("u_uv_transform", &self.uv_transform);
         }
 
-        for (_, loc) in &self.shader.attributes {
+        for attribute in self.shader.attributes().iter() {
             unsafe {
-                gl.enable_vertex_attrib_array(*loc);
+                gl.enable_vertex_attrib_array(attribute.location);
             }
         }
     }
+
+    fn apply_phong(&self) {
+        // illum 0 is flat color and 1 has no highlight
+        let specular_color = if self.illum >= 2 {
+            self.specular
+        } else {
+            Vec3::ZERO
+        };
+
+        self.shader
+            .setUniform3fv("u_material.ambient", &self.ambient);
+        self.shader
+            .setUniform3fv("u_material.diffuse", &self.diffuse);
+        self.shader
+            .setUniform3fv("u_material.specular", &specular_color);
+        self.shader
+            .setUniform1f("u_material.shininess", self.shininess);
+        self.shader.setUniform1f("u_material.opacity", self.opacity);
+        self.shader
+            .setUniform1i("u_material.lit", (self.illum > 0) as i32);
+        self.shader
+            .setUniform3fv("u_material.emissive", &self.emissive);
+        self.apply_normal_map();
+    }
+
+    fn apply_metallic_roughness(&self) {
+        self.shader
+            .setUniform3fv("u_material.base_color", &self.diffuse);
+        self.shader.setUniform1f("u_material.opacity", self.opacity);
+        self.shader
+            .setUniform1f("u_material.metallic", self.metallic);
+        self.shader
+            .setUniform1f("u_material.roughness", self.roughness);
+        self.shader
+            .setUniform1f("u_material.occlusion_strength", self.occlusion_strength);
+        self.shader
+            .setUniform3fv("u_material.emissive", &self.emissive);
+        self.apply_normal_map();
+    }
+
+    /// Without a normal map the shaders keep the interpolated vertex normal.
+    fn apply_normal_map(&self) {
+        self.shader
+            .setUniform1f("u_material.normal_scale", self.normal_scale);
+        self.shader.setUniform1i(
+            "u_material.has_normal_map",
+            self.normal_map.is_some() as i32,
+        );
+    }
 }
@@ -1,10 +1,10 @@
-use bytemuck::{cast_slice, offset_of};
+use bytemuck::cast_slice;
 use glow::{Buffer, Context, HasContext, VertexArray};
-use std::mem::size_of;
+use std::borrow::Cow;
 
+use crate::game::Aabb;
 use crate::gl_check_error;
-use crate::graphics::{ShaderRef, VEC3};
-use crate::graphics::{VEC2, Vertex};
+use crate::graphics::{Semantic, ShaderRef, Vertex, VertexAttribute, VertexBuffer, VertexLayout};
 
 #[derive(Clone)]
 
@@ -14,20 +14,62 @@ pub struct Mesh {
     pub ibo: Option<Buffer>,
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
+    /// How `vertices` are uploaded. `None` leaves out the fields no vertex
+    /// uses, see [`Vertex::compact_layout`].
+    pub layout: Option<VertexLayout>,
+    /// Attributes `Vertex` has no field for, or that replace one of its fields,
+    /// uploaded as they are. Each has a value for every vertex.
+    pub buffers: Vec<VertexBuffer>,
+    buffer_handles: Vec<Buffer>,
+    /// Shader inputs the mesh has no data for, with the value they get instead.
+    /// That value isn't part of the vertex array, so it's set on every draw.
+    defaults: Vec<(u32, [f32; 4])>,
 }
 
 impl Mesh {
+    /// Creates a CPU-side mesh. Call [`Mesh::upload`] before drawing it.
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        Self {
+            vao: None,
+            vbo: None,
+            ibo: None,
+            vertices,
+            indices,
+            layout: None,
+            buffers: Vec::new(),
+            buffer_handles: Vec::new(),
+            defaults: Vec::new(),
+        }
+    }
+
+    /// Add vertex data `Vertex` can't hold, like RGBA colors or joints.
+    pub fn add_buffer(&mut self, buffer: VertexBuffer) -> Result<(), String> {
+        if buffer.vertex_count() != self.vertices.len() {
+            return Err(format!(
+                "Vertex buffer has {} vertices, the mesh has {}",
+                buffer.vertex_count(),
+                self.vertices.len()
+            ));
+        }
+        self.buffers.push(buffer);
+        Ok(())
+    }
+
     pub fn draw(&self, gl: &Context) {
         if !self.is_uploaded() {
             panic!("Mesh not uploaded to GPU");
         }
 
         unsafe {
+            for (loc, [x, y, z, w]) in &self.defaults {
+                gl.vertex_attrib_4_f32(*loc, *x, *y, *z, *w);
+            }
+
             gl.bind_vertex_array(self.vao);
 
             gl_check_error!(gl);
 
-            if let Some(_) = self.ibo {
+            if self.ibo.is_some() {
                 gl.draw_elements(
                     glow::TRIANGLES,
                     self.indices.len() as i32,
@@ -42,31 +84,76 @@ impl Mesh {
         }
     }
 
+    /// Bounding box of the vertex positions in model space.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|v| v.position))
+    }
+
     fn is_uploaded(&self) -> bool {
-        self.vao.is_some() && self.vbo.is_some()
+        self.vao.is_some()
     }
 
+    /// Upload the vertices and extra buffers, and point every input of `shader`
+    /// at its attribute. Inputs the mesh has nothing for get a default value.
     pub fn upload(&mut self, gl: &Context, shader: ShaderRef) -> Result<(), String> {
+        // Extra buffers take the place of the vertex fields with the same semantic
+        let layout = self
+            .layout
+            .clone()
+            .unwrap_or_else(|| Vertex::compact_layout(&self.vertices))
+            .filtered(|a| !self.buffers.iter().any(|b| b.layout.contains(a.semantic)));
+        layout.validate()?;
+        if let Some(attribute) = layout
+            .attributes
+            .iter()
+            .find(|a| Vertex::default().attribute(a.semantic).is_none())
+        {
+            return Err(format!(
+                "Vertex has no {:?}, give it to the mesh as a VertexBuffer",
+                attribute.semantic
+            ));
+        }
+        if let Some(buffer) = self
+            .buffers
+            .iter()
+            .find(|b| b.vertex_count() != self.vertices.len())
+        {
+            return Err(format!(
+                "Vertex buffer has {} vertices, the mesh has {}",
+                buffer.vertex_count(),
+                self.vertices.len()
+            ));
+        }
+
+        // The full layout is the memory layout already
+        let data: Cow<[u8]> = if layout == Vertex::layout() {
+            Cow::Borrowed(cast_slice(&self.vertices))
+        } else {
+            Cow::Owned(layout.pack(self.vertices.len(), |index, semantic| {
+                self.vertices[index]
+                    .attribute(semantic)
+                    .expect("Checked above")
+            }))
+        };
+
         unsafe {
             let vao = gl
                 .create_vertex_array()
                 .expect("Failed to create vertex array");
 
-            let vbo = gl.create_buffer().expect("Failed to create vertex buffer");
-
             gl.bind_vertex_array(Some(vao));
-            gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
-
-            gl_check_error!(gl);
 
-            // Upload vertex data
-            gl.buffer_data_u8_slice(
-                glow::ARRAY_BUFFER,
-                cast_slice(&self.vertices),
-                glow::STATIC_DRAW,
-            );
+            let upload = |data: &[u8]| {
+                let vbo = gl.create_buffer().expect("Failed to create vertex buffer");
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
+                gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, data, glow::STATIC_DRAW);
+                gl_check_error!(gl);
+                vbo
+            };
 
-            gl_check_error!(gl);
+            let vbo = (!layout.attributes.is_empty()).then(|| upload(&data));
+            let buffer_handles: Vec<Buffer> =
+                self.buffers.iter().map(|b| upload(&b.data)).collect();
 
             // Upload index data if present
             if !self.indices.is_empty() {
@@ -83,30 +170,69 @@ impl Mesh {
                 self.ibo = Some(ibo);
             }
 
-            self.vao = Some(vao);
-            self.vbo = Some(vbo);
-
-            let stride = size_of::<Vertex>() as i32;
-
-            // Setup vertex attributes
-            for (name, loc) in &shader.attributes {
-                let (offset, size) = match *name {
-                    "i_position" => (offset_of!(Vertex, position), VEC3),
-                    "i_color" => (offset_of!(Vertex, color), VEC3),
-                    "i_normal" => (offset_of!(Vertex, normal), VEC3),
-                    "i_uv" => (offset_of!(Vertex, tex_coords), VEC2),
-                    _ => {
-                        return Err(format!("Unknown attribute name: {}", name));
+            // Where each semantic comes from: a buffer, its layout and the attribute
+            let mut sources: Vec<(Buffer, &VertexLayout, &VertexAttribute)> = self
+                .buffers
+                .iter()
+                .zip(&buffer_handles)
+                .flat_map(|(b, handle)| {
+                    b.layout
+                        .attributes
+                        .iter()
+                        .map(move |a| (*handle, &b.layout, a))
+                })
+                .collect();
+            if let Some(vbo) = vbo {
+                sources.extend(layout.attributes.iter().map(|a| (vbo, &layout, a)));
+            }
+
+            self.defaults.clear();
+            for input in shader.attributes().iter() {
+                let (name, loc) = (&input.name, &input.location);
+                let semantic = Semantic::from_attribute_name(name)
+                    .ok_or_else(|| format!("Unknown attribute name: {}", name))?;
+
+                let Some((buffer, layout, attribute)) =
+                    sources.iter().find(|(_, _, a)| a.semantic == semantic)
+                else {
+                    gl.disable_vertex_attrib_array(*loc);
+                    if let Some(value) = semantic.default_value() {
+                        self.defaults.push((*loc, value));
                     }
+                    continue;
                 };
+
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(*buffer));
                 gl.enable_vertex_attrib_array(*loc);
-                gl.vertex_attrib_pointer_f32(*loc, size, glow::FLOAT, false, stride, offset as i32);
+                let (count, kind) = (attribute.count, attribute.component.gl_type());
+                if attribute.is_integer() {
+                    gl.vertex_attrib_pointer_i32(
+                        *loc,
+                        count,
+                        kind,
+                        layout.stride,
+                        attribute.offset,
+                    );
+                } else {
+                    gl.vertex_attrib_pointer_f32(
+                        *loc,
+                        count,
+                        kind,
+                        attribute.normalized,
+                        layout.stride,
+                        attribute.offset,
+                    );
+                }
                 gl_check_error!(gl);
             }
 
             gl.bind_vertex_array(None);
             gl.bind_buffer(glow::ARRAY_BUFFER, None);
 
+            self.vao = Some(vao);
+            self.vbo = vbo;
+            self.buffer_handles = buffer_handles;
+
             Ok(())
         }
     }
@@ -1,22 +1,36 @@
+pub mod depth;
+pub mod environment;
+pub mod framebuffer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hot_reload;
 pub mod material;
 pub mod mesh;
+pub mod reflection;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod render_target;
 pub mod shader;
 pub mod shader_paths;
+pub mod shadow_map;
 pub mod source;
 pub mod texture;
 pub mod types;
 pub mod vertex;
+pub mod vertex_layout;
 
+pub use environment::Environment;
+pub use framebuffer::Framebuffer;
 pub use material::Material;
 pub use mesh::Mesh;
-pub use shader::Shader;
+pub use reflection::{ShaderAttribute, ShaderUniform, UniformValue};
+pub use shader::{Shader, object_shader};
 pub use shader_paths::*;
+pub use shadow_map::ShadowMap;
 pub(crate) use source::ShaderSource;
-pub use texture::Texture;
+pub use texture::{CubemapLayout, Texture, TextureBuilder, TextureKind};
 pub use types::{
-    GlRef, PhysicalRef, RenderableRef, ShaderRef, TextureRef, WindowRef, new_game_obj_ref,
-    new_gl_ref, new_physical_ref, new_renderable_ref, new_shader_ref, new_texture_ref,
+    GlRef, PhysicalRef, RenderableRef, ShaderRef, SurfaceRef, TextureRef, WindowRef,
+    new_game_obj_ref, new_gl_ref, new_physical_ref, new_renderable_ref, new_shader_ref,
+    new_texture_ref,
 };
-pub use vertex::VEC2;
-pub use vertex::VEC3;
 pub use vertex::Vertex;
+pub use vertex_layout::{ComponentType, Semantic, VertexAttribute, VertexBuffer, VertexLayout};
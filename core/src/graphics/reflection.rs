@@ -0,0 +1,227 @@
+use glow::{HasContext, Program, UniformLocation};
+
+use crate::graphics::GlRef;
+
+/// An active vertex input of a linked program.
+#[derive(Clone, Debug)]
+pub struct ShaderAttribute {
+    pub name: String,
+    /// GL type, like `glow::FLOAT_VEC3`. See [`type_name`].
+    pub kind: u32,
+    /// Array length, 1 for plain inputs.
+    pub size: i32,
+    pub location: u32,
+}
+
+/// An active uniform of a linked program.
+///
+/// Arrays are listed once, under the name of their first element (`u_maps[0]`),
+/// with `size` elements. Struct members are listed one by one (`u_material.diffuse`).
+#[derive(Clone, Debug)]
+pub struct ShaderUniform {
+    pub name: String,
+    /// GL type, like `glow::FLOAT_VEC3`. See [`type_name`].
+    pub kind: u32,
+    /// Array length, 1 for plain uniforms.
+    pub size: i32,
+    pub location: UniformLocation,
+}
+
+impl ShaderUniform {
+    /// Name of every element, just `name` unless it's an array.
+    pub fn element_names(&self) -> Vec<String> {
+        match self.name.strip_suffix("[0]") {
+            Some(base) if self.size > 1 => (0..self.size)
+                .map(|index| format!("{}[{}]", base, index))
+                .collect(),
+            _ => vec![self.name.clone()],
+        }
+    }
+}
+
+/// A value a uniform can be set to, one variant per `Shader::setUniform*`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat3([f32; 9]),
+    Mat4([f32; 16]),
+}
+
+impl UniformValue {
+    /// Whether a uniform of GL type `kind` can be set to the value.
+    pub fn fits(&self, kind: u32) -> bool {
+        match self {
+            // Booleans take any scalar, samplers take their texture unit
+            UniformValue::Int(_) => kind == glow::INT || kind == glow::BOOL || is_sampler(kind),
+            UniformValue::UInt(_) => kind == glow::UNSIGNED_INT || kind == glow::BOOL,
+            UniformValue::Float(_) => kind == glow::FLOAT || kind == glow::BOOL,
+            UniformValue::Vec2(_) => kind == glow::FLOAT_VEC2,
+            UniformValue::Vec3(_) => kind == glow::FLOAT_VEC3,
+            UniformValue::Vec4(_) => kind == glow::FLOAT_VEC4,
+            UniformValue::Mat3(_) => kind == glow::FLOAT_MAT3,
+            UniformValue::Mat4(_) => kind == glow::FLOAT_MAT4,
+        }
+    }
+
+    /// Name of the GL type the value is meant for.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            UniformValue::Int(_) => "int",
+            UniformValue::UInt(_) => "uint",
+            UniformValue::Float(_) => "float",
+            UniformValue::Vec2(_) => "vec2",
+            UniformValue::Vec3(_) => "vec3",
+            UniformValue::Vec4(_) => "vec4",
+            UniformValue::Mat3(_) => "mat3",
+            UniformValue::Mat4(_) => "mat4",
+        }
+    }
+
+    /// Set the uniform at `location` of the bound program.
+    pub(crate) fn upload(&self, gl: &GlRef, location: &UniformLocation) {
+        let location = Some(location);
+        unsafe {
+            match self {
+                UniformValue::Int(v) => gl.uniform_1_i32(location, *v),
+                UniformValue::UInt(v) => gl.uniform_1_u32(location, *v),
+                UniformValue::Float(v) => gl.uniform_1_f32(location, *v),
+                UniformValue::Vec2(v) => gl.uniform_2_f32_slice(location, v),
+                UniformValue::Vec3(v) => gl.uniform_3_f32_slice(location, v),
+                UniformValue::Vec4(v) => gl.uniform_4_f32_slice(location, v),
+                UniformValue::Mat3(v) => gl.uniform_matrix_3_f32_slice(location, false, v),
+                UniformValue::Mat4(v) => gl.uniform_matrix_4_f32_slice(location, false, v),
+            }
+        }
+    }
+
+    /// What the uniform of GL type `kind` at `location` holds now, `None` for
+    /// types there's no variant for.
+    pub(crate) fn read(
+        gl: &GlRef,
+        program: Program,
+        location: &UniformLocation,
+        kind: u32,
+    ) -> Option<Self> {
+        let floats = |count: usize| {
+            let mut v = [0.0; 16];
+            unsafe { gl.get_uniform_f32(program, location, &mut v[..count]) };
+            v
+        };
+
+        let value = match kind {
+            glow::FLOAT => UniformValue::Float(floats(1)[0]),
+            glow::FLOAT_VEC2 => UniformValue::Vec2(floats(2)[..2].try_into().unwrap()),
+            glow::FLOAT_VEC3 => UniformValue::Vec3(floats(3)[..3].try_into().unwrap()),
+            glow::FLOAT_VEC4 => UniformValue::Vec4(floats(4)[..4].try_into().unwrap()),
+            glow::FLOAT_MAT3 => UniformValue::Mat3(floats(9)[..9].try_into().unwrap()),
+            glow::FLOAT_MAT4 => UniformValue::Mat4(floats(16)),
+            glow::UNSIGNED_INT => {
+                let mut v = [0];
+                unsafe { gl.get_uniform_u32(program, location, &mut v) };
+                UniformValue::UInt(v[0])
+            }
+            kind if kind == glow::INT || kind == glow::BOOL || is_sampler(kind) => {
+                let mut v = [0];
+                unsafe { gl.get_uniform_i32(program, location, &mut v) };
+                UniformValue::Int(v[0])
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Whether `kind` is one of the sampler types GLSL ES 3.0 has.
+pub fn is_sampler(kind: u32) -> bool {
+    matches!(
+        kind,
+        glow::SAMPLER_2D
+            | glow::SAMPLER_3D
+            | glow::SAMPLER_CUBE
+            | glow::SAMPLER_2D_SHADOW
+            | glow::SAMPLER_2D_ARRAY
+            | glow::SAMPLER_2D_ARRAY_SHADOW
+            | glow::SAMPLER_CUBE_SHADOW
+            | glow::INT_SAMPLER_2D
+            | glow::INT_SAMPLER_3D
+            | glow::INT_SAMPLER_CUBE
+            | glow::INT_SAMPLER_2D_ARRAY
+            | glow::UNSIGNED_INT_SAMPLER_2D
+            | glow::UNSIGNED_INT_SAMPLER_3D
+            | glow::UNSIGNED_INT_SAMPLER_CUBE
+            | glow::UNSIGNED_INT_SAMPLER_2D_ARRAY
+    )
+}
+
+/// GLSL name of the GL type `kind`, for messages and the uniform editors.
+pub fn type_name(kind: u32) -> &'static str {
+    match kind {
+        glow::FLOAT => "float",
+        glow::FLOAT_VEC2 => "vec2",
+        glow::FLOAT_VEC3 => "vec3",
+        glow::FLOAT_VEC4 => "vec4",
+        glow::INT => "int",
+        glow::INT_VEC2 => "ivec2",
+        glow::INT_VEC3 => "ivec3",
+        glow::INT_VEC4 => "ivec4",
+        glow::UNSIGNED_INT => "uint",
+        glow::UNSIGNED_INT_VEC2 => "uvec2",
+        glow::UNSIGNED_INT_VEC3 => "uvec3",
+        glow::UNSIGNED_INT_VEC4 => "uvec4",
+        glow::BOOL => "bool",
+        glow::FLOAT_MAT2 => "mat2",
+        glow::FLOAT_MAT3 => "mat3",
+        glow::FLOAT_MAT4 => "mat4",
+        glow::SAMPLER_2D => "sampler2D",
+        glow::SAMPLER_3D => "sampler3D",
+        glow::SAMPLER_CUBE => "samplerCube",
+        glow::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        glow::SAMPLER_2D_ARRAY => "sampler2DArray",
+        glow::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        glow::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        kind if is_sampler(kind) => "sampler",
+        _ => "unknown",
+    }
+}
+
+/// The active attributes and uniforms of `program`, which has to be linked.
+/// Built-in inputs like `gl_VertexID` are left out.
+pub(crate) fn reflect(gl: &GlRef, program: Program) -> (Vec<ShaderAttribute>, Vec<ShaderUniform>) {
+    unsafe {
+        let attributes = (0..gl.get_active_attributes(program))
+            .filter_map(|index| gl.get_active_attribute(program, index))
+            .filter(|a| !a.name.starts_with("gl_"))
+            .filter_map(|a| {
+                let location = gl.get_attrib_location(program, &a.name)?;
+                Some(ShaderAttribute {
+                    name: a.name,
+                    kind: a.atype,
+                    size: a.size,
+                    location,
+                })
+            })
+            .collect();
+
+        // Uniforms in blocks have no location
+        let uniforms = (0..gl.get_active_uniforms(program))
+            .filter_map(|index| gl.get_active_uniform(program, index))
+            .filter(|u| !u.name.starts_with("gl_"))
+            .filter_map(|u| {
+                let location = gl.get_uniform_location(program, &u.name)?;
+                Some(ShaderUniform {
+                    name: u.name,
+                    kind: u.utype,
+                    size: u.size,
+                    location,
+                })
+            })
+            .collect();
+
+        (attributes, uniforms)
+    }
+}
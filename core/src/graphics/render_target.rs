@@ -0,0 +1,224 @@
+use glow::{Framebuffer as GlowFramebuffer, HasContext, Renderbuffer, Texture, VertexArray};
+use image::RgbaImage;
+
+use crate::gl_check_error;
+use crate::graphics::framebuffer::read_pixels;
+use crate::graphics::shader_paths::{
+    PRESENT_FRAG_PATH, PRESENT_FRAG_SRC, SHADOW_DEBUG_VERT_PATH, SHADOW_DEBUG_VERT_SRC,
+};
+use crate::graphics::{GlRef, Shader};
+
+/// Where the main pass draws when the window's own depth buffer won't do,
+/// as reverse-Z needs a floating point one. It's multisampled like the window
+/// and [`RenderTarget::present`] copies it over.
+pub struct RenderTarget {
+    gl: GlRef,
+    handle: GlowFramebuffer,
+    color: Renderbuffer,
+    depth: Renderbuffer,
+    // The resolved image, drawn to the window
+    resolve: GlowFramebuffer,
+    texture: Texture,
+    shader: Shader,
+    vao: VertexArray,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RenderTarget {
+    pub fn new(gl: GlRef, width: u32, height: u32) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid render target size {}x{}", width, height));
+        }
+
+        let mut shader = Shader::new(gl.clone());
+        shader.add(
+            glow::VERTEX_SHADER,
+            SHADOW_DEBUG_VERT_SRC,
+            SHADOW_DEBUG_VERT_PATH,
+        )?;
+        shader.add(glow::FRAGMENT_SHADER, PRESENT_FRAG_SRC, PRESENT_FRAG_PATH)?;
+        shader.link()?;
+
+        unsafe {
+            let samples = gl.get_parameter_i32(glow::SAMPLES).max(0);
+            let (w, h) = (width as i32, height as i32);
+
+            let handle = gl.create_framebuffer()?;
+            let color = gl.create_renderbuffer()?;
+            let depth = gl.create_renderbuffer()?;
+            let resolve = gl.create_framebuffer()?;
+            let texture = gl.create_texture()?;
+            let vao = gl.create_vertex_array()?;
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(handle));
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(color));
+            gl.renderbuffer_storage_multisample(glow::RENDERBUFFER, samples, glow::RGBA8, w, h);
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(color),
+            );
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(depth));
+            gl.renderbuffer_storage_multisample(
+                glow::RENDERBUFFER,
+                samples,
+                glow::DEPTH_COMPONENT32F,
+                w,
+                h,
+            );
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                glow::RENDERBUFFER,
+                Some(depth),
+            );
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA8 as i32,
+                w,
+                h,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(None),
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MIN_FILTER,
+                glow::NEAREST as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MAG_FILTER,
+                glow::NEAREST as i32,
+            );
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(resolve));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            let resolve_status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            gl_check_error!(gl);
+
+            let target = Self {
+                gl,
+                handle,
+                color,
+                depth,
+                resolve,
+                texture,
+                shader,
+                vao,
+                width,
+                height,
+            };
+
+            for status in [status, resolve_status] {
+                if status != glow::FRAMEBUFFER_COMPLETE {
+                    return Err(format!(
+                        "Render target is incomplete (status 0x{:x})",
+                        status
+                    ));
+                }
+            }
+
+            Ok(target)
+        }
+    }
+
+    /// Draw into this target, covering all of it.
+    pub fn bind(&self) {
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.handle));
+            self.gl
+                .viewport(0, 0, self.width as i32, self.height as i32);
+        }
+    }
+
+    /// Resolve what was drawn and copy it to the window's framebuffer, which
+    /// is left bound.
+    pub fn present(&self) {
+        self.resolve();
+
+        unsafe {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            self.gl
+                .viewport(0, 0, self.width as i32, self.height as i32);
+            self.gl.disable(glow::DEPTH_TEST);
+            self.gl.disable(glow::BLEND);
+
+            self.gl.active_texture(glow::TEXTURE0);
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            self.shader.bind();
+            self.shader.setUniform1i("u_color", 0);
+
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.draw_arrays(glow::TRIANGLE_STRIP, 0, 4);
+            self.gl.bind_vertex_array(None);
+            self.gl.bind_texture(glow::TEXTURE_2D, None);
+
+            self.gl.enable(glow::DEPTH_TEST);
+            gl_check_error!(self.gl);
+        }
+    }
+
+    /// Read the color back, top row first.
+    pub fn read_pixels(&self) -> RgbaImage {
+        self.resolve();
+        read_pixels(&self.gl, Some(self.resolve), self.width, self.height)
+    }
+
+    fn resolve(&self) {
+        let (w, h) = (self.width as i32, self.height as i32);
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.handle));
+            self.gl
+                .bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.resolve));
+            self.gl.blit_framebuffer(
+                0,
+                0,
+                w,
+                h,
+                0,
+                0,
+                w,
+                h,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            gl_check_error!(self.gl);
+        }
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_framebuffer(self.handle);
+            self.gl.delete_renderbuffer(self.color);
+            self.gl.delete_renderbuffer(self.depth);
+            self.gl.delete_framebuffer(self.resolve);
+            self.gl.delete_texture(self.texture);
+            self.gl.delete_vertex_array(self.vao);
+        }
+    }
+}
@@ -1,49 +1,103 @@
 #![allow(non_snake_case)]
-use glam::{Mat4, Vec2, Vec3, Vec4};
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
 use glow::{HasContext, Program, UniformLocation};
-use std::collections::HashMap;
+use log::warn;
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 
 use crate::gl_check_error;
 use crate::graphics::GlRef;
-use crate::graphics::ShaderSource;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::hot_reload::ReloadError;
+use crate::graphics::reflection::{
+    ShaderAttribute, ShaderUniform, UniformValue, reflect, type_name,
+};
+use crate::graphics::{Semantic, ShaderSource};
+use crate::loaders::ShadingModel;
 
 #[derive(Clone)]
 pub struct Shader {
     gl: GlRef,
-    pub(crate) handle: Program,
-    pub(crate) attributes: HashMap<&'static str, u32>, // Name and Location
+    /// Swapped for a new one when the shader is reloaded, which is why it's
+    /// behind a `RefCell`: shaders are shared between materials.
+    program: RefCell<LinkedProgram>,
     pub(crate) sources: Vec<ShaderSource>,
+    /// `#define`s put in front of every source, see [`Shader::define`].
+    defines: Vec<(String, String)>,
+    /// Uniforms set from the uniform editors, which the `setUniform*` calls leave alone.
+    overrides: RefCell<HashMap<String, UniformValue>>,
+    /// Uniform names already warned about, so each is only reported once.
+    warned: RefCell<HashSet<String>>,
     destroyed: bool,
 }
 
+/// A GL program and what [`Shader::link`] found in it.
+#[derive(Clone)]
+struct LinkedProgram {
+    handle: Program,
+    attributes: Vec<ShaderAttribute>,
+    uniforms: Vec<ShaderUniform>,
+    /// Location and GL type of every uniform name that can be set, array
+    /// elements included.
+    locations: HashMap<String, (UniformLocation, u32)>,
+}
+
 // Create a basic loaded object shader
 #[macro_export]
 macro_rules! loaded_shader {
     ($gl:expr) => {{
-        let mut shader = crate::graphics::Shader::new($gl.clone());
+        let mut shader = $crate::graphics::Shader::new($gl.clone());
+        shader.define("MAX_LIGHTS", $crate::game::light_manager::max_lights());
         let _ = shader.add(
             glow::FRAGMENT_SHADER,
-            crate::graphics::LOADED_OBJ_FRAG_SRC,
-            crate::graphics::LOADED_OBJ_FRAG_PATH,
+            $crate::graphics::LOADED_OBJ_FRAG_SRC,
+            $crate::graphics::LOADED_OBJ_FRAG_PATH,
         );
         let _ = shader.add(
             glow::VERTEX_SHADER,
-            crate::graphics::LOADED_OBJ_VERT_SRC,
-            crate::graphics::LOADED_OBJ_VERT_PATH,
+            $crate::graphics::LOADED_OBJ_VERT_SRC,
+            $crate::graphics::LOADED_OBJ_VERT_PATH,
         );
         let _ = shader.link();
 
-        shader.add_attribute("i_position");
-        shader.add_attribute("i_color");
-        shader.add_attribute("i_normal");
-        shader.add_attribute("i_uv");
+        shader
+    }};
+}
+
+// Create a loaded object shader with metallic-roughness shading
+#[macro_export]
+macro_rules! pbr_shader {
+    ($gl:expr) => {{
+        let mut shader = $crate::graphics::Shader::new($gl.clone());
+        shader.define("MAX_LIGHTS", $crate::game::light_manager::max_lights());
+        let _ = shader.add(
+            glow::FRAGMENT_SHADER,
+            $crate::graphics::PBR_FRAG_SRC,
+            $crate::graphics::PBR_FRAG_PATH,
+        );
+        let _ = shader.add(
+            glow::VERTEX_SHADER,
+            $crate::graphics::LOADED_OBJ_VERT_SRC,
+            $crate::graphics::LOADED_OBJ_VERT_PATH,
+        );
+        let _ = shader.link();
 
         shader
     }};
 }
 
+/// The shader objects with a material of the given shading are drawn with.
+pub fn object_shader(gl: &GlRef, shading: ShadingModel) -> Shader {
+    match shading {
+        ShadingModel::Phong => crate::loaded_shader!(gl),
+        ShadingModel::MetallicRoughness => crate::pbr_shader!(gl),
+    }
+}
+
 impl Shader {
     pub fn new(renderer: GlRef) -> Self {
         unsafe {
@@ -53,14 +107,27 @@ impl Shader {
 
             Self {
                 gl: renderer,
-                handle: program,
-                attributes: HashMap::new(),
+                program: RefCell::new(LinkedProgram {
+                    handle: program,
+                    attributes: Vec::new(),
+                    uniforms: Vec::new(),
+                    locations: HashMap::new(),
+                }),
                 sources: Vec::new(),
+                defines: Vec::new(),
+                overrides: RefCell::new(HashMap::new()),
+                warned: RefCell::new(HashSet::new()),
                 destroyed: false,
             }
         }
     }
 
+    /// `#define name value` in every source added afterwards, and again on reload.
+    pub fn define(&mut self, name: &str, value: impl ToString) {
+        self.defines.retain(|(n, _)| n != name);
+        self.defines.push((name.to_string(), value.to_string()));
+    }
+
     /// Compile Shader and attach to the program
     pub fn add(
         &mut self,
@@ -68,26 +135,85 @@ impl Shader {
         source: &str,
         filepath: &'static str,
     ) -> Result<(), String> {
-        let src = ShaderSource::new(self.gl.clone(), self.handle, shader_type, source, filepath)?;
+        self.compile(shader_type, source, filepath)
+            .map_err(|e| format!("Unable to compile shader '{filepath}': {e}"))
+    }
+
+    /// [`Shader::add`], failing with the compiler's log as it is.
+    fn compile(
+        &mut self,
+        shader_type: u32,
+        source: &str,
+        filepath: &'static str,
+    ) -> Result<(), String> {
+        let source = self.with_defines(source);
+        let src = ShaderSource::new(
+            self.gl.clone(),
+            self.handle(),
+            shader_type,
+            &source,
+            filepath,
+        )?;
 
         self.sources.push(src);
 
         Ok(())
     }
 
+    /// The GL program, which changes when the shader is reloaded.
+    pub fn handle(&self) -> Program {
+        self.program.borrow().handle
+    }
+
+    /// `source` with the defines inserted after its `#version` line, which has to stay first.
+    fn with_defines(&self, source: &str) -> String {
+        if self.defines.is_empty() {
+            return source.to_string();
+        }
+
+        let (version, rest, line) = match source.split_once('\n') {
+            Some((first, rest)) if first.trim_start().starts_with("#version") => (first, rest, 2),
+            _ => ("", source, 1),
+        };
+
+        let mut out = String::with_capacity(source.len() + 32 * self.defines.len());
+        if !version.is_empty() {
+            out.push_str(version);
+            out.push('\n');
+        }
+        for (name, value) in &self.defines {
+            out.push_str(&format!("#define {} {}\n", name, value));
+        }
+        // Keep line numbers in error messages matching the file
+        out.push_str(&format!("#line {}\n", line));
+        out.push_str(rest);
+        out
+    }
+
     pub fn is_linked(&self) -> bool {
-        unsafe { self.gl.get_program_link_status(self.handle) }
+        unsafe { self.gl.get_program_link_status(self.handle()) }
     }
 
-    /// Link shader to the program
+    /// Link shader to the program, and look up its active attributes and uniforms.
+    ///
+    /// Attributes get the location of their [`Semantic`], so meshes uploaded
+    /// for one build of a shader still draw with the next after a reload.
     pub fn link(&mut self) -> Result<(), String> {
+        let handle = self.handle();
         unsafe {
-            self.gl.link_program(self.handle);
+            for semantic in Semantic::ALL {
+                self.gl.bind_attrib_location(
+                    handle,
+                    semantic.location(),
+                    semantic.attribute_name(),
+                );
+            }
+            self.gl.link_program(handle);
 
             gl_check_error!(&self.gl);
 
             if !self.is_linked() {
-                let e = self.gl.get_program_info_log(self.handle);
+                let e = self.gl.get_program_info_log(handle);
                 return Err(format!("Shader failed to link: {e}"));
             }
         }
@@ -96,63 +222,148 @@ impl Shader {
             source.delete();
         }
 
+        let (attributes, uniforms) = reflect(&self.gl, handle);
+        let program = self.program.get_mut();
+        program.locations.clear();
+        for uniform in &uniforms {
+            let mut names = uniform.element_names();
+            // An array's name on its own is its first element
+            if let Some(base) = uniform.name.strip_suffix("[0]") {
+                names.push(base.to_string());
+            }
+
+            for name in names {
+                if let Some(location) = unsafe { self.gl.get_uniform_location(handle, &name) } {
+                    program.locations.insert(name, (location, uniform.kind));
+                }
+            }
+        }
+        program.attributes = attributes;
+        program.uniforms = uniforms;
+        self.warned.borrow_mut().clear();
+
         Ok(())
     }
 
-    // Use the shader
-    pub fn bind(&self) {
-        if self.destroyed {
-            return;
-        }
+    /// The active vertex inputs, found when the shader was linked.
+    pub fn attributes(&self) -> Ref<'_, [ShaderAttribute]> {
+        Ref::map(self.program.borrow(), |p| p.attributes.as_slice())
+    }
 
-        unsafe {
-            self.gl.use_program(Some(self.handle));
-            gl_check_error!(&self.gl);
-        }
+    /// The active uniforms, found when the shader was linked.
+    pub fn uniforms(&self) -> Ref<'_, [ShaderUniform]> {
+        Ref::map(self.program.borrow(), |p| p.uniforms.as_slice())
+    }
+
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.program.borrow().locations.contains_key(name)
     }
 
+    /// Whether the shader was built from the file at `path`.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn reload_shader(gl: GlRef, shader: &mut Shader) -> Result<(), String> {
-        let mut reloaded_shader = Shader::new(gl.clone());
+    pub fn uses(&self, path: &Path) -> bool {
+        let Ok(path) = fs::canonicalize(path) else {
+            return false;
+        };
+        self.sources
+            .iter()
+            .any(|s| fs::canonicalize(s.filepath).is_ok_and(|p| p == path))
+    }
 
-        for s in &shader.sources {
-            let f = s.filepath;
-            let t = s.shader_type;
+    /// The files the shader was built from, to tell shaders apart in messages.
+    pub fn label(&self) -> String {
+        let paths: Vec<&str> = self.sources.iter().map(|s| s.filepath).collect();
+        paths.join(" + ")
+    }
 
-            let source = fs::read_to_string(f).map_err(|e| e.to_string())?;
+    /// What the uniform `name` holds now, `None` when there's no such uniform
+    /// or [`UniformValue`] can't hold its type.
+    pub fn uniform_value(&self, name: &str) -> Option<UniformValue> {
+        let program = self.program.borrow();
+        let (location, kind) = program.locations.get(name)?;
+        UniformValue::read(&self.gl, program.handle, location, *kind)
+    }
 
-            reloaded_shader.add(t, source.as_str(), f)?;
+    /// Set the uniform `name` and keep it that way: the `setUniform*` calls
+    /// leave it alone until [`Shader::clear_override`]. Binds the shader.
+    pub fn override_uniform(&self, name: &str, value: UniformValue) -> Result<(), String> {
+        let program = self.program.borrow();
+        let (location, kind) = program
+            .locations
+            .get(name)
+            .ok_or_else(|| format!("{} has no uniform '{}'", self.label(), name))?;
+        if !value.fits(*kind) {
+            return Err(format!(
+                "Uniform '{}' is a {}, not a {}",
+                name,
+                type_name(*kind),
+                value.type_name()
+            ));
         }
 
-        reloaded_shader.link()?;
+        self.bind();
+        value.upload(&self.gl, location);
+        self.overrides.borrow_mut().insert(name.to_string(), value);
+        Ok(())
+    }
 
-        for (name, _) in &shader.attributes {
-            reloaded_shader.add_attribute(name);
-        }
+    /// Hand the uniform `name` back to the `setUniform*` calls.
+    pub fn clear_override(&self, name: &str) {
+        self.overrides.borrow_mut().remove(name);
+    }
 
-        let old_handle = shader.handle;
-        shader.handle = reloaded_shader.handle;
+    pub fn is_overridden(&self, name: &str) -> bool {
+        self.overrides.borrow().contains_key(name)
+    }
 
-        unsafe {
-            gl.delete_program(old_handle);
+    // Use the shader
+    pub fn bind(&self) {
+        if self.destroyed {
+            return;
         }
 
-        // Prevent the temporary `reloaded_shader` from deleting its program in Drop.
-        // Safety: The handle is passed to the Shader object which will delete the shader on the GPU when Dropped
-        // All sources are deleted after linking.
-        std::mem::forget(reloaded_shader);
-
-        Ok(())
+        unsafe {
+            self.gl.use_program(Some(self.handle()));
+            gl_check_error!(&self.gl);
+        }
     }
 
-    /// Add attribute to the shader
-    pub fn add_attribute(&mut self, name: &'static str) {
-        let loc = self.getAttribLocation(name);
+    /// Build the shader again from its files. On failure the shader keeps the
+    /// program it had, so it can still be drawn with.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload(&self) -> Result<(), ReloadError> {
+        let mut reloaded = Shader::new(self.gl.clone());
+        reloaded.defines = self.defines.clone();
+
+        for s in &self.sources {
+            let source = fs::read_to_string(s.filepath)
+                .map_err(|e| ReloadError::new(self, Some(s.filepath), &e.to_string()))?;
+            reloaded
+                .compile(s.shader_type, &source, s.filepath)
+                .map_err(|e| ReloadError::new(self, Some(s.filepath), &e))?;
+        }
 
-        match loc {
-            Some(loc) => self.attributes.insert(name, loc),
-            None => return,
-        };
+        reloaded
+            .link()
+            .map_err(|e| ReloadError::new(self, None, &e))?;
+
+        // `reloaded` ends up with the old program, and deletes it when dropped
+        self.program.swap(&reloaded.program);
+        self.warned.borrow_mut().clear();
+
+        // Overrides of uniforms that are still there carry over
+        let overrides = self.overrides.take();
+        for (name, value) in overrides {
+            if self.override_uniform(&name, value).is_err() {
+                warn!(
+                    "Dropped the override of uniform '{}' of {}",
+                    name,
+                    self.label()
+                );
+            }
+        }
+
+        Ok(())
     }
 
     /// Remove shader from GPU memory
@@ -162,39 +373,50 @@ impl Shader {
         }
 
         unsafe {
-            self.gl.delete_program(self.handle);
+            self.gl.delete_program(self.handle());
         }
 
         self.destroyed = true;
     }
 
-    fn getAttribLocation(&self, name: &str) -> Option<u32> {
-        unsafe { self.gl.get_attrib_location(self.handle, name) }
-    }
+    /// Set the uniform `name` of the bound program, unless it's overridden.
+    /// Missing uniforms and ones of another type are warned about once.
+    fn set(&self, name: &str, value: UniformValue) {
+        if self.overrides.borrow().contains_key(name) {
+            return;
+        }
 
-    fn getUniformLocation(&self, name: &str) -> Option<UniformLocation> {
-        unsafe { self.gl.get_uniform_location(self.handle, name) }
+        let program = self.program.borrow();
+        let problem = match program.locations.get(name) {
+            Some((location, kind)) if value.fits(*kind) => {
+                value.upload(&self.gl, location);
+                return;
+            }
+            Some((_, kind)) => format!(
+                "Uniform '{}' of {} is a {}, not a {}",
+                name,
+                self.label(),
+                type_name(*kind),
+                value.type_name()
+            ),
+            None => format!("{} has no active uniform '{}'", self.label(), name),
+        };
+
+        if self.warned.borrow_mut().insert(name.to_string()) {
+            warn!("{}", problem);
+        }
     }
 
     pub fn setUniform1i(&self, name: &str, value: i32) {
-        unsafe {
-            self.gl
-                .uniform_1_i32(self.getUniformLocation(name).as_ref(), value);
-        }
+        self.set(name, UniformValue::Int(value));
     }
 
     pub fn setUniform1ui(&self, name: &str, value: u32) {
-        unsafe {
-            self.gl
-                .uniform_1_u32(self.getUniformLocation(name).as_ref(), value);
-        }
+        self.set(name, UniformValue::UInt(value));
     }
 
     pub fn setUniform1f(&self, name: &str, value: f32) {
-        unsafe {
-            self.gl
-                .uniform_1_f32(self.getUniformLocation(name).as_ref(), value);
-        }
+        self.set(name, UniformValue::Float(value));
     }
 
     pub fn setUniform2fv(&self, name: &str, value: &Vec2) {
@@ -202,10 +424,7 @@ impl Shader {
     }
 
     pub fn setUniform2f(&self, name: &str, x: f32, y: f32) {
-        unsafe {
-            self.gl
-                .uniform_2_f32(self.getUniformLocation(name).as_ref(), x, y);
-        }
+        self.set(name, UniformValue::Vec2([x, y]));
     }
 
     pub fn setUniform3fv(&self, name: &str, value: &Vec3) {
@@ -213,10 +432,7 @@ impl Shader {
     }
 
     pub fn setUniform3f(&self, name: &str, x: f32, y: f32, z: f32) {
-        unsafe {
-            self.gl
-                .uniform_3_f32(self.getUniformLocation(name).as_ref(), x, y, z);
-        }
+        self.set(name, UniformValue::Vec3([x, y, z]));
     }
 
     pub fn setUniform4fv(&self, name: &str, value: &Vec4) {
@@ -224,20 +440,15 @@ impl Shader {
     }
 
     pub fn setUniform4f(&self, name: &str, x: f32, y: f32, z: f32, w: f32) {
-        unsafe {
-            self.gl
-                .uniform_4_f32(self.getUniformLocation(name).as_ref(), x, y, z, w);
-        }
+        self.set(name, UniformValue::Vec4([x, y, z, w]));
+    }
+
+    pub fn setUniform3fm(&self, name: &str, mat: &Mat3) {
+        self.set(name, UniformValue::Mat3(mat.to_cols_array()));
     }
 
     pub fn setUniform4fm(&self, name: &str, mat: &Mat4) {
-        unsafe {
-            self.gl.uniform_matrix_4_f32_slice(
-                self.getUniformLocation(name).as_ref(),
-                false,
-                &mat.to_cols_array(),
-            );
-        }
+        self.set(name, UniformValue::Mat4(mat.to_cols_array()));
     }
 }
 
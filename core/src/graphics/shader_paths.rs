@@ -19,6 +19,60 @@ pub mod native {
         include_str!("../../../resources/shaders/native/loaded_obj.vert");
     pub const LOADED_OBJ_FRAG_PATH: &str = "resources/shaders/native/loaded_obj.frag";
     pub const LOADED_OBJ_VERT_PATH: &str = "resources/shaders/native/loaded_obj.vert";
+
+    // Metallic-roughness shader, drawn with the loaded obj vertex shader
+    pub const PBR_FRAG_SRC: &str = include_str!("../../../resources/shaders/native/pbr.frag");
+    pub const PBR_FRAG_PATH: &str = "resources/shaders/native/pbr.frag";
+
+    // Shadow map depth pass shader
+    pub const SHADOW_DEPTH_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/shadow_depth.frag");
+    pub const SHADOW_DEPTH_VERT_SRC: &str =
+        include_str!("../../../resources/shaders/native/shadow_depth.vert");
+    pub const SHADOW_DEPTH_FRAG_PATH: &str = "resources/shaders/native/shadow_depth.frag";
+    pub const SHADOW_DEPTH_VERT_PATH: &str = "resources/shaders/native/shadow_depth.vert";
+
+    // Shadow map debug view shader
+    pub const SHADOW_DEBUG_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/shadow_debug.frag");
+    pub const SHADOW_DEBUG_VERT_SRC: &str =
+        include_str!("../../../resources/shaders/native/shadow_debug.vert");
+    pub const SHADOW_DEBUG_FRAG_PATH: &str = "resources/shaders/native/shadow_debug.frag";
+    pub const SHADOW_DEBUG_VERT_PATH: &str = "resources/shaders/native/shadow_debug.vert";
+
+    // Selection highlight, drawn over the selected object
+    pub const HIGHLIGHT_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/highlight.frag");
+    pub const HIGHLIGHT_VERT_SRC: &str =
+        include_str!("../../../resources/shaders/native/highlight.vert");
+    pub const HIGHLIGHT_FRAG_PATH: &str = "resources/shaders/native/highlight.frag";
+    pub const HIGHLIGHT_VERT_PATH: &str = "resources/shaders/native/highlight.vert";
+
+    // Copies the reverse-Z render target to the window, drawn with the shadow debug vertex shader
+    pub const PRESENT_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/present.frag");
+    pub const PRESENT_FRAG_PATH: &str = "resources/shaders/native/present.frag";
+
+    // Environment cubemap, used by the skybox and every baking pass
+    pub const CUBEMAP_VERT_SRC: &str =
+        include_str!("../../../resources/shaders/native/cubemap.vert");
+    pub const CUBEMAP_VERT_PATH: &str = "resources/shaders/native/cubemap.vert";
+    pub const SKYBOX_FRAG_SRC: &str = include_str!("../../../resources/shaders/native/skybox.frag");
+    pub const SKYBOX_FRAG_PATH: &str = "resources/shaders/native/skybox.frag";
+
+    // Image based lighting baking passes
+    pub const EQUIRECT_TO_CUBE_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/equirect_to_cube.frag");
+    pub const EQUIRECT_TO_CUBE_FRAG_PATH: &str = "resources/shaders/native/equirect_to_cube.frag";
+    pub const IRRADIANCE_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/irradiance.frag");
+    pub const IRRADIANCE_FRAG_PATH: &str = "resources/shaders/native/irradiance.frag";
+    pub const PREFILTER_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/prefilter.frag");
+    pub const PREFILTER_FRAG_PATH: &str = "resources/shaders/native/prefilter.frag";
+    pub const BRDF_LUT_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/native/brdf_lut.frag");
+    pub const BRDF_LUT_FRAG_PATH: &str = "resources/shaders/native/brdf_lut.frag";
 }
 
 // ============================================================================
@@ -42,6 +96,54 @@ pub mod web {
         include_str!("../../../resources/shaders/web/loaded_obj.vert");
     pub const LOADED_OBJ_FRAG_PATH: &str = "resources/shaders/web/loaded_obj.frag";
     pub const LOADED_OBJ_VERT_PATH: &str = "resources/shaders/web/loaded_obj.vert";
+
+    // Metallic-roughness shader, drawn with the loaded obj vertex shader
+    pub const PBR_FRAG_SRC: &str = include_str!("../../../resources/shaders/web/pbr.frag");
+    pub const PBR_FRAG_PATH: &str = "resources/shaders/web/pbr.frag";
+
+    // Shadow map depth pass shader
+    pub const SHADOW_DEPTH_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/web/shadow_depth.frag");
+    pub const SHADOW_DEPTH_VERT_SRC: &str =
+        include_str!("../../../resources/shaders/web/shadow_depth.vert");
+    pub const SHADOW_DEPTH_FRAG_PATH: &str = "resources/shaders/web/shadow_depth.frag";
+    pub const SHADOW_DEPTH_VERT_PATH: &str = "resources/shaders/web/shadow_depth.vert";
+
+    // Shadow map debug view shader
+    pub const SHADOW_DEBUG_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/web/shadow_debug.frag");
+    pub const SHADOW_DEBUG_VERT_SRC: &str =
+        include_str!("../../../resources/shaders/web/shadow_debug.vert");
+    pub const SHADOW_DEBUG_FRAG_PATH: &str = "resources/shaders/web/shadow_debug.frag";
+    pub const SHADOW_DEBUG_VERT_PATH: &str = "resources/shaders/web/shadow_debug.vert";
+
+    // Selection highlight, drawn over the selected object
+    pub const HIGHLIGHT_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/web/highlight.frag");
+    pub const HIGHLIGHT_VERT_SRC: &str =
+        include_str!("../../../resources/shaders/web/highlight.vert");
+    pub const HIGHLIGHT_FRAG_PATH: &str = "resources/shaders/web/highlight.frag";
+    pub const HIGHLIGHT_VERT_PATH: &str = "resources/shaders/web/highlight.vert";
+
+    // Environment cubemap, used by the skybox and every baking pass
+    pub const CUBEMAP_VERT_SRC: &str = include_str!("../../../resources/shaders/web/cubemap.vert");
+    pub const CUBEMAP_VERT_PATH: &str = "resources/shaders/web/cubemap.vert";
+    pub const SKYBOX_FRAG_SRC: &str = include_str!("../../../resources/shaders/web/skybox.frag");
+    pub const SKYBOX_FRAG_PATH: &str = "resources/shaders/web/skybox.frag";
+
+    // Image based lighting baking passes
+    pub const EQUIRECT_TO_CUBE_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/web/equirect_to_cube.frag");
+    pub const EQUIRECT_TO_CUBE_FRAG_PATH: &str = "resources/shaders/web/equirect_to_cube.frag";
+    pub const IRRADIANCE_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/web/irradiance.frag");
+    pub const IRRADIANCE_FRAG_PATH: &str = "resources/shaders/web/irradiance.frag";
+    pub const PREFILTER_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/web/prefilter.frag");
+    pub const PREFILTER_FRAG_PATH: &str = "resources/shaders/web/prefilter.frag";
+    pub const BRDF_LUT_FRAG_SRC: &str =
+        include_str!("../../../resources/shaders/web/brdf_lut.frag");
+    pub const BRDF_LUT_FRAG_PATH: &str = "resources/shaders/web/brdf_lut.frag";
 }
 
 // ============================================================================
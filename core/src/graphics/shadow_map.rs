@@ -0,0 +1,133 @@
+use glow::{Framebuffer as GlowFramebuffer, HasContext, Texture as GlowTexture};
+
+use crate::gl_check_error;
+use crate::graphics::GlRef;
+
+/// A square depth texture that a light's view of the scene is rendered into.
+///
+/// It's set up for `sampler2DShadow`, so sampling compares against the stored
+/// depth and filters the result.
+pub struct ShadowMap {
+    gl: GlRef,
+    framebuffer: GlowFramebuffer,
+    texture: GlowTexture,
+    pub resolution: u32,
+}
+
+impl ShadowMap {
+    pub fn new(gl: GlRef, resolution: u32) -> Result<Self, String> {
+        if resolution == 0 {
+            return Err("Invalid shadow map resolution 0".to_string());
+        }
+
+        unsafe {
+            let texture = gl.create_texture()?;
+            let framebuffer = gl.create_framebuffer()?;
+
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::DEPTH_COMPONENT24 as i32,
+                resolution as i32,
+                resolution as i32,
+                0,
+                glow::DEPTH_COMPONENT,
+                glow::UNSIGNED_INT,
+                glow::PixelUnpackData::Slice(None),
+            );
+            let clamp = glow::CLAMP_TO_EDGE as i32;
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, clamp);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, clamp);
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            // No color attachment to draw to or read from
+            gl.draw_buffers(&[glow::NONE]);
+            gl.read_buffer(glow::NONE);
+
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            gl_check_error!(gl);
+
+            let map = Self {
+                gl,
+                framebuffer,
+                texture,
+                resolution,
+            };
+            map.set_compare(true);
+
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                return Err(format!(
+                    "Shadow map framebuffer is incomplete (status 0x{:x})",
+                    status
+                ));
+            }
+
+            Ok(map)
+        }
+    }
+
+    /// Draw into the depth texture, covering all of it. The caller restores the
+    /// framebuffer and viewport it had.
+    pub fn bind(&self) {
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            self.gl
+                .viewport(0, 0, self.resolution as i32, self.resolution as i32);
+        }
+    }
+
+    /// Bind the depth texture to a specific texture unit
+    pub fn bind_unit(&self, unit: i32) {
+        unsafe {
+            self.gl.active_texture(glow::TEXTURE0 + unit as u32);
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+        }
+    }
+
+    /// Whether sampling compares depths, for `sampler2DShadow`, or reads them
+    /// as they are, for a plain `sampler2D`.
+    pub fn set_compare(&self, compare: bool) {
+        // Raw depth can't be filtered linearly everywhere
+        let (mode, filter) = if compare {
+            (glow::COMPARE_REF_TO_TEXTURE, glow::LINEAR)
+        } else {
+            (glow::NONE, glow::NEAREST)
+        };
+
+        unsafe {
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_COMPARE_MODE, mode as i32);
+            self.gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_COMPARE_FUNC,
+                glow::LEQUAL as i32,
+            );
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter as i32);
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter as i32);
+            self.gl.bind_texture(glow::TEXTURE_2D, None);
+        }
+    }
+}
+
+impl Drop for ShadowMap {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_framebuffer(self.framebuffer);
+            self.gl.delete_texture(self.texture);
+        }
+    }
+}
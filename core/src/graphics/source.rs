@@ -32,9 +32,11 @@ impl ShaderSource {
 
             gl_check_error!(&renderer);
 
+            // The caller knows which file it was, the log goes out as it is
             if !renderer.get_shader_compile_status(shader) {
                 let e = renderer.get_shader_info_log(shader);
-                return Err(format!("Unable to compile shader '{filepath}': {e}"));
+                renderer.delete_shader(shader);
+                return Err(e);
             }
 
             renderer.attach_shader(program, shader);
@@ -1,16 +1,127 @@
+use glam::{Vec2, Vec3, Vec4};
 use glow::{HasContext, Texture as GlowTexture};
 #[cfg(not(target_arch = "wasm32"))]
 use image::ImageReader;
+use image::imageops;
+use image::{Rgba32FImage, RgbaImage};
+use std::f32::consts::PI;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::path::PathBuf;
 
 use crate::graphics::GlRef;
+use crate::loaders::TextureSource;
 
 #[derive(Clone)]
 pub struct Texture {
-    gl: GlRef,
+    pub(crate) gl: GlRef,
     pub(crate) handle: GlowTexture,
+    pub kind: TextureKind,
     pub unit: i32,
+    /// Size of one face for cubemaps.
     pub width: u32,
     pub height: u32,
+    /// The image file it was loaded from, if it came from one.
+    pub path: Option<PathBuf>,
+}
+
+/// What a texture is bound as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureKind {
+    #[default]
+    Texture2D,
+    /// Six square faces, sampled by direction with a `samplerCube`.
+    Cubemap,
+}
+
+impl TextureKind {
+    pub fn target(&self) -> u32 {
+        match self {
+            TextureKind::Texture2D => glow::TEXTURE_2D,
+            TextureKind::Cubemap => glow::TEXTURE_CUBE_MAP,
+        }
+    }
+}
+
+/// File names [`CubemapLayout::from_path`] looks for in a directory, one list
+/// per face in +X, -X, +Y, -Y, +Z, -Z order.
+#[cfg(not(target_arch = "wasm32"))]
+const CUBEMAP_FACE_NAMES: [&[&str]; 6] = [
+    &["px", "posx", "right"],
+    &["nx", "negx", "left"],
+    &["py", "posy", "top", "up"],
+    &["ny", "negy", "bottom", "down"],
+    &["pz", "posz", "front"],
+    &["nz", "negz", "back"],
+];
+
+/// Where the six faces of a cubemap come from.
+///
+/// Faces follow the GL convention: +X, -X, +Y, -Y, +Z, -Z, each seen from
+/// inside the cube with its top row first.
+pub enum CubemapLayout {
+    /// One image per face, in +X, -X, +Y, -Y, +Z, -Z order.
+    Faces([TextureSource; 6]),
+    /// One image with the faces in a cross, either 4 faces wide and 3 high with
+    /// -X, +Z, +X, -Z in the middle row, or 3 wide and 4 high with -Z upside
+    /// down at the bottom.
+    Cross(TextureSource),
+    /// One equirectangular image, twice as wide as it is high.
+    Equirect(TextureSource),
+}
+
+impl CubemapLayout {
+    /// Six face images for a directory, named `px`, `nx`, ... or `right`, `left`,
+    /// ..., otherwise a cross or an equirectangular image going by its shape.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        if path.is_dir() {
+            return Self::find_faces(path);
+        }
+
+        let (width, height) = image::image_dimensions(path)
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        let source = TextureSource::File(path.to_path_buf());
+
+        if width == 2 * height {
+            Ok(CubemapLayout::Equirect(source))
+        } else if width * 3 == height * 4 || width * 4 == height * 3 {
+            Ok(CubemapLayout::Cross(source))
+        } else {
+            Err(format!(
+                "'{}' is {}x{}, neither a cross (4:3 or 3:4) nor equirectangular (2:1)",
+                path.display(),
+                width,
+                height
+            ))
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn find_faces(dir: &Path) -> Result<Self, String> {
+        let entries: Vec<_> = std::fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read '{}': {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file())
+            .collect();
+
+        let mut faces = Vec::with_capacity(6);
+        for names in CUBEMAP_FACE_NAMES {
+            let face = entries
+                .iter()
+                .find(|path| {
+                    path.file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .is_some_and(|stem| names.contains(&stem.to_lowercase().as_str()))
+                })
+                .ok_or_else(|| format!("'{}' has no '{}' face image", dir.display(), names[0]))?;
+            faces.push(TextureSource::File(face.clone()));
+        }
+
+        Ok(CubemapLayout::Faces(
+            faces.try_into().expect("One source per face"),
+        ))
+    }
 }
 
 #[derive(Clone, Copy, Default)]
@@ -63,28 +174,125 @@ impl TextureBuilder {
     /// Load texture from file path (not supported on WASM - use load_bytes instead)
     #[cfg(not(target_arch = "wasm32"))]
     pub fn load_file(self, path: &str) -> Result<Texture, String> {
-        let img = ImageReader::open(path)
-            .map_err(|e| format!("Failed to open '{}': {}", path, e))?
-            .decode()
-            .map_err(|e| format!("Failed to decode '{}': {}", path, e))?
-            .to_rgba8();
+        let img = decode_file(path)?;
+        self.load_rgba(img.as_raw(), img.width(), img.height())
+    }
 
-        self.load_rgba(&img.as_raw(), img.width(), img.height())
+    /// Load texture from an imported model's texture reference
+    pub fn load_source(self, source: &TextureSource) -> Result<Texture, String> {
+        let img = decode_source(source)?;
+        let mut texture = self.load_rgba(img.as_raw(), img.width(), img.height())?;
+        if let TextureSource::File(path) = source {
+            texture.path = Some(path.clone());
+        }
+        Ok(texture)
     }
 
     /// Load texture from embedded bytes (works on all platforms including WASM)
     pub fn load_bytes(self, data: &[u8]) -> Result<Texture, String> {
-        let img = image::load_from_memory(data)
-            .map_err(|e| format!("Failed to decode image: {}", e))?
-            .to_rgba8();
+        let img = decode_bytes(data)?;
+        self.load_rgba(img.as_raw(), img.width(), img.height())
+    }
+
+    /// Load a cubemap. Crosses and equirectangular images are cut into faces
+    /// on the CPU, so this works the same on every platform.
+    pub fn load_cubemap(self, layout: &CubemapLayout) -> Result<Texture, String> {
+        let faces = match layout {
+            CubemapLayout::Faces(sources) => sources
+                .iter()
+                .map(decode_source)
+                .collect::<Result<Vec<_>, _>>()?,
+            CubemapLayout::Cross(source) => cross_faces(&decode_source(source)?)?,
+            CubemapLayout::Equirect(source) => equirect_faces(&decode_source(source)?)?,
+        };
+
+        let size = faces[0].width();
+        if let Some(face) = faces
+            .iter()
+            .find(|face| face.width() != size || face.height() != size)
+        {
+            return Err(format!(
+                "Cubemap faces must all be the same square size, found {}x{} and {}x{}",
+                size,
+                faces[0].height(),
+                face.width(),
+                face.height()
+            ));
+        }
+
+        let faces: Vec<&[u8]> = faces.iter().map(|face| face.as_raw().as_slice()).collect();
+        self.load_cubemap_rgba(&faces, size)
+    }
+
+    /// Load a cubemap from six square faces of raw RGBA bytes, in +X, -X, +Y, -Y, +Z, -Z order
+    pub fn load_cubemap_rgba(self, faces: &[&[u8]], size: u32) -> Result<Texture, String> {
+        if faces.len() != 6 || size == 0 {
+            return Err(format!(
+                "A cubemap needs 6 faces of at least 1x1, got {} of {}x{}",
+                faces.len(),
+                size,
+                size
+            ));
+        }
+
+        unsafe {
+            let texture = self.gl.create_texture()?;
+
+            self.gl.active_texture(glow::TEXTURE0 + self.unit as u32);
+            self.gl.bind_texture(glow::TEXTURE_CUBE_MAP, Some(texture));
+
+            for (face, data) in faces.iter().enumerate() {
+                self.gl.tex_image_2d(
+                    glow::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                    0,
+                    glow::RGBA8 as i32,
+                    size as i32,
+                    size as i32,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    glow::PixelUnpackData::Slice(Some(data)),
+                );
+            }
+
+            // Anything but clamping shows the seams between faces
+            let filter = self.filter_param();
+            let clamp = glow::CLAMP_TO_EDGE as i32;
+            let target = glow::TEXTURE_CUBE_MAP;
+            self.gl
+                .tex_parameter_i32(target, glow::TEXTURE_MIN_FILTER, filter);
+            self.gl
+                .tex_parameter_i32(target, glow::TEXTURE_MAG_FILTER, filter);
+            self.gl
+                .tex_parameter_i32(target, glow::TEXTURE_WRAP_S, clamp);
+            self.gl
+                .tex_parameter_i32(target, glow::TEXTURE_WRAP_T, clamp);
+            self.gl
+                .tex_parameter_i32(target, glow::TEXTURE_WRAP_R, clamp);
+
+            Ok(Texture {
+                gl: self.gl,
+                handle: texture,
+                kind: TextureKind::Cubemap,
+                unit: self.unit,
+                width: size,
+                height: size,
+                path: None,
+            })
+        }
+    }
 
-        self.load_rgba(&img.as_raw(), img.width(), img.height())
+    fn filter_param(&self) -> i32 {
+        match self.filter {
+            FilterMode::Nearest => glow::NEAREST as i32,
+            FilterMode::Linear => glow::LINEAR as i32,
+        }
     }
 
     /// Load texture from raw RGBA bytes
     pub fn load_rgba(self, data: &[u8], width: u32, height: u32) -> Result<Texture, String> {
         unsafe {
-            let texture = self.gl.create_texture().map_err(|e| e)?;
+            let texture = self.gl.create_texture()?;
             let pixels = glow::PixelUnpackData::Slice(Some(data));
 
             self.gl.active_texture(glow::TEXTURE0 + self.unit as u32);
@@ -102,10 +310,7 @@ impl TextureBuilder {
                 pixels,
             );
 
-            let filter = match self.filter {
-                FilterMode::Nearest => glow::NEAREST as i32,
-                FilterMode::Linear => glow::LINEAR as i32,
-            };
+            let filter = self.filter_param();
 
             let wrap = match self.wrap {
                 WrapMode::Repeat => glow::REPEAT as i32,
@@ -125,9 +330,11 @@ impl TextureBuilder {
             Ok(Texture {
                 gl: self.gl,
                 handle: texture,
+                kind: TextureKind::Texture2D,
                 unit: self.unit,
                 width,
                 height,
+                path: None,
             })
         }
     }
@@ -161,9 +368,141 @@ impl Texture {
 
     /// Bind this texture to its assigned texture unit
     pub fn bind(&self) {
+        self.bind_unit(self.unit);
+    }
+
+    /// Bind this texture to a specific texture unit
+    pub fn bind_unit(&self, unit: i32) {
         unsafe {
-            self.gl.active_texture(glow::TEXTURE0 + self.unit as u32);
-            self.gl.bind_texture(glow::TEXTURE_2D, Some(self.handle));
+            self.gl.active_texture(glow::TEXTURE0 + unit as u32);
+            self.gl.bind_texture(self.kind.target(), Some(self.handle));
         }
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn decode_file(path: &str) -> Result<RgbaImage, String> {
+    Ok(ImageReader::open(path)
+        .map_err(|e| format!("Failed to open '{}': {}", path, e))?
+        .decode()
+        .map_err(|e| format!("Failed to decode '{}': {}", path, e))?
+        .to_rgba8())
+}
+
+fn decode_bytes(data: &[u8]) -> Result<RgbaImage, String> {
+    Ok(image::load_from_memory(data)
+        .map_err(|e| format!("Failed to decode image: {}", e))?
+        .to_rgba8())
+}
+
+fn decode_source(source: &TextureSource) -> Result<RgbaImage, String> {
+    match source {
+        #[cfg(not(target_arch = "wasm32"))]
+        TextureSource::File(path) => decode_file(&path.to_string_lossy()),
+        #[cfg(target_arch = "wasm32")]
+        TextureSource::File(path) => Err(format!(
+            "Cannot read '{}' on WASM, embed the image instead",
+            path.display()
+        )),
+        TextureSource::Bytes(data) => decode_bytes(data),
+    }
+}
+
+/// Cut the six faces out of a cross, in +X, -X, +Y, -Y, +Z, -Z order.
+fn cross_faces(image: &RgbaImage) -> Result<Vec<RgbaImage>, String> {
+    let (width, height) = image.dimensions();
+    let crop = |column: u32, row: u32, size: u32| {
+        imageops::crop_imm(image, column * size, row * size, size, size).to_image()
+    };
+
+    if width * 3 == height * 4 {
+        let size = width / 4;
+        Ok(vec![
+            crop(2, 1, size),
+            crop(0, 1, size),
+            crop(1, 0, size),
+            crop(1, 2, size),
+            crop(1, 1, size),
+            crop(3, 1, size),
+        ])
+    } else if width * 4 == height * 3 {
+        let size = width / 3;
+        Ok(vec![
+            crop(2, 1, size),
+            crop(0, 1, size),
+            crop(1, 0, size),
+            crop(1, 2, size),
+            crop(1, 1, size),
+            // Folding the cross into a cube turns the last face over
+            imageops::rotate180(&crop(1, 3, size)),
+        ])
+    } else {
+        Err(format!(
+            "A {}x{} image isn't a cross, those are 4:3 or 3:4",
+            width, height
+        ))
+    }
+}
+
+/// Resample an equirectangular image into six faces a quarter of its width.
+fn equirect_faces(image: &RgbaImage) -> Result<Vec<RgbaImage>, String> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Err("Equirectangular image is empty".to_string());
+    }
+
+    // Filtered in float so the bilinear taps below are simple
+    let source: Rgba32FImage = image::DynamicImage::ImageRgba8(image.clone()).to_rgba32f();
+    let size = (width / 4).max(1);
+
+    let sample = |uv: Vec2| -> Vec4 {
+        let x = uv.x * width as f32 - 0.5;
+        let y = (uv.y * height as f32 - 0.5).clamp(0.0, height as f32 - 1.0);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let texel = |x: f32, y: f32| {
+            // Wraps around horizontally, stops at the poles
+            let x = (x as i64).rem_euclid(width as i64) as u32;
+            let y = (y.max(0.0) as u32).min(height - 1);
+            Vec4::from(source.get_pixel(x, y).0)
+        };
+
+        let top = texel(x0, y0).lerp(texel(x0 + 1.0, y0), fx);
+        let bottom = texel(x0, y0 + 1.0).lerp(texel(x0 + 1.0, y0 + 1.0), fx);
+        top.lerp(bottom, fy)
+    };
+
+    let faces = (0..6)
+        .map(|face| {
+            RgbaImage::from_fn(size, size, |x, y| {
+                let s = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let t = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let dir = cube_direction(face, s, t).normalize();
+
+                // Same mapping as `equirect_to_cube.frag`
+                let uv = Vec2::new(
+                    dir.z.atan2(dir.x) / (2.0 * PI) + 0.5,
+                    0.5 - dir.y.clamp(-1.0, 1.0).asin() / PI,
+                );
+                let color = sample(uv).clamp(Vec4::ZERO, Vec4::ONE) * 255.0 + 0.5;
+                image::Rgba(color.to_array().map(|c| c as u8))
+            })
+        })
+        .collect();
+
+    Ok(faces)
+}
+
+/// Direction through the point `s`, `t` (both -1 to 1, `t` down) of a cubemap
+/// face, as the GL spec lays them out.
+fn cube_direction(face: u32, s: f32, t: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    }
+}
@@ -8,10 +8,14 @@ use winit::window::Window;
 
 use super::{Shader, Texture};
 use crate::game::{GameObject, GlobalLight, Physical, Renderable};
+use crate::surface::Surface;
 
 /// Reference-counted pointer to a Window
 pub type WindowRef = Rc<Window>;
 
+/// Reference-counted pointer to whatever the viewport draws into
+pub type SurfaceRef = Rc<dyn Surface>;
+
 /// Reference-counted pointer to a GL context
 pub type GlRef = Rc<Context>;
 
@@ -1,17 +1,18 @@
 use bytemuck::{Pod, Zeroable};
 use glam::{Vec2, Vec3};
 
-pub const VEC3: i32 = 3;
-pub const VEC2: i32 = 2;
+use crate::graphics::{ComponentType, Semantic, VertexLayout};
 
-/// A vertex with position, color, normal, and texture coordinates.
+/// A vertex with position, color, normal, texture coordinates, and tangent.
 ///
 /// Memory layout is `#[repr(C)]` for GPU compatibility:
 /// - `position`: 12 bytes (3 × f32)
 /// - `color`: 12 bytes (3 × f32)
 /// - `normal`: 12 bytes (3 × f32)
 /// - `tex_coords`: 8 bytes (2 × f32)
-/// - Total stride: 44 bytes
+/// - `tangent`: 12 bytes (3 × f32)
+/// - `handedness`: 4 bytes (f32), read together with `tangent` as a `vec4`
+/// - Total stride: 60 bytes
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct Vertex {
@@ -19,9 +20,48 @@ pub struct Vertex {
     pub color: Vec3,
     pub normal: Vec3,
     pub tex_coords: Vec2,
+    /// Zero when the mesh has no tangents.
+    pub tangent: Vec3,
+    /// What `normal × tangent` is multiplied by to get the bitangent, 1 or -1.
+    pub handedness: f32,
 }
 
 impl Vertex {
+    /// Every field as floats, the way they are in memory.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new()
+            .with(Semantic::Position, ComponentType::F32, 3, false)
+            .with(Semantic::Color, ComponentType::F32, 3, false)
+            .with(Semantic::Normal, ComponentType::F32, 3, false)
+            .with(Semantic::TexCoord0, ComponentType::F32, 2, false)
+            .with(Semantic::Tangent, ComponentType::F32, 4, false)
+    }
+
+    /// [`Vertex::layout`] without the fields every one of `vertices` leaves at
+    /// what shaders read when the attribute is missing, like white colors or
+    /// zero texture coordinates.
+    pub fn compact_layout(vertices: &[Vertex]) -> VertexLayout {
+        Self::layout().filtered(|attribute| {
+            let semantic = attribute.semantic;
+            semantic == Semantic::Position
+                || vertices
+                    .iter()
+                    .any(|v| v.attribute(semantic) != semantic.default_value())
+        })
+    }
+
+    /// The field for `semantic` as four floats, `None` if `Vertex` has no such field.
+    pub fn attribute(&self, semantic: Semantic) -> Option<[f32; 4]> {
+        match semantic {
+            Semantic::Position => Some(self.position.extend(1.0).into()),
+            Semantic::Color => Some(self.color.extend(1.0).into()),
+            Semantic::Normal => Some(self.normal.extend(1.0).into()),
+            Semantic::TexCoord0 => Some(self.tex_coords.extend(0.0).extend(1.0).into()),
+            Semantic::Tangent => Some(self.tangent.extend(self.handedness).into()),
+            _ => None,
+        }
+    }
+
     /// Creates a new vertex with position, normal, color, and texture.
     pub fn new(position: Vec3, color: Vec3, normal: Vec3, tex_coords: Vec2) -> Self {
         Self {
@@ -29,6 +69,8 @@ impl Vertex {
             color,
             normal,
             tex_coords,
+            tangent: Vec3::ZERO,
+            handedness: 1.0,
         }
     }
 
@@ -39,6 +81,8 @@ impl Vertex {
             color,
             normal,
             tex_coords: Vec2::ZERO,
+            tangent: Vec3::ZERO,
+            handedness: 1.0,
         }
     }
 
@@ -49,6 +93,8 @@ impl Vertex {
             color: Vec3::ONE,
             normal,
             tex_coords,
+            tangent: Vec3::ZERO,
+            handedness: 1.0,
         }
     }
 
@@ -80,6 +126,8 @@ impl Default for Vertex {
             color: Vec3::ONE,
             normal: Vec3::ZERO,
             tex_coords: Vec2::ZERO,
+            tangent: Vec3::ZERO,
+            handedness: 1.0,
         }
     }
 }
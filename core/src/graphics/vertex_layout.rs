@@ -0,0 +1,372 @@
+use bytemuck::{Pod, cast_slice};
+
+/// What a vertex attribute means. Shaders declare each one under a fixed name,
+/// see [`Semantic::attribute_name`], and meshes are bound to them by semantic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Semantic {
+    Position,
+    Color,
+    Normal,
+    TexCoord0,
+    Tangent,
+    TexCoord1,
+    Joints,
+    Weights,
+}
+
+impl Semantic {
+    pub const ALL: [Semantic; 8] = [
+        Semantic::Position,
+        Semantic::Color,
+        Semantic::Normal,
+        Semantic::TexCoord0,
+        Semantic::Tangent,
+        Semantic::TexCoord1,
+        Semantic::Joints,
+        Semantic::Weights,
+    ];
+
+    /// Name of the shader input the attribute is read through.
+    pub fn attribute_name(&self) -> &'static str {
+        match self {
+            Semantic::Position => "i_position",
+            Semantic::Color => "i_color",
+            Semantic::Normal => "i_normal",
+            Semantic::TexCoord0 => "i_uv",
+            Semantic::Tangent => "i_tangent",
+            Semantic::TexCoord1 => "i_uv1",
+            Semantic::Joints => "i_joints",
+            Semantic::Weights => "i_weights",
+        }
+    }
+
+    /// Location every shader reads the attribute from, the same as the
+    /// `layout(location)`s of the shaders that spell it out.
+    pub fn location(&self) -> u32 {
+        *self as u32
+    }
+
+    pub fn from_attribute_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.attribute_name() == name)
+    }
+
+    /// What a shader reads for the attribute when the mesh doesn't have it,
+    /// `None` for integer attributes, which have no sensible default.
+    pub fn default_value(&self) -> Option<[f32; 4]> {
+        match self {
+            // White, so the vertex color leaves the material's alone
+            Semantic::Color => Some([1.0, 1.0, 1.0, 1.0]),
+            // A zero tangent makes the shaders build their own
+            Semantic::Tangent => Some([0.0, 0.0, 0.0, 1.0]),
+            Semantic::Joints => None,
+            _ => Some([0.0, 0.0, 0.0, 1.0]),
+        }
+    }
+}
+
+/// Type of each component of a vertex attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentType {
+    F32,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+}
+
+impl ComponentType {
+    pub fn size(&self) -> i32 {
+        match self {
+            ComponentType::U8 | ComponentType::I8 => 1,
+            ComponentType::U16 | ComponentType::I16 => 2,
+            ComponentType::F32 | ComponentType::U32 => 4,
+        }
+    }
+
+    pub fn gl_type(&self) -> u32 {
+        match self {
+            ComponentType::F32 => glow::FLOAT,
+            ComponentType::U8 => glow::UNSIGNED_BYTE,
+            ComponentType::I8 => glow::BYTE,
+            ComponentType::U16 => glow::UNSIGNED_SHORT,
+            ComponentType::I16 => glow::SHORT,
+            ComponentType::U32 => glow::UNSIGNED_INT,
+        }
+    }
+
+    /// Write `value` as this type, scaled to the type's range when `normalized`.
+    fn write(&self, value: f32, normalized: bool, out: &mut Vec<u8>) {
+        let scaled = |max: f32| {
+            if normalized {
+                (value.clamp(-1.0, 1.0) * max).round()
+            } else {
+                value.round()
+            }
+        };
+
+        match self {
+            ComponentType::F32 => out.extend_from_slice(&value.to_le_bytes()),
+            ComponentType::U8 => out.push(scaled(u8::MAX as f32).max(0.0) as u8),
+            ComponentType::I8 => {
+                out.extend_from_slice(&(scaled(i8::MAX as f32) as i8).to_le_bytes())
+            }
+            ComponentType::U16 => {
+                out.extend_from_slice(&(scaled(u16::MAX as f32).max(0.0) as u16).to_le_bytes())
+            }
+            ComponentType::I16 => {
+                out.extend_from_slice(&(scaled(i16::MAX as f32) as i16).to_le_bytes())
+            }
+            ComponentType::U32 => out.extend_from_slice(&(value.max(0.0) as u32).to_le_bytes()),
+        }
+    }
+}
+
+/// One attribute in a [`VertexLayout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub semantic: Semantic,
+    pub component: ComponentType,
+    /// Components per vertex, 1 to 4.
+    pub count: i32,
+    /// Integers are read as floats in 0..1 (or -1..1 when signed) instead of as they are.
+    pub normalized: bool,
+    /// Byte offset from the start of the vertex.
+    pub offset: i32,
+}
+
+impl VertexAttribute {
+    pub fn size(&self) -> i32 {
+        self.component.size() * self.count
+    }
+
+    /// Whether shaders read it as integers, with `ivec` or `uvec` inputs.
+    pub fn is_integer(&self) -> bool {
+        self.component != ComponentType::F32 && !self.normalized
+    }
+}
+
+/// How the attributes of one vertex are laid out in a buffer.
+///
+/// Attributes are added in order, each starting where the previous one ended.
+/// GL wants every attribute, and the stride, aligned to its component size, so
+/// there may be padding:
+///
+/// ```ignore
+/// let layout = VertexLayout::new()
+///     .with(Semantic::Position, ComponentType::F32, 3, false)
+///     .with(Semantic::Color, ComponentType::U8, 4, true);
+/// assert_eq!(layout.stride, 16);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VertexLayout {
+    pub attributes: Vec<VertexAttribute>,
+    /// Bytes from one vertex to the next.
+    pub stride: i32,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an attribute after the ones already there.
+    pub fn with(
+        mut self,
+        semantic: Semantic,
+        component: ComponentType,
+        count: i32,
+        normalized: bool,
+    ) -> Self {
+        let align = |bytes: i32, to: i32| (bytes + to - 1) / to * to;
+
+        let end = self.attributes.last().map_or(0, |a| a.offset + a.size());
+        let offset = align(end, component.size());
+        self.attributes.push(VertexAttribute {
+            semantic,
+            component,
+            count,
+            normalized,
+            offset,
+        });
+
+        let largest = self
+            .attributes
+            .iter()
+            .map(|a| a.component.size())
+            .max()
+            .unwrap_or(1);
+        self.stride = align(offset + component.size() * count, largest);
+        self
+    }
+
+    pub fn attribute(&self, semantic: Semantic) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|a| a.semantic == semantic)
+    }
+
+    pub fn contains(&self, semantic: Semantic) -> bool {
+        self.attribute(semantic).is_some()
+    }
+
+    /// The same attributes minus the ones `keep` turns down, packed together again.
+    pub fn filtered(&self, keep: impl Fn(&VertexAttribute) -> bool) -> Self {
+        self.attributes
+            .iter()
+            .filter(|a| keep(a))
+            .fold(Self::new(), |layout, a| {
+                layout.with(a.semantic, a.component, a.count, a.normalized)
+            })
+    }
+
+    /// Pack `count` vertices, `value` giving each of a vertex's attributes as
+    /// floats, which are converted to the attribute's type.
+    pub fn pack(&self, count: usize, value: impl Fn(usize, Semantic) -> [f32; 4]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(count * self.stride as usize);
+        for index in 0..count {
+            let start = data.len();
+            for attribute in &self.attributes {
+                data.resize(start + attribute.offset as usize, 0);
+                let value = value(index, attribute.semantic);
+                for &component in &value[..attribute.count as usize] {
+                    attribute
+                        .component
+                        .write(component, attribute.normalized, &mut data);
+                }
+            }
+            data.resize(start + self.stride as usize, 0);
+        }
+        data
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        for attribute in &self.attributes {
+            if !(1..=4).contains(&attribute.count) {
+                return Err(format!(
+                    "{:?} has {} components, attributes have 1 to 4",
+                    attribute.semantic, attribute.count
+                ));
+            }
+            if attribute.offset + attribute.size() > self.stride {
+                return Err(format!(
+                    "{:?} ends past the {} byte stride",
+                    attribute.semantic, self.stride
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Raw vertex data for one GL buffer, either several attributes interleaved
+/// or a single one.
+#[derive(Clone, Debug)]
+pub struct VertexBuffer {
+    pub layout: VertexLayout,
+    pub data: Vec<u8>,
+}
+
+impl VertexBuffer {
+    pub fn new(layout: VertexLayout, data: Vec<u8>) -> Result<Self, String> {
+        layout.validate()?;
+        if layout.stride == 0 || !data.len().is_multiple_of(layout.stride as usize) {
+            return Err(format!(
+                "{} bytes of vertex data don't divide into {} byte vertices",
+                data.len(),
+                layout.stride
+            ));
+        }
+        Ok(Self { layout, data })
+    }
+
+    /// A buffer holding a single attribute, one `T` per vertex.
+    pub fn from_slice<T: Pod>(
+        semantic: Semantic,
+        component: ComponentType,
+        count: i32,
+        normalized: bool,
+        values: &[T],
+    ) -> Result<Self, String> {
+        let layout = VertexLayout::new().with(semantic, component, count, normalized);
+        Self::new(layout, cast_slice(values).to_vec())
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.data.len() / self.layout.stride as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_are_aligned() {
+        let layout = VertexLayout::new()
+            .with(Semantic::Color, ComponentType::U8, 3, true)
+            .with(Semantic::Position, ComponentType::F32, 3, false)
+            .with(Semantic::TexCoord0, ComponentType::U16, 2, true)
+            .with(Semantic::Joints, ComponentType::U8, 1, false);
+
+        let offsets: Vec<i32> = layout.attributes.iter().map(|a| a.offset).collect();
+        assert_eq!(offsets, [0, 4, 16, 20]);
+        // Rounded up to the largest component
+        assert_eq!(layout.stride, 24);
+        assert!(layout.validate().is_ok());
+    }
+
+    #[test]
+    fn filtered_packs_again() {
+        let layout = VertexLayout::new()
+            .with(Semantic::Position, ComponentType::F32, 3, false)
+            .with(Semantic::Normal, ComponentType::F32, 3, false)
+            .with(Semantic::Color, ComponentType::U8, 4, true);
+        let filtered = layout.filtered(|a| a.semantic != Semantic::Normal);
+
+        assert_eq!(
+            filtered,
+            VertexLayout::new()
+                .with(Semantic::Position, ComponentType::F32, 3, false)
+                .with(Semantic::Color, ComponentType::U8, 4, true)
+        );
+        assert_eq!(filtered.attribute(Semantic::Color).unwrap().offset, 12);
+        assert_eq!(filtered.stride, 16);
+    }
+
+    #[test]
+    fn pack_converts_and_pads() {
+        let layout = VertexLayout::new()
+            .with(Semantic::Position, ComponentType::F32, 1, false)
+            .with(Semantic::Color, ComponentType::U8, 3, true)
+            .with(Semantic::Normal, ComponentType::I16, 2, true)
+            .with(Semantic::Joints, ComponentType::U8, 1, false);
+        let data = layout.pack(2, |index, semantic| match semantic {
+            Semantic::Position => [index as f32 + 0.5, 0.0, 0.0, 0.0],
+            Semantic::Color => [1.0, 0.5, -2.0, 1.0],
+            Semantic::Normal => [-1.0, 2.0, 0.0, 0.0],
+            _ => [7.0, 0.0, 0.0, 0.0],
+        });
+
+        assert_eq!(layout.stride, 16);
+        assert_eq!(data.len(), 32);
+        let mut expected = Vec::new();
+        for index in 0..2 {
+            expected.extend_from_slice(&(index as f32 + 0.5).to_le_bytes());
+            // Out of range values are clamped, and there's a byte of padding
+            expected.extend_from_slice(&[255, 128, 0, 0]);
+            expected.extend_from_slice(&(-i16::MAX).to_le_bytes());
+            expected.extend_from_slice(&i16::MAX.to_le_bytes());
+            expected.extend_from_slice(&[7, 0, 0, 0]);
+        }
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn buffer_needs_whole_vertices() {
+        let layout = VertexLayout::new().with(Semantic::Position, ComponentType::F32, 3, false);
+        assert!(VertexBuffer::new(layout.clone(), vec![0; 24]).is_ok());
+        assert!(VertexBuffer::new(layout, vec![0; 20]).is_err());
+
+        let too_wide = VertexLayout::new().with(Semantic::Position, ComponentType::F32, 5, false);
+        assert!(VertexBuffer::new(too_wide, vec![0; 20]).is_err());
+    }
+}
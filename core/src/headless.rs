@@ -0,0 +1,114 @@
+//! Rendering into an image instead of a window.
+//!
+//! [`HeadlessRenderer`] owns its own [`RenderManager`], camera and sun and draws
+//! them into a [`Framebuffer`]. It only needs a current GL context, so it works
+//! with surfaceless contexts on machines without a display.
+
+use glam::Vec3;
+use glow::HasContext;
+use image::RgbaImage;
+use log::info;
+
+use crate::ModelOptions;
+use crate::game::{Camera, Projection, RenderManager, Transform};
+use crate::graphics::types::{LightObjectRef, new_light_obj_ref};
+use crate::graphics::{Framebuffer, GlRef};
+use crate::loaders::ModelData;
+use crate::objects::{Light, Model};
+
+pub struct HeadlessRenderer {
+    gl: GlRef,
+    framebuffer: Framebuffer,
+
+    pub camera: Camera,
+    pub render_manager: RenderManager,
+    pub sun: LightObjectRef,
+}
+
+impl HeadlessRenderer {
+    pub fn new(gl: GlRef, (width, height): (u32, u32)) -> Result<Self, String> {
+        let framebuffer = Framebuffer::new(gl.clone(), width, height)?;
+        let mut render_manager = RenderManager::new(gl.clone())?;
+
+        let mut camera = Camera::new(0.1, 100.0);
+        camera.transform.position = Vec3::new(0.0, 0.0, 5.0);
+
+        // The sun only lights the scene, its lamp isn't drawn
+        let mut sun = Light::lamp(&gl);
+        sun.transform.position = Vec3::new(1.0, 1.0, 1.0);
+
+        let sun = new_light_obj_ref(sun);
+        render_manager.lights.add_light(sun.clone());
+
+        info!("Headless renderer: {}x{}", width, height);
+
+        Ok(Self {
+            gl,
+            framebuffer,
+            camera,
+            render_manager,
+            sun,
+        })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.framebuffer.width, self.framebuffer.height)
+    }
+
+    /// Add `model` to the scene and point the camera at it.
+    pub fn load_model(&mut self, model: ModelData, options: &ModelOptions) -> Result<(), String> {
+        let graph = &mut self.render_manager.graph;
+        let transform = Transform {
+            scale: Vec3::splat(options.scale),
+            ..Default::default()
+        };
+        let root = graph.add_node(&model.name, transform, None);
+
+        if let Err(e) = Model::add_to_graph(&self.gl, model, graph, root) {
+            graph.remove(root);
+            return Err(e);
+        }
+
+        if let Some(bounds) = graph.bounds(root) {
+            let (width, height) = self.size();
+            self.camera.frame(
+                &bounds,
+                width as f32 / height as f32,
+                options.camera_position,
+            );
+
+            let radius = bounds.radius().max(1e-3);
+            self.sun.borrow_mut().transform_mut().position = bounds.center() + Vec3::splat(radius);
+        }
+
+        Ok(())
+    }
+
+    /// Draw everything in the render manager and read the result back.
+    pub fn render(&mut self, clear_color: [f32; 4]) -> RgbaImage {
+        self.framebuffer.bind();
+
+        unsafe {
+            self.gl.enable(glow::DEPTH_TEST);
+            self.gl.clear_color(
+                clear_color[0],
+                clear_color[1],
+                clear_color[2],
+                clear_color[3],
+            );
+            self.gl
+                .clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+        }
+
+        let (width, height) = self.size();
+        let projection = self
+            .camera
+            .get_camera_projection_matrix(Projection::Perspective(width as f32 / height as f32));
+        let pv = projection * self.camera.get_camera_view_matrix();
+        self.render_manager.draw(&pv, &self.camera);
+
+        let image = self.framebuffer.read_pixels();
+        self.framebuffer.unbind();
+        image
+    }
+}
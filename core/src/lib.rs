@@ -1,7 +1,12 @@
+pub mod exporters;
 pub mod game;
 pub mod graphics;
+mod headless;
+pub mod loaders;
 pub mod objects;
 pub mod platform;
+pub mod scene;
+pub mod surface;
 mod view_port;
 
 use std::rc::Rc;
@@ -10,8 +15,10 @@ use glow::{Context, HasContext};
 use winit::window::Window;
 use winit_input_helper::WinitInputHelper;
 
+pub use self::headless::HeadlessRenderer;
 pub use self::platform::PlatformBackend;
-pub use self::view_port::ViewPort;
+pub use self::surface::{FakeSurface, Surface};
+pub use self::view_port::{ModelOptions, ViewPort};
 
 /// Shared state that exists on all platforms.
 pub struct State {
@@ -0,0 +1,562 @@
+//! glTF 2.0 importer for `.gltf` (with external or embedded resources) and `.glb`.
+//!
+//! Every node of the default scene becomes a [`ModelNode`] and every triangle
+//! primitive a [`ModelPart`] hanging off it. Images stay encoded and are handed to
+//! the renderer as [`TextureSource::Bytes`], so the same path works on wasm where
+//! nothing can be read from disk. Sparse accessors, normalized and 8/16-bit
+//! attributes are decoded by the `gltf` crate's reader.
+//!
+//! Supported extensions: `KHR_texture_transform` and `KHR_materials_emissive_strength`.
+
+use std::collections::HashSet;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use ::gltf::buffer::Source as BufferSource;
+use ::gltf::image::Source as ImageSource;
+use ::gltf::mesh::Mode;
+use ::gltf::texture::Info;
+use ::gltf::{Document, Gltf, Node, Primitive};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use glam::{Mat3, Quat, Vec2, Vec3, Vec4};
+use log::warn;
+
+use crate::game::Transform;
+use crate::graphics::{ComponentType, Mesh, Semantic, Vertex, VertexBuffer};
+use crate::loaders::{MaterialData, ModelData, ModelNode, ModelPart, ShadingModel, TextureSource};
+use crate::objects::{calculate_normals, calculate_tangents};
+
+#[derive(Debug)]
+pub enum GltfError {
+    /// The file could not be read.
+    Io { path: String, message: String },
+    /// The JSON or GLB container is malformed or fails validation.
+    Parse(String),
+    /// A buffer referenced by the file could not be loaded.
+    Buffer { index: usize, message: String },
+    /// A primitive's accessors could not be turned into a mesh.
+    Mesh { name: String, message: String },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io { path, message } => write!(f, "Failed to read '{}': {}", path, message),
+            GltfError::Parse(message) => write!(f, "Invalid glTF: {}", message),
+            GltfError::Buffer { index, message } => {
+                write!(f, "glTF buffer {}: {}", index, message)
+            }
+            GltfError::Mesh { name, message } => write!(f, "glTF mesh '{}': {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+/// Parse a `.gltf` or `.glb` file.
+///
+/// `resolve` is called with the (percent-decoded) URI of every external buffer and
+/// image; `data:` URIs are decoded here.
+pub fn parse_gltf(
+    data: &[u8],
+    resolve: impl Fn(&str) -> Result<Vec<u8>, String>,
+) -> Result<ModelData, GltfError> {
+    let Gltf { document, blob } =
+        Gltf::from_slice(data).map_err(|e| GltfError::Parse(e.to_string()))?;
+
+    let buffers = load_buffers(&document, blob, &resolve)?;
+    let images = load_images(&document, &buffers, &resolve);
+
+    let mut materials = Vec::new();
+    let mut uv_sets = Vec::new();
+    for material in document.materials() {
+        let (data, uv_set) = read_material(&material, &images);
+        materials.push(data);
+        uv_sets.push(uv_set);
+    }
+
+    let mut importer = Importer {
+        buffers: &buffers,
+        uv_sets: &uv_sets,
+        model: ModelData {
+            name: String::new(),
+            parts: Vec::new(),
+            materials,
+            nodes: Vec::new(),
+            source: None,
+        },
+        visited: HashSet::new(),
+    };
+
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            importer.model.name = scene.name().unwrap_or_default().to_string();
+            for node in scene.nodes() {
+                importer.add_node(&node, None)?;
+            }
+        }
+        // Without a scene there is no hierarchy, so show every mesh at the origin
+        None => {
+            for mesh in document.meshes() {
+                importer.add_mesh(&mesh, None)?;
+            }
+        }
+    }
+
+    Ok(importer.model)
+}
+
+/// Read and parse a `.gltf` or `.glb` file, loading external resources next to it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<ModelData, GltfError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|e| GltfError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut model = parse_gltf(&data, |uri| {
+        fs::read(base_dir.join(uri)).map_err(|e| e.to_string())
+    })?;
+
+    if model.name.is_empty() {
+        model.name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+    }
+
+    Ok(model)
+}
+
+struct Importer<'a> {
+    buffers: &'a [Vec<u8>],
+    /// UV set each material's maps sample, by material index.
+    uv_sets: &'a [u32],
+    model: ModelData,
+    visited: HashSet<usize>,
+}
+
+impl Importer<'_> {
+    fn add_node(&mut self, node: &Node, parent: Option<usize>) -> Result<(), GltfError> {
+        // Validation doesn't rule out cycles
+        if !self.visited.insert(node.index()) {
+            warn!("glTF node {} is referenced more than once", node.index());
+            return Ok(());
+        }
+
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let transform = Transform {
+            position: Vec3::from(translation),
+            rotation: Quat::from_array(rotation),
+            scale: Vec3::from(scale),
+            ..Default::default()
+        };
+
+        self.model.nodes.push(ModelNode {
+            name: node
+                .name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("node {}", node.index())),
+            transform,
+            parent,
+        });
+        let index = self.model.nodes.len() - 1;
+
+        if let Some(mesh) = node.mesh() {
+            self.add_mesh(&mesh, Some(index))?;
+        }
+
+        for child in node.children() {
+            self.add_node(&child, Some(index))?;
+        }
+
+        Ok(())
+    }
+
+    fn add_mesh(&mut self, mesh: &::gltf::Mesh, node: Option<usize>) -> Result<(), GltfError> {
+        let mesh_name = mesh
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("mesh {}", mesh.index()));
+
+        for primitive in mesh.primitives() {
+            let name = match mesh.primitives().len() {
+                1 => mesh_name.clone(),
+                _ => format!("{} #{}", mesh_name, primitive.index()),
+            };
+
+            let material = primitive.material().index();
+            let uv_set = material.map_or(0, |m| self.uv_sets[m]);
+
+            let Some(mesh) =
+                read_primitive(&primitive, self.buffers, uv_set).map_err(|message| {
+                    GltfError::Mesh {
+                        name: name.clone(),
+                        message,
+                    }
+                })?
+            else {
+                warn!(
+                    "Skipping '{}': {:?} primitives aren't supported",
+                    name,
+                    primitive.mode()
+                );
+                continue;
+            };
+
+            self.model.parts.push(ModelPart {
+                name,
+                mesh,
+                material,
+                node,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn load_buffers(
+    document: &Document,
+    mut blob: Option<Vec<u8>>,
+    resolve: &impl Fn(&str) -> Result<Vec<u8>, String>,
+) -> Result<Vec<Vec<u8>>, GltfError> {
+    document
+        .buffers()
+        .map(|buffer| {
+            let error = |message: String| GltfError::Buffer {
+                index: buffer.index(),
+                message,
+            };
+
+            let data = match buffer.source() {
+                BufferSource::Bin => blob
+                    .take()
+                    .ok_or_else(|| error("GLB binary chunk is missing".to_string()))?,
+                BufferSource::Uri(uri) => read_uri(uri, resolve).map_err(error)?,
+            };
+
+            if data.len() < buffer.length() {
+                return Err(error(format!(
+                    "expected {} bytes, got {}",
+                    buffer.length(),
+                    data.len()
+                )));
+            }
+
+            Ok(data)
+        })
+        .collect()
+}
+
+/// Encoded image data by image index. Missing images only cost their texture.
+fn load_images(
+    document: &Document,
+    buffers: &[Vec<u8>],
+    resolve: &impl Fn(&str) -> Result<Vec<u8>, String>,
+) -> Vec<Option<Vec<u8>>> {
+    document
+        .images()
+        .map(|image| {
+            let data = match image.source() {
+                ImageSource::View { view, .. } => buffers
+                    .get(view.buffer().index())
+                    .and_then(|b| b.get(view.offset()..view.offset() + view.length()))
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| "buffer view is out of range".to_string()),
+                ImageSource::Uri { uri, .. } => read_uri(uri, resolve),
+            };
+
+            data.map_err(|e| warn!("Skipping glTF image {}: {}", image.index(), e))
+                .ok()
+        })
+        .collect()
+}
+
+fn read_uri(
+    uri: &str,
+    resolve: &impl Fn(&str) -> Result<Vec<u8>, String>,
+) -> Result<Vec<u8>, String> {
+    match uri.strip_prefix("data:") {
+        Some(data) => {
+            let (header, payload) = data
+                .split_once(',')
+                .ok_or_else(|| "malformed data URI".to_string())?;
+
+            if !header.ends_with(";base64") {
+                return Err("only base64 data URIs are supported".to_string());
+            }
+
+            BASE64
+                .decode(payload)
+                .map_err(|e| format!("invalid base64 data: {}", e))
+        }
+        None => resolve(&percent_decode(uri)).map_err(|e| format!("'{}': {}", uri, e)),
+    }
+}
+
+/// Undo `%XX` escapes in a relative URI, e.g. `my%20texture.png`.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Map a metallic-roughness material onto the Phong parameters the renderer uses.
+///
+/// Also returns the UV set the base color texture samples.
+fn read_material(material: &::gltf::Material, images: &[Option<Vec<u8>>]) -> (MaterialData, u32) {
+    let name = material
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("material {}", material.index().unwrap_or_default()));
+    let mut data = MaterialData::new(&name);
+
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+    data.diffuse = Vec3::new(r, g, b);
+    data.ambient = data.diffuse;
+
+    if material.alpha_mode() == ::gltf::material::AlphaMode::Blend {
+        data.opacity = a;
+    }
+
+    // Rough surfaces get a broad, dim highlight and metals take on their base color
+    let roughness = pbr.roughness_factor().clamp(0.05, 1.0);
+    data.shininess = (2.0 / roughness.powi(4) - 2.0).clamp(1.0, 256.0);
+    data.specular = Vec3::splat(0.04).lerp(data.diffuse, pbr.metallic_factor());
+
+    data.emissive =
+        Vec3::from(material.emissive_factor()) * material.emissive_strength().unwrap_or(1.0);
+
+    // The Phong values above are kept for exporting to formats without PBR
+    data.shading = ShadingModel::MetallicRoughness;
+    data.metallic = pbr.metallic_factor();
+    data.roughness = pbr.roughness_factor();
+
+    let texture = |texture: ::gltf::Texture| {
+        images
+            .get(texture.source().index())
+            .cloned()
+            .flatten()
+            .map(TextureSource::Bytes)
+    };
+
+    let mut uv_set = 0;
+    if let Some(info) = pbr.base_color_texture() {
+        let (set, transform) = texture_transform(&info);
+        uv_set = set;
+        data.uv_transform = transform;
+        data.diffuse_map = texture(info.texture());
+    }
+
+    // The renderer has one set of texture coordinates, which follows the base color
+    let check_set = |map: &str, set: u32| {
+        if set != uv_set {
+            warn!(
+                "'{}': {} map uses a different UV set than the base color",
+                name, map
+            );
+        }
+    };
+
+    if let Some(info) = material.emissive_texture() {
+        check_set("emissive", texture_transform(&info).0);
+        data.emissive_map = texture(info.texture());
+    }
+
+    if let Some(info) = pbr.metallic_roughness_texture() {
+        check_set("metallic-roughness", texture_transform(&info).0);
+        data.metallic_roughness_map = texture(info.texture());
+    }
+
+    if let Some(normal) = material.normal_texture() {
+        check_set("normal", normal.tex_coord());
+        data.normal_scale = normal.scale();
+        data.normal_map = texture(normal.texture());
+    }
+
+    if let Some(occlusion) = material.occlusion_texture() {
+        check_set("occlusion", occlusion.tex_coord());
+        data.occlusion_strength = occlusion.strength();
+        data.occlusion_map = texture(occlusion.texture());
+    }
+
+    (data, uv_set)
+}
+
+/// UV set and `KHR_texture_transform` matrix of a texture reference.
+fn texture_transform(info: &Info) -> (u32, Mat3) {
+    let Some(transform) = info.texture_transform() else {
+        return (info.tex_coord(), Mat3::IDENTITY);
+    };
+
+    let (sin, cos) = transform.rotation().sin_cos();
+    let translation = Mat3::from_translation(Vec2::from(transform.offset()));
+    let rotation = Mat3::from_cols(Vec3::new(cos, -sin, 0.0), Vec3::new(sin, cos, 0.0), Vec3::Z);
+    let scale = Mat3::from_scale(Vec2::from(transform.scale()));
+
+    (
+        transform.tex_coord().unwrap_or(info.tex_coord()),
+        translation * rotation * scale,
+    )
+}
+
+/// Build a mesh from a primitive, or `None` for points and lines.
+fn read_primitive(
+    primitive: &Primitive,
+    buffers: &[Vec<u8>],
+    uv_set: u32,
+) -> Result<Option<Mesh>, String> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+
+    let positions: Vec<Vec3> = reader
+        .read_positions()
+        .ok_or("primitive has no POSITION attribute")?
+        .map(Vec3::from)
+        .collect();
+    let normals: Option<Vec<Vec3>> = reader.read_normals().map(|n| n.map(Vec3::from).collect());
+    let tangents: Option<Vec<Vec4>> = reader.read_tangents().map(|t| t.map(Vec4::from).collect());
+    let uvs: Vec<Vec2> = reader
+        .read_tex_coords(uv_set)
+        .map(|t| t.into_f32().map(Vec2::from).collect())
+        .unwrap_or_default();
+    let colors: Vec<[f32; 4]> = reader
+        .read_colors(0)
+        .map(|c| c.into_rgba_f32().collect())
+        .unwrap_or_default();
+
+    let count = positions.len() as u32;
+    let order: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..count).collect(),
+    };
+    if let Some(index) = order.iter().find(|&&i| i >= count) {
+        return Err(format!(
+            "index {} is out of range ({} vertices)",
+            index, count
+        ));
+    }
+
+    let indices: Vec<u32> = match primitive.mode() {
+        Mode::Triangles => order.chunks_exact(3).flatten().copied().collect(),
+        Mode::TriangleStrip => (0..order.len().saturating_sub(2))
+            .flat_map(|i| {
+                // Every other triangle is wound the other way round
+                let (a, b) = if i % 2 == 0 { (i, i + 1) } else { (i + 1, i) };
+                [order[a], order[b], order[i + 2]]
+            })
+            .collect(),
+        Mode::TriangleFan => (1..order.len().saturating_sub(1))
+            .flat_map(|i| [order[0], order[i], order[i + 1]])
+            .collect(),
+        _ => return Ok(None),
+    };
+
+    let mut vertices: Vec<Vertex> = positions
+        .iter()
+        .enumerate()
+        .map(|(i, &position)| {
+            Vertex::new(
+                position,
+                colors
+                    .get(i)
+                    .map_or(Vec3::ONE, |&[r, g, b, _]| Vec3::new(r, g, b)),
+                normals
+                    .as_ref()
+                    .and_then(|n| n.get(i).copied())
+                    .unwrap_or(Vec3::ZERO),
+                uvs.get(i).copied().unwrap_or(Vec2::ZERO),
+            )
+        })
+        .collect();
+
+    if normals.is_none() {
+        calculate_normals(&mut vertices, &indices);
+    }
+
+    // The spec only allows the file's tangents alongside its normals
+    match tangents.filter(|t| normals.is_some() && t.len() == vertices.len()) {
+        Some(tangents) => {
+            for (vertex, tangent) in vertices.iter_mut().zip(tangents) {
+                vertex.tangent = tangent.truncate();
+                vertex.handedness = tangent.w;
+            }
+        }
+        None => {
+            calculate_tangents(&mut vertices, &indices);
+        }
+    }
+
+    let mut mesh = Mesh::new(vertices, indices);
+
+    // What Vertex has no room for goes in buffers of its own
+    if colors.len() == mesh.vertices.len() && colors.iter().any(|c| c[3] != 1.0) {
+        mesh.add_buffer(VertexBuffer::from_slice(
+            Semantic::Color,
+            ComponentType::F32,
+            4,
+            false,
+            &colors,
+        )?)?;
+    }
+    if let Some(uvs) = reader.read_tex_coords(if uv_set == 0 { 1 } else { 0 }) {
+        let uvs: Vec<[f32; 2]> = uvs.into_f32().collect();
+        mesh.add_buffer(VertexBuffer::from_slice(
+            Semantic::TexCoord1,
+            ComponentType::F32,
+            2,
+            false,
+            &uvs,
+        )?)?;
+    }
+    if let (Some(joints), Some(weights)) = (reader.read_joints(0), reader.read_weights(0)) {
+        let joints: Vec<[u16; 4]> = joints.into_u16().collect();
+        let weights: Vec<[f32; 4]> = weights.into_f32().collect();
+        mesh.add_buffer(VertexBuffer::from_slice(
+            Semantic::Joints,
+            ComponentType::U16,
+            4,
+            false,
+            &joints,
+        )?)?;
+        mesh.add_buffer(VertexBuffer::from_slice(
+            Semantic::Weights,
+            ComponentType::F32,
+            4,
+            false,
+            &weights,
+        )?)?;
+    }
+
+    Ok(Some(mesh))
+}
@@ -0,0 +1,179 @@
+//! Model loaders.
+//!
+//! Each loader parses a file format into CPU-side [`Mesh`](crate::graphics::Mesh) data
+//! that is ready for [`Mesh::upload`](crate::graphics::Mesh::upload). Nothing in here
+//! touches the GL context, so parsing can happen before a window exists.
+
+pub mod gltf;
+pub mod model;
+pub mod mtl;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+#[cfg(not(target_arch = "wasm32"))]
+use log::warn;
+
+pub use self::gltf::{GltfError, parse_gltf};
+pub use model::{MaterialData, ModelData, ModelNode, ModelPart, ShadingModel, TextureSource};
+pub use mtl::{MtlError, MtlMaterial, parse_mtl};
+pub use obj::{ObjError, ObjModel, ObjSubMesh, parse_obj, parse_obj_model};
+pub use ply::{PlyError, parse_ply};
+pub use stl::{DEFAULT_WELD_EPSILON, StlError, parse_stl};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::gltf::load_gltf;
+#[cfg(not(target_arch = "wasm32"))]
+pub use mtl::load_mtl;
+#[cfg(not(target_arch = "wasm32"))]
+pub use obj::{load_obj, load_obj_model};
+#[cfg(not(target_arch = "wasm32"))]
+pub use ply::load_ply;
+#[cfg(not(target_arch = "wasm32"))]
+pub use stl::load_stl;
+
+/// Extensions [`load_model`] knows how to import, in lower case.
+pub const MODEL_EXTENSIONS: &[&str] = &["obj", "gltf", "glb", "stl", "ply"];
+
+#[derive(Debug)]
+pub enum LoadError {
+    Obj(ObjError),
+    Mtl(MtlError),
+    Gltf(GltfError),
+    Stl(StlError),
+    Ply(PlyError),
+    /// The file extension isn't one of the supported model formats.
+    UnsupportedFormat(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Obj(e) => e.fmt(f),
+            LoadError::Mtl(e) => e.fmt(f),
+            LoadError::Gltf(e) => e.fmt(f),
+            LoadError::Stl(e) => e.fmt(f),
+            LoadError::Ply(e) => e.fmt(f),
+            LoadError::UnsupportedFormat(path) => {
+                write!(f, "Unsupported model format: '{}'", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<ObjError> for LoadError {
+    fn from(e: ObjError) -> Self {
+        LoadError::Obj(e)
+    }
+}
+
+impl From<MtlError> for LoadError {
+    fn from(e: MtlError) -> Self {
+        LoadError::Mtl(e)
+    }
+}
+
+impl From<GltfError> for LoadError {
+    fn from(e: GltfError) -> Self {
+        LoadError::Gltf(e)
+    }
+}
+
+impl From<StlError> for LoadError {
+    fn from(e: StlError) -> Self {
+        LoadError::Stl(e)
+    }
+}
+
+impl From<PlyError> for LoadError {
+    fn from(e: PlyError) -> Self {
+        LoadError::Ply(e)
+    }
+}
+
+/// Load a model file, picking the importer from its extension.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_model(path: impl AsRef<Path>) -> Result<ModelData, LoadError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut model = match extension.as_str() {
+        "obj" => load_obj_data(path)?,
+        "gltf" | "glb" => load_gltf(path)?,
+        "stl" => ModelData::from_mesh(&name, load_stl(path, Some(DEFAULT_WELD_EPSILON))?),
+        "ply" => ModelData::from_mesh(&name, load_ply(path)?),
+        _ => return Err(LoadError::UnsupportedFormat(path.display().to_string())),
+    };
+
+    model.source = Some(path.to_path_buf());
+    Ok(model)
+}
+
+/// Load an OBJ file and the material libraries it references.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_obj_data(path: &Path) -> Result<ModelData, LoadError> {
+    let obj = load_obj_model(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    // A missing library shouldn't stop the geometry from showing up
+    let mut library = Vec::new();
+    for lib in &obj.material_libs {
+        match load_mtl(base_dir.join(lib)) {
+            Ok(materials) => library.extend(materials),
+            Err(MtlError::Io { path, message }) => {
+                warn!("Skipping material library '{}': {}", path, message)
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let materials: Vec<MaterialData> = library
+        .iter()
+        .map(|m| MaterialData::from_mtl(m, base_dir))
+        .collect();
+
+    let parts = obj
+        .sub_meshes
+        .into_iter()
+        .map(|sub_mesh| {
+            let material = sub_mesh.material.as_ref().and_then(|name| {
+                let index = materials.iter().position(|m| &m.name == name);
+                if index.is_none() {
+                    warn!("Material '{}' not found for '{}'", name, sub_mesh.name);
+                }
+                index
+            });
+
+            ModelPart {
+                name: sub_mesh.name,
+                mesh: sub_mesh.mesh,
+                material,
+                node: None,
+            }
+        })
+        .collect();
+
+    Ok(ModelData {
+        name: path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        parts,
+        materials,
+        nodes: Vec::new(),
+        source: None,
+    })
+}
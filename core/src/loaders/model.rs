@@ -0,0 +1,231 @@
+//! Format-neutral model data.
+//!
+//! Every importer produces a [`ModelData`]: a list of named parts, each with a mesh,
+//! an index into the model's materials and optionally the node it hangs off. The
+//! viewer turns it into scene objects without caring which file format it came from.
+
+use std::path::{Path, PathBuf};
+
+use glam::{Mat3, Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::game::Transform;
+use crate::graphics::{Material, Mesh, TextureRef};
+use crate::loaders::MtlMaterial;
+
+/// Where a texture's pixels come from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureSource {
+    /// An image file on disk (native only).
+    File(PathBuf),
+    /// An encoded image (PNG, JPEG, ...) already in memory.
+    Bytes(Vec<u8>),
+}
+
+/// Which lighting model a material is drawn with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShadingModel {
+    /// Blinn-Phong with the MTL parameters.
+    #[default]
+    Phong,
+    /// Cook-Torrance with glTF's metallic-roughness parameters. `diffuse`, `opacity`
+    /// and `diffuse_map` are the base color.
+    MetallicRoughness,
+}
+
+/// Material parameters and texture sources, before anything is uploaded.
+///
+/// The Phong parameters are always filled in, also for metallic-roughness
+/// materials, so formats without PBR can still export something close.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialData {
+    pub name: String,
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub illum: u32,
+    pub emissive: Vec3,
+    pub shading: ShadingModel,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// How much the occlusion map darkens, 0 ignores it.
+    pub occlusion_strength: f32,
+    /// Scales the X and Y of the normal map's normals.
+    pub normal_scale: f32,
+    pub diffuse_map: Option<TextureSource>,
+    pub specular_map: Option<TextureSource>,
+    pub normal_map: Option<TextureSource>,
+    pub emissive_map: Option<TextureSource>,
+    /// Metallic in blue, roughness in green, as glTF packs them.
+    pub metallic_roughness_map: Option<TextureSource>,
+    /// Ambient occlusion in red.
+    pub occlusion_map: Option<TextureSource>,
+    /// Applied to the texture coordinates of every map.
+    pub uv_transform: Mat3,
+}
+
+impl MaterialData {
+    /// A plain white material matching [`Material::new`](crate::graphics::Material::new).
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ambient: Vec3::ONE,
+            diffuse: Vec3::ONE,
+            specular: Vec3::ONE,
+            shininess: 32.0,
+            opacity: 1.0,
+            illum: 2,
+            emissive: Vec3::ZERO,
+            shading: ShadingModel::Phong,
+            metallic: 0.0,
+            roughness: 0.5,
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+            emissive_map: None,
+            metallic_roughness_map: None,
+            occlusion_map: None,
+            uv_transform: Mat3::IDENTITY,
+        }
+    }
+
+    /// Convert an MTL entry, resolving its texture paths against `base_dir`.
+    pub fn from_mtl(mtl: &MtlMaterial, base_dir: &Path) -> Self {
+        let source =
+            |file: &Option<String>| file.as_ref().map(|f| TextureSource::File(base_dir.join(f)));
+
+        Self {
+            name: mtl.name.clone(),
+            ambient: mtl.ambient,
+            diffuse: mtl.diffuse,
+            specular: mtl.specular,
+            shininess: mtl.shininess,
+            opacity: mtl.opacity,
+            illum: mtl.illum,
+            diffuse_map: source(&mtl.diffuse_map),
+            specular_map: source(&mtl.specular_map),
+            normal_map: source(&mtl.bump_map),
+            ..Self::new(&mtl.name)
+        }
+    }
+
+    /// Copy the parameters of a live material. Maps are kept for the textures
+    /// that were loaded from files, the others are already only on the GPU.
+    pub fn from_material(material: &Material) -> Self {
+        let map = |texture: &Option<TextureRef>| {
+            let path = texture.as_ref()?.path.clone()?;
+            Some(TextureSource::File(path))
+        };
+
+        Self {
+            ambient: material.ambient,
+            diffuse: material.diffuse,
+            specular: material.specular,
+            shininess: material.shininess,
+            opacity: material.opacity,
+            illum: material.illum,
+            emissive: material.emissive,
+            shading: material.shading,
+            metallic: material.metallic,
+            roughness: material.roughness,
+            occlusion_strength: material.occlusion_strength,
+            normal_scale: material.normal_scale,
+            uv_transform: material.uv_transform,
+            diffuse_map: map(&material.texture),
+            specular_map: map(&material.specular_map),
+            normal_map: map(&material.normal_map),
+            emissive_map: map(&material.emissive_map),
+            metallic_roughness_map: map(&material.metallic_roughness_map),
+            occlusion_map: map(&material.occlusion_map),
+            ..Self::new(&material.name)
+        }
+    }
+}
+
+/// A node of the model's hierarchy.
+pub struct ModelNode {
+    pub name: String,
+    /// Transform relative to `parent`.
+    pub transform: Transform,
+    /// Index into [`ModelData::nodes`].
+    pub parent: Option<usize>,
+}
+
+/// One drawable piece of a model.
+pub struct ModelPart {
+    pub name: String,
+    pub mesh: Mesh,
+    /// Index into [`ModelData::materials`].
+    pub material: Option<usize>,
+    /// Index into [`ModelData::nodes`]; `None` places the mesh at the model's origin.
+    pub node: Option<usize>,
+}
+
+pub struct ModelData {
+    pub name: String,
+    pub parts: Vec<ModelPart>,
+    pub materials: Vec<MaterialData>,
+    /// Parents always come before their children.
+    pub nodes: Vec<ModelNode>,
+    /// File the model was read from, if it came from one.
+    pub source: Option<PathBuf>,
+}
+
+impl ModelData {
+    /// A single-part model without materials, for formats that only carry geometry.
+    pub fn from_mesh(name: &str, mesh: Mesh) -> Self {
+        Self {
+            name: name.to_string(),
+            parts: vec![ModelPart {
+                name: name.to_string(),
+                mesh,
+                material: None,
+                node: None,
+            }],
+            materials: Vec::new(),
+            nodes: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Model-space matrix of `node`, including all of its parents.
+    pub fn node_matrix(&self, node: usize) -> Mat4 {
+        let mut matrix = Mat4::IDENTITY;
+        let mut current = Some(node);
+
+        while let Some(index) = current {
+            let node = &self.nodes[index];
+            let local = Mat4::from_scale_rotation_translation(
+                node.transform.scale,
+                node.transform.rotation,
+                node.transform.position,
+            );
+            matrix = local * matrix;
+            current = node.parent;
+        }
+
+        matrix
+    }
+
+    /// Use `texture` as the diffuse map of every part, giving untextured parts a material.
+    pub fn set_texture(&mut self, texture: TextureSource) {
+        for material in &mut self.materials {
+            material.diffuse_map = Some(texture.clone());
+        }
+
+        if self.parts.iter().any(|p| p.material.is_none()) {
+            let mut material = MaterialData::new("texture");
+            material.diffuse_map = Some(texture);
+            self.materials.push(material);
+
+            let index = self.materials.len() - 1;
+            for part in self.parts.iter_mut().filter(|p| p.material.is_none()) {
+                part.material = Some(index);
+            }
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Wavefront MTL material library parser.
+//!
+//! Reads the Phong parameters (`Ka`/`Kd`/`Ks`/`Ns`/`d`/`Tr`/`illum`) and the
+//! `map_Kd`/`map_Ks`/`map_Bump` texture references of every `newmtl` entry.
+//! Texture paths are kept as written; they are resolved against the library's
+//! directory when the GPU material is built.
+
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use glam::Vec3;
+
+#[derive(Debug)]
+pub enum MtlError {
+    /// The file could not be read.
+    Io { path: String, message: String },
+    /// A statement could not be parsed. `line` is 1-based.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for MtlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MtlError::Io { path, message } => write!(f, "Failed to read '{}': {}", path, message),
+            MtlError::Parse { line, message } => write!(f, "MTL line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for MtlError {}
+
+/// A single `newmtl` entry.
+#[derive(Clone, Debug)]
+pub struct MtlMaterial {
+    pub name: String,
+    /// `Ka`
+    pub ambient: Vec3,
+    /// `Kd`
+    pub diffuse: Vec3,
+    /// `Ks`
+    pub specular: Vec3,
+    /// `Ns`
+    pub shininess: f32,
+    /// `d`, or `1 - Tr`
+    pub opacity: f32,
+    /// `illum`: 0 = unlit color, 1 = diffuse only, 2+ = diffuse and specular
+    pub illum: u32,
+    /// `map_Kd`
+    pub diffuse_map: Option<String>,
+    /// `map_Ks`
+    pub specular_map: Option<String>,
+    /// `map_Bump` / `bump`
+    pub bump_map: Option<String>,
+}
+
+impl MtlMaterial {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ambient: Vec3::ONE,
+            diffuse: Vec3::ONE,
+            specular: Vec3::ZERO,
+            shininess: 32.0,
+            opacity: 1.0,
+            illum: 2,
+            diffuse_map: None,
+            specular_map: None,
+            bump_map: None,
+        }
+    }
+}
+
+/// Parse MTL source text into its materials, in file order.
+pub fn parse_mtl(source: &str) -> Result<Vec<MtlMaterial>, MtlError> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for (number, raw) in source.lines().enumerate() {
+        let line = number + 1;
+        let error = |message: String| MtlError::Parse { line, message };
+
+        let content = match raw.find('#') {
+            Some(i) => &raw[..i],
+            None => raw,
+        };
+        let mut tokens = content.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(error("'newmtl' needs a name".to_string()));
+            }
+            materials.push(MtlMaterial::new(&args.join(" ")));
+            continue;
+        }
+
+        let Some(material) = materials.last_mut() else {
+            return Err(error(format!("'{}' before any 'newmtl'", keyword)));
+        };
+
+        match keyword {
+            "Ka" => material.ambient = color(&args).map_err(error)?,
+            "Kd" => material.diffuse = color(&args).map_err(error)?,
+            "Ks" => material.specular = color(&args).map_err(error)?,
+            "Ns" => material.shininess = scalar(&args).map_err(error)?,
+            "d" => material.opacity = scalar(&args).map_err(error)?,
+            "Tr" => material.opacity = 1.0 - scalar(&args).map_err(error)?,
+            "illum" => {
+                material.illum = args
+                    .first()
+                    .and_then(|a| a.parse().ok())
+                    .ok_or_else(|| error("'illum' needs an integer".to_string()))?
+            }
+            "map_Kd" => material.diffuse_map = Some(texture_path(&args).map_err(error)?),
+            "map_Ks" => material.specular_map = Some(texture_path(&args).map_err(error)?),
+            "map_Bump" | "map_bump" | "bump" => {
+                material.bump_map = Some(texture_path(&args).map_err(error)?)
+            }
+            // Emission, refraction and the other maps aren't used by the renderer
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+/// Read and parse an MTL file from disk.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<Vec<MtlMaterial>, MtlError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| MtlError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    parse_mtl(&source)
+}
+
+fn scalar(args: &[&str]) -> Result<f32, String> {
+    let value = args.first().ok_or("Expected a value")?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number '{}'", value))
+}
+
+fn color(args: &[&str]) -> Result<Vec3, String> {
+    if args.first() == Some(&"spectral") || args.first() == Some(&"xyz") {
+        return Err(format!("Unsupported color space '{}'", args[0]));
+    }
+
+    let values = args
+        .iter()
+        .map(|a| {
+            a.parse::<f32>()
+                .map_err(|_| format!("Invalid number '{}'", a))
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+
+    // A single value is shorthand for a grey
+    match values.as_slice() {
+        [v] => Ok(Vec3::splat(*v)),
+        [r, g, b] => Ok(Vec3::new(*r, *g, *b)),
+        _ => Err(format!(
+            "Expected 1 or 3 color values, got {}",
+            values.len()
+        )),
+    }
+}
+
+/// Strip texture options such as `-bm 0.5` or `-imfchan r` and return the file name.
+fn texture_path(args: &[&str]) -> Result<String, String> {
+    let mut i = 0;
+    while let Some(option) = args.get(i).filter(|a| a.starts_with('-')) {
+        // Arguments every use of the option has, then how many more numbers it may have
+        let (required, optional) = match *option {
+            "-blendu" | "-blendv" | "-cc" | "-clamp" => (1, 0),
+            "-texres" | "-bm" | "-boost" => (1, 0),
+            "-imfchan" | "-type" => (1, 0),
+            "-mm" => (2, 0),
+            "-o" | "-s" | "-t" => (1, 2),
+            // Unknown options are assumed to only take numbers
+            _ => (0, usize::MAX),
+        };
+        i += 1 + required;
+
+        // The last argument is the file name, even if it looks like a number
+        let mut extra = 0;
+        while extra < optional && i + 1 < args.len() && args[i].parse::<f32>().is_ok() {
+            i += 1;
+            extra += 1;
+        }
+    }
+
+    if i >= args.len() {
+        return Err("Texture statement has no file name".to_string());
+    }
+
+    Ok(args[i..].join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn materials_in_file_order() {
+        let source = "\
+# exported
+newmtl brass metal
+Ka 0.1
+Kd 0.8 0.6 0.2
+Ks 1 1 1
+Ns 64
+Tr 0.25
+illum 1
+map_Kd -s 1 1 1 -bm 0.5 textures/brass base.png
+bump bump.png
+
+newmtl plain
+";
+        let materials = parse_mtl(source).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        let brass = &materials[0];
+        assert_eq!(brass.name, "brass metal");
+        assert_eq!(brass.ambient, Vec3::splat(0.1));
+        assert_eq!(brass.diffuse, Vec3::new(0.8, 0.6, 0.2));
+        assert_eq!(brass.specular, Vec3::ONE);
+        assert_eq!(brass.shininess, 64.0);
+        assert_eq!(brass.opacity, 0.75);
+        assert_eq!(brass.illum, 1);
+        assert_eq!(
+            brass.diffuse_map.as_deref(),
+            Some("textures/brass base.png")
+        );
+        assert_eq!(brass.bump_map.as_deref(), Some("bump.png"));
+        assert_eq!(brass.specular_map, None);
+
+        let plain = &materials[1];
+        assert_eq!(plain.name, "plain");
+        assert_eq!(plain.diffuse, Vec3::ONE);
+        assert_eq!(plain.illum, 2);
+    }
+
+    #[test]
+    fn texture_options_are_skipped() {
+        let cases = [
+            ("-imfchan r tex.png", "tex.png"),
+            ("-type sphere sky.png", "sky.png"),
+            ("-clamp on -mm 0 1 -bm 0.5 bump.png", "bump.png"),
+            ("-o 0.5 -s 2 2 -t 0 0 0 tiles.png", "tiles.png"),
+            ("-texres 512 -boost 1.5 -cc off 5.png", "5.png"),
+            ("-s 1 1 1 2", "2"),
+            ("-xyz 1 2 file name.png", "file name.png"),
+        ];
+
+        for (args, expected) in cases {
+            let args: Vec<&str> = args.split(' ').collect();
+            assert_eq!(texture_path(&args).as_deref(), Ok(expected));
+        }
+        assert!(texture_path(&["-imfchan", "r"]).is_err());
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            ("Kd 1 1 1\n", 1),
+            ("newmtl\n", 1),
+            ("newmtl a\nKd 1 1\n", 2),
+            ("newmtl a\nKd spectral file.rfl\n", 2),
+            ("newmtl a\n\nNs x\n", 3),
+            ("newmtl a\nillum\n", 2),
+            ("newmtl a\nmap_Kd\n", 2),
+        ];
+
+        for (source, expected) in cases {
+            match parse_mtl(source) {
+                Err(MtlError::Parse { line, .. }) => assert_eq!(line, expected, "{:?}", source),
+                other => panic!("{:?} gave {:?}", source, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,512 @@
+//! Wavefront OBJ parser.
+//!
+//! Supports `v`/`vt`/`vn`/`f` statements, negative (relative) indices and polygons
+//! with more than three vertices, which are fan-triangulated. Every unique
+//! position/uv/normal triple becomes one [`Vertex`] in a shared index buffer.
+//!
+//! Faces are split into sub-meshes by their `g`/`o` group and `usemtl` material,
+//! and `mtllib` references are collected so the materials can be loaded next to it.
+
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use glam::{Vec2, Vec3};
+
+use crate::graphics::{Mesh, Vertex};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::loaders::mtl::{MtlError, MtlMaterial, load_mtl};
+use crate::objects::{calculate_normals, calculate_tangents};
+
+#[derive(Debug)]
+pub enum ObjError {
+    /// The file could not be read.
+    Io { path: String, message: String },
+    /// A statement could not be parsed. `line` is 1-based.
+    Parse { line: usize, message: String },
+    /// The file parsed but did not contain any faces.
+    NoGeometry,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, message } => write!(f, "Failed to read '{}': {}", path, message),
+            ObjError::Parse { line, message } => write!(f, "OBJ line {}: {}", line, message),
+            ObjError::NoGeometry => write!(f, "OBJ file contains no faces"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// A parsed OBJ file.
+pub struct ObjModel {
+    /// Files named by `mtllib`, relative to the OBJ file.
+    pub material_libs: Vec<String>,
+    pub sub_meshes: Vec<ObjSubMesh>,
+}
+
+/// The faces of one group that share a material.
+pub struct ObjSubMesh {
+    /// Name from the enclosing `g` or `o` statement, `"default"` if there was none.
+    pub name: String,
+    /// Name from the active `usemtl` statement.
+    pub material: Option<String>,
+    pub mesh: Mesh,
+}
+
+impl ObjModel {
+    /// Combine every sub-mesh into one mesh, dropping the material assignments.
+    pub fn merged(self) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for sub_mesh in self.sub_meshes {
+            let base = vertices.len() as u32;
+            vertices.extend(sub_mesh.mesh.vertices);
+            indices.extend(sub_mesh.mesh.indices.iter().map(|i| i + base));
+        }
+
+        Mesh::new(vertices, indices)
+    }
+
+    /// Load every referenced material library. `base_dir` is the OBJ file's directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_materials(&self, base_dir: &Path) -> Result<Vec<MtlMaterial>, MtlError> {
+        let mut materials = Vec::new();
+
+        for lib in &self.material_libs {
+            materials.extend(load_mtl(base_dir.join(lib))?);
+        }
+
+        Ok(materials)
+    }
+}
+
+/// Parse OBJ source text into a single mesh.
+pub fn parse_obj(source: &str) -> Result<Mesh, ObjError> {
+    parse_obj_model(source).map(ObjModel::merged)
+}
+
+/// Parse OBJ source text, keeping groups and materials apart.
+pub fn parse_obj_model(source: &str) -> Result<ObjModel, ObjError> {
+    let mut parser = ObjParser::default();
+
+    for (number, raw) in source.lines().enumerate() {
+        parser.line = number + 1;
+        parser.statement(raw)?;
+    }
+
+    parser.finish()
+}
+
+/// Read and parse an OBJ file from disk into a single mesh.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Mesh, ObjError> {
+    load_obj_model(path).map(ObjModel::merged)
+}
+
+/// Read and parse an OBJ file from disk, keeping groups and materials apart.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_obj_model(path: impl AsRef<Path>) -> Result<ObjModel, ObjError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| ObjError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    parse_obj_model(&source)
+}
+
+/// Indices into the position, uv and normal pools of a single face corner.
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+#[derive(Default)]
+struct SubMeshBuilder {
+    name: String,
+    material: Option<String>,
+
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    has_normal: Vec<bool>,
+    lookup: HashMap<VertexKey, u32>,
+}
+
+#[derive(Default)]
+struct ObjParser {
+    line: usize,
+
+    positions: Vec<Vec3>,
+    colors: Vec<Vec3>,
+    tex_coords: Vec<Vec2>,
+    normals: Vec<Vec3>,
+
+    material_libs: Vec<String>,
+    group: Option<String>,
+    material: Option<String>,
+    sub_meshes: Vec<SubMeshBuilder>,
+    current: Option<usize>,
+}
+
+impl ObjParser {
+    fn error(&self, message: impl Into<String>) -> ObjError {
+        ObjError::Parse {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn statement(&mut self, raw: &str) -> Result<(), ObjError> {
+        let content = match raw.find('#') {
+            Some(i) => &raw[..i],
+            None => raw,
+        };
+
+        let mut tokens = content.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            return Ok(());
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => self.position(&args),
+            "vt" => self.tex_coord(&args),
+            "vn" => self.normal(&args),
+            "f" => self.face(&args),
+            "g" | "o" => {
+                self.group = (!args.is_empty()).then(|| args.join(" "));
+                self.current = None;
+                Ok(())
+            }
+            "usemtl" => {
+                self.material = (!args.is_empty()).then(|| args.join(" "));
+                self.current = None;
+                Ok(())
+            }
+            "mtllib" => {
+                self.material_libs
+                    .extend(args.iter().map(|a| a.to_string()));
+                Ok(())
+            }
+            // Smoothing groups and free-form geometry don't affect the mesh
+            _ => Ok(()),
+        }
+    }
+
+    fn floats(&self, args: &[&str]) -> Result<Vec<f32>, ObjError> {
+        args.iter()
+            .map(|a| {
+                a.parse::<f32>()
+                    .map_err(|_| self.error(format!("Invalid number '{}'", a)))
+            })
+            .collect()
+    }
+
+    fn position(&mut self, args: &[&str]) -> Result<(), ObjError> {
+        let v = self.floats(args)?;
+
+        // `v x y z [w]` or the common `v x y z r g b` vertex color extension
+        let color = match v.len() {
+            3 | 4 => Vec3::ONE,
+            6 | 7 => Vec3::new(v[3], v[4], v[5]),
+            n => return Err(self.error(format!("Expected 3 to 7 values for 'v', got {}", n))),
+        };
+
+        self.positions.push(Vec3::new(v[0], v[1], v[2]));
+        self.colors.push(color);
+        Ok(())
+    }
+
+    fn tex_coord(&mut self, args: &[&str]) -> Result<(), ObjError> {
+        let v = self.floats(args)?;
+        if v.is_empty() || v.len() > 3 {
+            return Err(self.error(format!("Expected 1 to 3 values for 'vt', got {}", v.len())));
+        }
+
+        // OBJ puts v = 0 at the bottom of the image; textures are uploaded top row first
+        let v_coord = v.get(1).copied().unwrap_or(0.0);
+        self.tex_coords.push(Vec2::new(v[0], 1.0 - v_coord));
+        Ok(())
+    }
+
+    fn normal(&mut self, args: &[&str]) -> Result<(), ObjError> {
+        let v = self.floats(args)?;
+        if v.len() != 3 {
+            return Err(self.error(format!("Expected 3 values for 'vn', got {}", v.len())));
+        }
+
+        self.normals
+            .push(Vec3::new(v[0], v[1], v[2]).normalize_or_zero());
+        Ok(())
+    }
+
+    fn face(&mut self, args: &[&str]) -> Result<(), ObjError> {
+        if args.len() < 3 {
+            return Err(self.error(format!(
+                "A face needs at least 3 vertices, got {}",
+                args.len()
+            )));
+        }
+
+        let target = self.current_sub_mesh();
+        let corners = args
+            .iter()
+            .map(|a| self.corner(target, a))
+            .collect::<Result<Vec<u32>, ObjError>>()?;
+
+        // Fan triangulation, fine for the convex polygons exporters write
+        let indices = &mut self.sub_meshes[target].indices;
+        for i in 1..corners.len() - 1 {
+            indices.extend_from_slice(&[corners[0], corners[i], corners[i + 1]]);
+        }
+
+        Ok(())
+    }
+
+    /// Index of the sub-mesh for the active group and material, created on first use.
+    fn current_sub_mesh(&mut self) -> usize {
+        if let Some(current) = self.current {
+            return current;
+        }
+
+        let name = self.group.clone().unwrap_or_else(|| "default".to_string());
+        let index = match self
+            .sub_meshes
+            .iter()
+            .position(|s| s.name == name && s.material == self.material)
+        {
+            Some(index) => index,
+            None => {
+                self.sub_meshes.push(SubMeshBuilder {
+                    name,
+                    material: self.material.clone(),
+                    ..Default::default()
+                });
+                self.sub_meshes.len() - 1
+            }
+        };
+
+        self.current = Some(index);
+        index
+    }
+
+    /// Resolve a `v`, `v/vt`, `v//vn` or `v/vt/vn` reference to an output vertex index.
+    fn corner(&mut self, target: usize, token: &str) -> Result<u32, ObjError> {
+        let mut parts = token.split('/');
+
+        let position = match parts.next() {
+            Some(p) if !p.is_empty() => self.resolve(p, self.positions.len(), "position")?,
+            _ => return Err(self.error(format!("Missing position index in '{}'", token))),
+        };
+        let tex_coord = match parts.next() {
+            Some(t) if !t.is_empty() => Some(self.resolve(t, self.tex_coords.len(), "uv")?),
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(n) if !n.is_empty() => Some(self.resolve(n, self.normals.len(), "normal")?),
+            _ => None,
+        };
+        if parts.next().is_some() {
+            return Err(self.error(format!("Malformed face vertex '{}'", token)));
+        }
+
+        let vertex = Vertex::new(
+            self.positions[position],
+            self.colors[position],
+            normal.map_or(Vec3::ZERO, |n| self.normals[n]),
+            tex_coord.map_or(Vec2::ZERO, |t| self.tex_coords[t]),
+        );
+
+        let sub_mesh = &mut self.sub_meshes[target];
+        let key = (position, tex_coord, normal);
+        if let Some(&index) = sub_mesh.lookup.get(&key) {
+            return Ok(index);
+        }
+
+        let index = sub_mesh.vertices.len() as u32;
+        sub_mesh.vertices.push(vertex);
+        sub_mesh.has_normal.push(normal.is_some());
+        sub_mesh.lookup.insert(key, index);
+
+        Ok(index)
+    }
+
+    /// Convert a 1-based (or negative, relative) OBJ index into a 0-based one.
+    fn resolve(&self, token: &str, count: usize, what: &str) -> Result<usize, ObjError> {
+        let index: i64 = token
+            .parse()
+            .map_err(|_| self.error(format!("Invalid {} index '{}'", what, token)))?;
+
+        let resolved = match index {
+            i if i > 0 => i - 1,
+            i if i < 0 => count as i64 + i,
+            _ => return Err(self.error(format!("{} index 0 is not valid", what))),
+        };
+
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!(
+                "{} index {} is out of range ({} defined)",
+                what, index, count
+            )));
+        }
+
+        Ok(resolved as usize)
+    }
+
+    fn finish(self) -> Result<ObjModel, ObjError> {
+        if self.sub_meshes.is_empty() {
+            return Err(ObjError::NoGeometry);
+        }
+
+        let sub_meshes = self
+            .sub_meshes
+            .into_iter()
+            .map(|mut s| {
+                fill_missing_normals(&mut s.vertices, &s.indices, &s.has_normal);
+                calculate_tangents(&mut s.vertices, &s.indices);
+
+                ObjSubMesh {
+                    name: s.name,
+                    material: s.material,
+                    mesh: Mesh::new(s.vertices, s.indices),
+                }
+            })
+            .collect();
+
+        Ok(ObjModel {
+            material_libs: self.material_libs,
+            sub_meshes,
+        })
+    }
+}
+
+/// Generate smooth normals for the vertices the file didn't provide one for.
+fn fill_missing_normals(vertices: &mut [Vertex], indices: &[u32], has_normal: &[bool]) {
+    if has_normal.iter().all(|&h| h) {
+        return;
+    }
+
+    if !has_normal.iter().any(|&h| h) {
+        calculate_normals(vertices, indices);
+        return;
+    }
+
+    let mut generated = vertices.to_vec();
+    calculate_normals(&mut generated, indices);
+
+    for ((vertex, generated), &has) in vertices.iter_mut().zip(&generated).zip(has_normal) {
+        if !has {
+            vertex.normal = generated.normal;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 1
+vn 0 0 1
+f 1/1/1 2/1/1 3/2/1 4/2/1
+";
+
+    #[test]
+    fn quad_is_fan_triangulated() {
+        let mesh = parse_obj(QUAD).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[0].normal, Vec3::Z);
+        // Flipped so the first row of the image is at the top
+        assert_eq!(mesh.vertices[0].tex_coords, Vec2::new(0.0, 1.0));
+        assert_eq!(mesh.vertices[2].tex_coords, Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn negative_indices_are_relative() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nf 1 2 3\n";
+        let mesh = parse_obj(source).unwrap();
+
+        // Both faces use the same corners, which are shared
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 1, 2]);
+        // No normals in the file, so they're generated from the winding
+        assert!(mesh.vertices[0].normal.abs_diff_eq(Vec3::Z, 1e-6));
+    }
+
+    #[test]
+    fn vertex_colors() {
+        let source = "v 0 0 0 1 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        let mesh = parse_obj(source).unwrap();
+
+        assert_eq!(mesh.vertices[0].color, Vec3::X);
+        assert_eq!(mesh.vertices[1].color, Vec3::ONE);
+    }
+
+    #[test]
+    fn groups_and_materials_split_sub_meshes() {
+        let source = "\
+mtllib a.mtl b.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 3
+g lid
+usemtl red
+f 1 2 3
+usemtl blue
+f 1 2 3
+usemtl red
+f 3 2 1
+";
+        let model = parse_obj_model(source).unwrap();
+
+        assert_eq!(model.material_libs, ["a.mtl", "b.mtl"]);
+        let parts: Vec<_> = model
+            .sub_meshes
+            .iter()
+            .map(|s| (s.name.as_str(), s.material.as_deref(), s.mesh.indices.len()))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("default", None, 3),
+                ("lid", Some("red"), 6),
+                ("lid", Some("blue"), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            ("v 0 0 0\nv 1 0 0\nf 1 2 3\n", 3),
+            ("v 0 0 0\n\n# comment\nv 1 x 0\n", 4),
+            ("v 0 0 0\nf 0 1 1\n", 2),
+            ("v 0 0 0\nf 1 1\n", 2),
+            ("v 0 0 0\nf 1/1/1/1 1 1\n", 2),
+        ];
+
+        for (source, expected) in cases {
+            match parse_obj(source) {
+                Err(ObjError::Parse { line, .. }) => assert_eq!(line, expected, "{:?}", source),
+                other => panic!("{:?} gave {:?}", source, other.map(|m| m.indices)),
+            }
+        }
+    }
+
+    #[test]
+    fn no_faces() {
+        assert!(matches!(parse_obj("v 0 0 0\n"), Err(ObjError::NoGeometry)));
+    }
+}
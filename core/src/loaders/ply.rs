@@ -0,0 +1,567 @@
+//! PLY (Stanford polygon file) parser.
+//!
+//! Reads `ascii`, `binary_little_endian` and `binary_big_endian` files. Vertices take
+//! their position and, when present, normal (`nx ny nz`), color (`red green blue`)
+//! and texture coordinates (`u v`, `s t` or `texture_u texture_v`). Faces come from
+//! the `vertex_indices` list and are fan-triangulated. Other elements and properties
+//! are skipped.
+
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use glam::{Vec2, Vec3};
+
+use crate::graphics::{Mesh, Vertex};
+use crate::objects::{calculate_normals, calculate_tangents};
+
+#[derive(Debug)]
+pub enum PlyError {
+    /// The file could not be read.
+    Io { path: String, message: String },
+    /// A header line could not be parsed. `line` is 1-based.
+    Header { line: usize, message: String },
+    /// The body doesn't match the header.
+    Data {
+        element: String,
+        index: usize,
+        message: String,
+    },
+    /// The file parsed but did not contain any faces.
+    NoGeometry,
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io { path, message } => write!(f, "Failed to read '{}': {}", path, message),
+            PlyError::Header { line, message } => {
+                write!(f, "PLY header line {}: {}", line, message)
+            }
+            PlyError::Data {
+                element,
+                index,
+                message,
+            } => write!(f, "PLY {} {}: {}", element, index, message),
+            PlyError::NoGeometry => write!(f, "PLY file contains no faces"),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Scale that maps the type's range to 0..1, for colors.
+    fn unit_scale(self) -> f32 {
+        match self {
+            Scalar::U8 | Scalar::I8 => 1.0 / 255.0,
+            Scalar::U16 | Scalar::I16 => 1.0 / 65535.0,
+            Scalar::U32 | Scalar::I32 => 1.0 / u32::MAX as f32,
+            Scalar::F32 | Scalar::F64 => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar {
+        name: String,
+        kind: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn find(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|p| names.contains(&p.name()))
+    }
+}
+
+/// Parse a PLY file in any of the three encodings.
+pub fn parse_ply(data: &[u8]) -> Result<Mesh, PlyError> {
+    let (format, elements, body) = parse_header(data)?;
+
+    // Every row takes at least a byte, so a count beyond what's left is a
+    // broken or hostile header, and would only be allocated for
+    if let Some(element) = elements
+        .iter()
+        .find(|e| !e.properties.is_empty() && e.count > body.len())
+    {
+        return Err(data_error(element, 0)(format!(
+            "{} rows don't fit in the {} bytes after the header",
+            element.count,
+            body.len()
+        )));
+    }
+
+    let mut reader = match format {
+        Format::Ascii => Reader::Ascii(
+            std::str::from_utf8(body)
+                .map_err(|_| PlyError::Header {
+                    line: 1,
+                    message: "ASCII body is not valid UTF-8".to_string(),
+                })?
+                .split_ascii_whitespace(),
+        ),
+        Format::BinaryLittleEndian => Reader::Binary {
+            data: body,
+            offset: 0,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Reader::Binary {
+            data: body,
+            offset: 0,
+            big_endian: true,
+        },
+    };
+
+    // Faces may come before the vertices they index
+    let vertex_count = elements
+        .iter()
+        .find(|e| e.name == "vertex")
+        .map_or(0, |e| e.count);
+
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let mut has_normals = false;
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                has_normals = element.find(&["nx"]).is_some();
+                vertices = read_vertices(&mut reader, element)?;
+            }
+            "face" => indices = read_faces(&mut reader, element, vertex_count)?,
+            _ => skip_element(&mut reader, element)?,
+        }
+    }
+
+    if indices.is_empty() {
+        return Err(PlyError::NoGeometry);
+    }
+
+    if !has_normals {
+        calculate_normals(&mut vertices, &indices);
+    }
+    calculate_tangents(&mut vertices, &indices);
+
+    Ok(Mesh::new(vertices, indices))
+}
+
+/// Read and parse a PLY file from disk.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_ply(path: impl AsRef<Path>) -> Result<Mesh, PlyError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|e| PlyError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    parse_ply(&data)
+}
+
+fn parse_header(data: &[u8]) -> Result<(Format, Vec<Element>, &[u8]), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut line = 0;
+
+    loop {
+        line += 1;
+        let error = |message: String| PlyError::Header { line, message };
+
+        let end = data[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| error("Missing 'end_header'".to_string()))?;
+        let text = String::from_utf8_lossy(&data[offset..offset + end]);
+        offset += end + 1;
+
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        if line == 1 {
+            if tokens.first() != Some(&"ply") {
+                return Err(error("Not a PLY file".to_string()));
+            }
+            continue;
+        }
+
+        match tokens.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(error(format!("Unknown format '{}'", name))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| error(format!("Invalid element count '{}'", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let scalar = |t: &str| {
+                    Scalar::parse(t).ok_or_else(|| error(format!("Unknown type '{}'", t)))
+                };
+                let property = Property::List {
+                    name: name.to_string(),
+                    count: scalar(count)?,
+                    item: scalar(item)?,
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| error("'property' before any 'element'".to_string()))?
+                    .properties
+                    .push(property);
+            }
+            ["property", kind, name] => {
+                let property = Property::Scalar {
+                    name: name.to_string(),
+                    kind: Scalar::parse(kind)
+                        .ok_or_else(|| error(format!("Unknown type '{}'", kind)))?,
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| error("'property' before any 'element'".to_string()))?
+                    .properties
+                    .push(property);
+            }
+            ["end_header"] => break,
+            _ => return Err(error(format!("Unexpected '{}'", text.trim()))),
+        }
+    }
+
+    let format = format.ok_or(PlyError::Header {
+        line,
+        message: "Missing 'format'".to_string(),
+    })?;
+
+    Ok((format, elements, &data[offset..]))
+}
+
+enum Reader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl Reader<'_> {
+    fn read(&mut self, kind: Scalar) -> Result<f64, String> {
+        match self {
+            Reader::Ascii(tokens) => {
+                let token = tokens.next().ok_or("Unexpected end of file")?;
+                token
+                    .parse()
+                    .map_err(|_| format!("Invalid number '{}'", token))
+            }
+            Reader::Binary {
+                data,
+                offset,
+                big_endian,
+            } => {
+                let size = kind.size();
+                let bytes = data
+                    .get(*offset..*offset + size)
+                    .ok_or("Unexpected end of file")?;
+                *offset += size;
+
+                let mut buf = [0u8; 8];
+                buf[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    buf[..size].reverse();
+                }
+
+                Ok(match kind {
+                    Scalar::I8 => buf[0] as i8 as f64,
+                    Scalar::U8 => buf[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    Scalar::U32 => u32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    Scalar::F32 => f32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    Scalar::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+
+    /// Read every property of one element instance; lists are flattened in place.
+    fn read_row(&mut self, element: &Element) -> Result<Vec<Vec<f64>>, String> {
+        element
+            .properties
+            .iter()
+            .map(|property| match property {
+                Property::Scalar { kind, .. } => Ok(vec![self.read(*kind)?]),
+                Property::List { count, item, .. } => {
+                    let count = self.read(*count)?;
+                    if count < 0.0 {
+                        return Err(format!("Negative list length {}", count));
+                    }
+                    (0..count as usize).map(|_| self.read(*item)).collect()
+                }
+            })
+            .collect()
+    }
+}
+
+fn data_error(element: &Element, index: usize) -> impl Fn(String) -> PlyError + '_ {
+    move |message| PlyError::Data {
+        element: element.name.clone(),
+        index,
+        message,
+    }
+}
+
+fn read_vertices(reader: &mut Reader, element: &Element) -> Result<Vec<Vertex>, PlyError> {
+    let scalar = |names: &[&str]| {
+        element
+            .find(names)
+            .and_then(|i| match element.properties[i] {
+                Property::Scalar { kind, .. } => Some((i, kind)),
+                Property::List { .. } => None,
+            })
+    };
+
+    let (Some(x), Some(y), Some(z)) = (scalar(&["x"]), scalar(&["y"]), scalar(&["z"])) else {
+        return Err(data_error(element, 0)(
+            "Vertices need x, y and z".to_string(),
+        ));
+    };
+    let normal = scalar(&["nx"]).zip(scalar(&["ny"])).zip(scalar(&["nz"]));
+    let color = scalar(&["red", "r", "diffuse_red"])
+        .zip(scalar(&["green", "g", "diffuse_green"]))
+        .zip(scalar(&["blue", "b", "diffuse_blue"]));
+    let uv = scalar(&["u", "s", "texture_u", "texture_s"]).zip(scalar(&[
+        "v",
+        "t",
+        "texture_v",
+        "texture_t",
+    ]));
+
+    let mut vertices = Vec::with_capacity(element.count);
+    for index in 0..element.count {
+        let row = reader
+            .read_row(element)
+            .map_err(data_error(element, index))?;
+        let value = |(i, _): (usize, Scalar)| row[i][0] as f32;
+        let unit = |(i, kind): (usize, Scalar)| row[i][0] as f32 * kind.unit_scale();
+
+        vertices.push(Vertex::new(
+            Vec3::new(value(x), value(y), value(z)),
+            color.map_or(Vec3::ONE, |((r, g), b)| {
+                Vec3::new(unit(r), unit(g), unit(b))
+            }),
+            normal.map_or(Vec3::ZERO, |((nx, ny), nz)| {
+                Vec3::new(value(nx), value(ny), value(nz))
+            }),
+            // Flip V to match the image row order, as for OBJ
+            uv.map_or(Vec2::ZERO, |(u, v)| Vec2::new(value(u), 1.0 - value(v))),
+        ));
+    }
+
+    Ok(vertices)
+}
+
+fn read_faces(
+    reader: &mut Reader,
+    element: &Element,
+    vertex_count: usize,
+) -> Result<Vec<u32>, PlyError> {
+    let list = element
+        .find(&["vertex_indices", "vertex_index"])
+        .ok_or_else(|| data_error(element, 0)("Faces need a vertex_indices list".to_string()))?;
+
+    let mut indices = Vec::with_capacity(element.count * 3);
+    for index in 0..element.count {
+        let error = data_error(element, index);
+        let row = reader.read_row(element).map_err(&error)?;
+
+        let face = row[list]
+            .iter()
+            .map(|&i| {
+                if i < 0.0 || i as usize >= vertex_count {
+                    Err(error(format!(
+                        "vertex index {} is out of range ({} vertices)",
+                        i, vertex_count
+                    )))
+                } else {
+                    Ok(i as u32)
+                }
+            })
+            .collect::<Result<Vec<u32>, PlyError>>()?;
+
+        if face.len() < 3 {
+            return Err(error(format!(
+                "Face has {} vertices, expected at least 3",
+                face.len()
+            )));
+        }
+
+        for i in 1..face.len() - 1 {
+            indices.extend([face[0], face[i], face[i + 1]]);
+        }
+    }
+
+    Ok(indices)
+}
+
+fn skip_element(reader: &mut Reader, element: &Element) -> Result<(), PlyError> {
+    for index in 0..element.count {
+        reader
+            .read_row(element)
+            .map_err(data_error(element, index))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+4 0 1 2 3
+";
+
+    #[test]
+    fn ascii_square() {
+        let mesh = parse_ply(SQUARE.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3]);
+        assert!(mesh.vertices[0].normal.abs_diff_eq(Vec3::Z, 1e-6));
+    }
+
+    #[test]
+    fn binary_big_endian_triangle() {
+        let mut data = b"ply
+format binary_big_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar uint vertex_indices
+end_header
+"
+        .to_vec();
+        for v in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            for c in v {
+                data.extend(c.to_be_bytes());
+            }
+        }
+        data.push(3);
+        for i in [0u32, 1, 2] {
+            data.extend(i.to_be_bytes());
+        }
+
+        let mesh = parse_ply(&data).unwrap();
+        assert_eq!(mesh.vertices[1].position, Vec3::X);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn count_larger_than_the_file() {
+        let data = SQUARE.replace("element vertex 4", "element vertex 18446744073709551615");
+        assert!(matches!(
+            parse_ply(data.as_bytes()),
+            Err(PlyError::Data { element, .. }) if element == "vertex"
+        ));
+    }
+
+    #[test]
+    fn truncated_body() {
+        let data = SQUARE.replace("4 0 1 2 3", "4 0 1");
+        assert!(matches!(
+            parse_ply(data.as_bytes()),
+            Err(PlyError::Data { element, index: 0, .. }) if element == "face"
+        ));
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let data = SQUARE.replace("4 0 1 2 3", "3 0 1 4");
+        assert!(parse_ply(data.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,448 @@
+//! STL parser for both the ASCII and the binary variant.
+//!
+//! STL stores every triangle with its own three corners and a facet normal.
+//! Triangles always keep their facet normal and render flat, as CAD models are
+//! meant to. Welding merges the corners closer than the weld epsilon that also
+//! share a normal, so flat faces share their vertices.
+
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use glam::{Vec2, Vec3};
+
+use crate::graphics::{Mesh, Vertex};
+
+/// Weld distance used when none is given, in model units.
+pub const DEFAULT_WELD_EPSILON: f32 = 1e-5;
+
+/// Size of the binary header plus the triangle count.
+const BINARY_HEADER: usize = 84;
+/// Normal, three corners and the attribute byte count.
+const BINARY_TRIANGLE: usize = 50;
+
+/// How far apart, as one minus the cosine, normals of welded corners may be.
+const NORMAL_TOLERANCE: f32 = 1e-4;
+
+#[derive(Debug)]
+pub enum StlError {
+    /// The file could not be read.
+    Io { path: String, message: String },
+    /// An ASCII statement could not be parsed. `line` is 1-based.
+    Parse { line: usize, message: String },
+    /// A binary file is shorter than its triangle count says.
+    Truncated { expected: usize, actual: usize },
+    /// The file parsed but did not contain any triangles.
+    NoGeometry,
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io { path, message } => write!(f, "Failed to read '{}': {}", path, message),
+            StlError::Parse { line, message } => write!(f, "STL line {}: {}", line, message),
+            StlError::Truncated { expected, actual } => write!(
+                f,
+                "Binary STL is truncated: expected {} bytes, got {}",
+                expected, actual
+            ),
+            StlError::NoGeometry => write!(f, "STL file contains no triangles"),
+        }
+    }
+}
+
+impl std::error::Error for StlError {}
+
+/// One triangle as stored in the file.
+struct Facet {
+    normal: Vec3,
+    corners: [Vec3; 3],
+}
+
+/// Parse an ASCII or binary STL file.
+///
+/// `weld_epsilon` merges corners closer than that distance with the same
+/// normal; `None` keeps every triangle separate.
+pub fn parse_stl(data: &[u8], weld_epsilon: Option<f32>) -> Result<Mesh, StlError> {
+    let facets = if is_binary(data) {
+        parse_binary(data)?
+    } else {
+        parse_ascii(&String::from_utf8_lossy(data))?
+    };
+
+    if facets.is_empty() {
+        return Err(StlError::NoGeometry);
+    }
+
+    Ok(match weld_epsilon {
+        Some(epsilon) => welded(&facets, epsilon),
+        None => flat(&facets),
+    })
+}
+
+/// Read and parse an STL file from disk.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_stl(path: impl AsRef<Path>, weld_epsilon: Option<f32>) -> Result<Mesh, StlError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|e| StlError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    parse_stl(&data, weld_epsilon)
+}
+
+/// Binary headers may also start with `solid`, so trust the size first.
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= BINARY_HEADER {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if binary_size(count) == Some(data.len()) {
+            return true;
+        }
+    }
+
+    // Otherwise it's text if it says so and has no NUL bytes, which floats are full of
+    let start = data
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(0);
+    !data[start..].starts_with(b"solid") || data.contains(&0)
+}
+
+/// Bytes a binary file with `count` triangles takes, `None` if that overflows.
+fn binary_size(count: usize) -> Option<usize> {
+    count
+        .checked_mul(BINARY_TRIANGLE)?
+        .checked_add(BINARY_HEADER)
+}
+
+fn parse_binary(data: &[u8]) -> Result<Vec<Facet>, StlError> {
+    if data.len() < BINARY_HEADER {
+        return Err(StlError::Truncated {
+            expected: BINARY_HEADER,
+            actual: data.len(),
+        });
+    }
+
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    // A count too big to add up is too big for any file
+    let expected = binary_size(count).unwrap_or(usize::MAX);
+    if data.len() < expected {
+        return Err(StlError::Truncated {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    let read_vec3 = |bytes: &[u8]| {
+        let f = |i: usize| f32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+        Vec3::new(f(0), f(1), f(2))
+    };
+
+    Ok(data[BINARY_HEADER..expected]
+        .chunks_exact(BINARY_TRIANGLE)
+        .map(|t| Facet {
+            normal: read_vec3(&t[0..12]),
+            corners: [
+                read_vec3(&t[12..24]),
+                read_vec3(&t[24..36]),
+                read_vec3(&t[36..48]),
+            ],
+        })
+        .collect())
+}
+
+fn parse_ascii(source: &str) -> Result<Vec<Facet>, StlError> {
+    let mut facets = Vec::new();
+    let mut normal = Vec3::ZERO;
+    let mut corners: Vec<Vec3> = Vec::new();
+    let mut in_facet = false;
+
+    for (number, raw) in source.lines().enumerate() {
+        let line = number + 1;
+        let error = |message: String| StlError::Parse { line, message };
+
+        let tokens: Vec<&str> = raw.split_whitespace().collect();
+        let Some(&keyword) = tokens.first() else {
+            continue;
+        };
+
+        match keyword {
+            "facet" => {
+                if in_facet {
+                    return Err(error("'facet' inside another facet".to_string()));
+                }
+                in_facet = true;
+                corners.clear();
+                normal = match tokens.get(1) {
+                    Some(&"normal") => vec3(&tokens[2..]).map_err(error)?,
+                    _ => Vec3::ZERO,
+                };
+            }
+            "vertex" => {
+                if !in_facet {
+                    return Err(error("'vertex' outside of a facet".to_string()));
+                }
+                corners.push(vec3(&tokens[1..]).map_err(error)?);
+            }
+            "endfacet" => {
+                if corners.len() < 3 {
+                    return Err(error(format!(
+                        "Facet has {} vertices, expected at least 3",
+                        corners.len()
+                    )));
+                }
+                // Some exporters write polygons, fan them like OBJ faces
+                for i in 1..corners.len() - 1 {
+                    facets.push(Facet {
+                        normal,
+                        corners: [corners[0], corners[i], corners[i + 1]],
+                    });
+                }
+                in_facet = false;
+            }
+            "solid" | "endsolid" | "outer" | "endloop" => {}
+            _ => return Err(error(format!("Unknown statement '{}'", keyword))),
+        }
+    }
+
+    if in_facet {
+        return Err(StlError::Parse {
+            line: source.lines().count(),
+            message: "Missing 'endfacet'".to_string(),
+        });
+    }
+
+    Ok(facets)
+}
+
+fn vec3(args: &[&str]) -> Result<Vec3, String> {
+    if args.len() < 3 {
+        return Err(format!("Expected 3 numbers, got {}", args.len()));
+    }
+
+    let value = |s: &str| {
+        s.parse::<f32>()
+            .map_err(|_| format!("Invalid number '{}'", s))
+    };
+    Ok(Vec3::new(value(args[0])?, value(args[1])?, value(args[2])?))
+}
+
+/// The stored normal, or the winding normal if the file left it zeroed.
+fn facet_normal(facet: &Facet) -> Vec3 {
+    let [a, b, c] = facet.corners;
+    match facet.normal.try_normalize() {
+        Some(normal) => normal,
+        None => (b - a).cross(c - a).normalize_or_zero(),
+    }
+}
+
+fn flat(facets: &[Facet]) -> Mesh {
+    let mut vertices = Vec::with_capacity(facets.len() * 3);
+    for facet in facets {
+        let normal = facet_normal(facet);
+        vertices.extend(
+            facet
+                .corners
+                .iter()
+                .map(|&p| Vertex::new(p, Vec3::ONE, normal, Vec2::ZERO)),
+        );
+    }
+
+    let indices = (0..vertices.len() as u32).collect();
+    Mesh::new(vertices, indices)
+}
+
+fn welded(facets: &[Facet], epsilon: f32) -> Mesh {
+    let mut welder = Welder::new(epsilon);
+    let mut indices = Vec::with_capacity(facets.len() * 3);
+
+    for facet in facets {
+        let normal = facet_normal(facet);
+        let corners = facet.corners.map(|p| welder.insert(p, normal));
+        // Triangles that collapsed to a line or point have nothing to draw
+        if corners[0] != corners[1] && corners[1] != corners[2] && corners[0] != corners[2] {
+            indices.extend(corners);
+        }
+    }
+
+    Mesh::new(welder.vertices, indices)
+}
+
+/// Spatial hash that finds an existing vertex within `epsilon` of a new one,
+/// with the same normal.
+struct Welder {
+    epsilon: f32,
+    cells: HashMap<[i64; 3], Vec<u32>>,
+    vertices: Vec<Vertex>,
+}
+
+impl Welder {
+    fn new(epsilon: f32) -> Self {
+        Self {
+            epsilon: epsilon.max(0.0),
+            cells: HashMap::new(),
+            vertices: Vec::new(),
+        }
+    }
+
+    fn cell(&self, p: Vec3) -> [i64; 3] {
+        if self.epsilon == 0.0 {
+            // Exact matches only, keyed by the bit pattern (with -0.0 folded into 0.0)
+            let bits = |v: f32| (v + 0.0).to_bits() as i64;
+            return [bits(p.x), bits(p.y), bits(p.z)];
+        }
+
+        let q = (p / self.epsilon).floor();
+        [q.x as i64, q.y as i64, q.z as i64]
+    }
+
+    fn insert(&mut self, p: Vec3, normal: Vec3) -> u32 {
+        let [x, y, z] = self.cell(p);
+
+        // A match can sit across a cell boundary, so look at the neighbours too
+        let range = if self.epsilon == 0.0 { 0 } else { 1 };
+        for dx in -range..=range {
+            for dy in -range..=range {
+                for dz in -range..=range {
+                    let Some(candidates) = self.cells.get(&[x + dx, y + dy, z + dz]) else {
+                        continue;
+                    };
+                    if let Some(&index) = candidates.iter().find(|&&i| {
+                        let vertex = &self.vertices[i as usize];
+                        vertex.position.distance(p) <= self.epsilon
+                            && vertex.normal.dot(normal) >= 1.0 - NORMAL_TOLERANCE
+                    }) {
+                        return index;
+                    }
+                }
+            }
+        }
+
+        let index = self.vertices.len() as u32;
+        self.vertices
+            .push(Vertex::new(p, Vec3::ONE, normal, Vec2::ZERO));
+        self.cells.entry([x, y, z]).or_default().push(index);
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A binary file whose header claims `count` triangles, followed by `body`.
+    fn binary(count: u32, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; 80];
+        data.extend(count.to_le_bytes());
+        data.extend(body);
+        data
+    }
+
+    /// A unit cube's twelve triangles as ASCII, with their facet normals.
+    fn cube() -> String {
+        let corner =
+            |i: usize| Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32);
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+
+        let mut source = "solid cube\n".to_string();
+        for [a, b, c, d] in faces {
+            for triangle in [[a, b, c], [a, c, d]] {
+                let [p, q, r] = triangle.map(corner);
+                let n = (q - p).cross(r - p).normalize();
+                source += &format!("facet normal {} {} {}\nouter loop\n", n.x, n.y, n.z);
+                for v in [p, q, r] {
+                    source += &format!("vertex {} {} {}\n", v.x, v.y, v.z);
+                }
+                source += "endloop\nendfacet\n";
+            }
+        }
+        source + "endsolid cube\n"
+    }
+
+    #[test]
+    fn welding_keeps_facet_normals() {
+        let mesh = parse_stl(cube().as_bytes(), Some(DEFAULT_WELD_EPSILON)).unwrap();
+
+        // Each face's two triangles share their diagonal, faces share nothing
+        assert_eq!(mesh.indices.len(), 36);
+        assert_eq!(mesh.vertices.len(), 24);
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+            let normal = (b.position - a.position)
+                .cross(c.position - a.position)
+                .normalize();
+            for vertex in [a, b, c] {
+                assert!(vertex.normal.abs_diff_eq(normal, 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn unwelded_triangles_stay_separate() {
+        let mesh = parse_stl(cube().as_bytes(), None).unwrap();
+        assert_eq!(mesh.vertices.len(), 36);
+    }
+
+    #[test]
+    fn binary_triangle() {
+        let mut body = Vec::new();
+        for v in [
+            [0.0f32, 0.0, 1.0],
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ] {
+            for c in v {
+                body.extend(c.to_le_bytes());
+            }
+        }
+        body.extend([0, 0]);
+
+        let mesh = parse_stl(&binary(1, &body), None).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.vertices[2].position, Vec3::Y);
+        assert_eq!(mesh.vertices[0].normal, Vec3::Z);
+    }
+
+    #[test]
+    fn truncated_binary() {
+        let data = binary(2, &[0; BINARY_TRIANGLE]);
+        assert!(matches!(
+            parse_stl(&data, None),
+            Err(StlError::Truncated {
+                expected: 184,
+                actual: 134
+            })
+        ));
+    }
+
+    #[test]
+    fn count_that_overflows() {
+        assert_eq!(binary_size(usize::MAX / BINARY_TRIANGLE + 1), None);
+
+        let data = binary(u32::MAX, &[0; BINARY_TRIANGLE]);
+        assert!(matches!(
+            parse_stl(&data, None),
+            Err(StlError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn empty_ascii() {
+        assert!(matches!(
+            parse_stl(b"solid empty\nendsolid empty\n", None),
+            Err(StlError::NoGeometry)
+        ));
+    }
+}
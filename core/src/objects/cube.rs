@@ -1,7 +1,7 @@
 use crate::{
     game::{GameObject, Physical, Renderable, Transform},
     graphics::{Material, Mesh, Vertex},
-    objects::calculate_normals,
+    objects::{calculate_normals, calculate_tangents},
 };
 use glam::{Mat4, Vec3};
 
@@ -12,6 +12,7 @@ pub struct Cube {
     pub velocity: Vec3,
     sin_wave: Vec<f32>,
     sin_index: f32,
+    base_y: Option<f32>,
 }
 
 impl Renderable for Cube {
@@ -41,9 +42,9 @@ impl Renderable for Cube {
 
     fn animate(&mut self, dt: f32) {
         // Spin
-        let rotation_x = glam::Quat::from_rotation_x(0.5 * dt as f32);
-        let rotation_y = glam::Quat::from_rotation_y(0.5 * dt as f32);
-        let rotation_z = glam::Quat::from_rotation_z(0.5 * dt as f32);
+        let rotation_x = glam::Quat::from_rotation_x(0.5 * dt);
+        let rotation_y = glam::Quat::from_rotation_y(0.5 * dt);
+        let rotation_z = glam::Quat::from_rotation_z(0.5 * dt);
         self.transform.rotation = rotation_x * rotation_y * -rotation_z * self.transform.rotation;
 
         // Bobbing on Y axis using precomputed sin wave
@@ -68,7 +69,7 @@ impl Renderable for Cube {
 
         // Map sinv (-1..1) to desired Y range
         let amplitude: f32 = 0.5; // max offset from center
-        let base_y: f32 = 0.0; // center position
+        let base_y = *self.base_y.get_or_insert(self.transform.position.y); // where it was placed
         self.transform.position.y = base_y + sinv * amplitude;
     }
 }
@@ -97,21 +98,20 @@ impl Physical for Cube {
 }
 
 // Implement the GameObject super-trait for Cube (requires both Renderable + Physical)
-impl GameObject for Cube {}
+impl GameObject for Cube {
+    fn name(&self) -> &str {
+        "cube"
+    }
+}
 
 impl Cube {
     pub fn new(material: Material) -> Self {
         let (mut vertices, indices) = Self::data();
 
         calculate_normals(&mut vertices, &indices);
+        calculate_tangents(&mut vertices, &indices);
 
-        let mesh = Mesh {
-            vao: None,
-            vbo: None,
-            ibo: None,
-            vertices,
-            indices,
-        };
+        let mesh = Mesh::new(vertices, indices);
 
         // Generate Sin wave 0->2PI (one cycle)
         let samples: usize = 256;
@@ -129,6 +129,7 @@ impl Cube {
             velocity: Vec3::ZERO,
             sin_wave,
             sin_index: 0.0,
+            base_y: None,
         }
     }
 
@@ -161,6 +162,8 @@ impl Cube {
                     color,
                     normal: Vec3::ZERO,
                     tex_coords: uvs[i],
+                    tangent: Vec3::ZERO,
+                    handedness: 1.0,
                 });
             }
 
@@ -1,6 +1,10 @@
 use crate::{
-    game::{GlobalLight, Physical, Renderable, Transform},
-    graphics::{Material, Mesh, Vertex},
+    game::{GlobalLight, LightSource, Physical, Renderable, Transform},
+    graphics::{
+        GlRef, LIGHT_CUBE_FRAG_PATH, LIGHT_CUBE_FRAG_SRC, LIGHT_CUBE_VERT_PATH,
+        LIGHT_CUBE_VERT_SRC, Material, Mesh, Shader, ShaderRef, Texture, TextureRef, Vertex,
+        new_shader_ref, new_texture_ref,
+    },
 };
 use glam::{Mat4, Vec3};
 
@@ -9,9 +13,7 @@ pub struct Light {
     pub material: Material,
     pub mesh: Mesh,
     pub transform: Transform,
-
-    ambient: f32,
-    specular: f32,
+    pub source: LightSource,
 }
 
 impl Renderable for Light {
@@ -39,12 +41,17 @@ impl Renderable for Light {
         )
     }
 
+    // The lamp would block its own light
+    fn casts_shadows(&self) -> bool {
+        false
+    }
+
     fn animate(&mut self, dt: f32) {
         // Spin
         let dt = dt * 5.0;
 
-        let rotation_x = glam::Quat::from_rotation_x(0.5 * dt as f32);
-        let rotation_z = glam::Quat::from_rotation_z(0.5 * dt as f32);
+        let rotation_x = glam::Quat::from_rotation_x(0.5 * dt);
+        let rotation_z = glam::Quat::from_rotation_z(0.5 * dt);
         self.transform.rotation = rotation_x * rotation_z * self.transform.rotation;
     }
 }
@@ -70,20 +77,12 @@ impl Physical for Light {
 }
 
 impl GlobalLight for Light {
-    fn ambient(&self) -> f32 {
-        self.ambient
-    }
-
-    fn specular(&self) -> f32 {
-        self.specular
-    }
-
-    fn ambient_mut(&mut self) -> &mut f32 {
-        &mut self.ambient
+    fn source(&self) -> &LightSource {
+        &self.source
     }
 
-    fn specular_mut(&mut self) -> &mut f32 {
-        &mut self.specular
+    fn source_mut(&mut self) -> &mut LightSource {
+        &mut self.source
     }
 }
 
@@ -91,24 +90,51 @@ impl Light {
     pub fn new(material: Material) -> Self {
         let (vertices, indices) = Self::data();
 
-        let mesh = Mesh {
-            vao: None,
-            vbo: None,
-            ibo: None,
-            vertices,
-            indices,
-        };
+        let mesh = Mesh::new(vertices, indices);
 
         Self {
             material,
             mesh,
             transform: Transform::default(),
-
-            ambient: 0.2,
-            specular: 0.5,
+            source: LightSource::default(),
         }
     }
 
+    /// The textured lamp cube used as the scene's sun, uploaded and ready to draw.
+    pub fn lamp(gl: &GlRef) -> Self {
+        let light_shader: ShaderRef = {
+            let mut shader = Shader::new(gl.clone());
+            let _ = shader.add(
+                glow::FRAGMENT_SHADER,
+                LIGHT_CUBE_FRAG_SRC,
+                LIGHT_CUBE_FRAG_PATH,
+            );
+            let _ = shader.add(
+                glow::VERTEX_SHADER,
+                LIGHT_CUBE_VERT_SRC,
+                LIGHT_CUBE_VERT_PATH,
+            );
+            let _ = shader.link();
+
+            new_shader_ref(shader)
+        };
+        let mut light_material = Material::new(gl.clone(), light_shader.clone());
+        let light_texture: TextureRef = {
+            let tex = Texture::from_bytes(gl.clone(), include_bytes!("textures/redstone_lamp.png"))
+                .expect("Failed to load texture");
+            new_texture_ref(tex)
+        };
+        light_material.texture = Some(light_texture);
+
+        let mut light = Light::new(light_material);
+        light
+            .mesh
+            .upload(gl, light_shader)
+            .expect("Failed to upload mesh");
+
+        light
+    }
+
     fn data() -> (Vec<Vertex>, Vec<u32>) {
         let mut vertices: Vec<Vertex> = Vec::with_capacity(6 * 4); // 6 faces * 4 points
         let mut indices: Vec<u32> = Vec::with_capacity(36);
@@ -138,6 +164,8 @@ impl Light {
                     color,
                     normal: Vec3::ZERO,
                     tex_coords: uvs[i],
+                    tangent: Vec3::ZERO,
+                    handedness: 1.0,
                 });
             }
 
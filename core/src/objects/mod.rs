@@ -19,15 +19,18 @@
 pub mod cube;
 pub mod triangle;
 pub mod light;
+pub mod model;
 
 pub use cube::Cube;
 pub use triangle::Triangle;
 pub use light::Light;
+pub use model::Model;
 
 use crate::graphics::Vertex;
+use bevy_mikktspace::Geometry;
 use glam::Vec3;
 
-pub fn calculate_normals(vertices: &mut Vec<Vertex>, indices: &Vec<u32>) {
+pub fn calculate_normals(vertices: &mut [Vertex], indices: &[u32]) {
     let vertex_count = vertices.len();
 
     // Accumulate face normals into each vertex
@@ -64,4 +67,63 @@ pub fn calculate_normals(vertices: &mut Vec<Vertex>, indices: &Vec<u32>) {
             Vec3::ZERO
         };
     }
-}
\ No newline at end of file
+}
+
+/// Generate MikkTSpace tangents, the ones normal maps are usually baked
+/// against, from the normals and texture coordinates. Returns false, leaving
+/// the tangents as they were, when the mesh has no texture coordinates or
+/// nothing to build them from.
+pub fn calculate_tangents(vertices: &mut [Vertex], indices: &[u32]) -> bool {
+    let Some(first) = vertices.first().map(|v| v.tex_coords) else {
+        return false;
+    };
+    if indices.len() < 3 || vertices.iter().all(|v| v.tex_coords == first) {
+        return false;
+    }
+
+    let mut mesh = TangentMesh { vertices, indices };
+    bevy_mikktspace::generate_tangents(&mut mesh)
+}
+
+/// An indexed triangle list as MikkTSpace sees it.
+struct TangentMesh<'a> {
+    vertices: &'a mut [Vertex],
+    indices: &'a [u32],
+}
+
+impl TangentMesh<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.vertices[self.indices[face * 3 + vert] as usize]
+    }
+}
+
+impl Geometry for TangentMesh<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position.into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal.into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).tex_coords.into()
+    }
+
+    // Corners sharing a vertex get the same tangent unless the handedness
+    // flips, and the loaders already split vertices where the UVs do
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let [x, y, z, w] = tangent;
+        let vertex = &mut self.vertices[self.indices[face * 3 + vert] as usize];
+        vertex.tangent = Vec3::new(x, y, z);
+        vertex.handedness = w;
+    }
+}
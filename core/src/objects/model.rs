@@ -0,0 +1,186 @@
+use crate::{
+    game::{GameObject, NodeId, Physical, Renderable, SceneGraph, Transform},
+    graphics::{
+        GlRef, Material, Mesh, ShaderRef, new_game_obj_ref, new_shader_ref, object_shader,
+        types::GameObjectRef,
+    },
+    loaders::{ModelData, ShadingModel},
+};
+use glam::{Mat4, Vec3};
+
+/// A static piece of an imported model.
+pub struct Model {
+    pub name: String,
+    pub material: Material,
+    pub mesh: Mesh,
+    pub transform: Transform,
+    pub velocity: Vec3,
+}
+
+impl Renderable for Model {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn mesh_mut(&mut self) -> &mut Mesh {
+        &mut self.mesh
+    }
+
+    fn model_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            self.transform.scale,
+            self.transform.rotation,
+            self.transform.position,
+        )
+    }
+
+    fn animate(&mut self, _dt: f32) {}
+}
+
+impl Physical for Model {
+    fn update(&mut self, dt: f32) {
+        self.transform.position += self.velocity * dt;
+    }
+
+    fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    fn set_velocity(&mut self, velocity: Vec3) {
+        self.velocity = velocity;
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+}
+
+impl GameObject for Model {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Model {
+    pub fn new(name: &str, material: Material, mesh: Mesh) -> Self {
+        Self {
+            name: name.to_string(),
+            material,
+            mesh,
+            transform: Transform::default(),
+            velocity: Vec3::ZERO,
+        }
+    }
+
+    /// Upload every part of `model` and hang it into `graph` below `root`, with a
+    /// node for each of the model's own nodes. Returns each part with the node
+    /// holding it, in the order of [`ModelData::parts`].
+    ///
+    /// Nothing is added to `graph` if a part fails to upload.
+    pub fn add_to_graph(
+        gl: &GlRef,
+        mut model: ModelData,
+        graph: &mut SceneGraph,
+        root: NodeId,
+    ) -> Result<Vec<(NodeId, GameObjectRef)>, String> {
+        if model.parts.is_empty() {
+            return Err(format!("Model '{}' has no geometry", model.name));
+        }
+
+        let nodes: Vec<Option<usize>> = model.parts.iter().map(|p| p.node).collect();
+        let model_nodes = std::mem::take(&mut model.nodes);
+        let parts = Self::upload_parts(gl, model)?;
+
+        // Parents come before their children, so they always exist already
+        let mut ids: Vec<NodeId> = Vec::with_capacity(model_nodes.len());
+        for node in model_nodes {
+            let parent = node
+                .parent
+                .and_then(|p| ids.get(p))
+                .copied()
+                .unwrap_or(root);
+            ids.push(graph.add_node(&node.name, node.transform, Some(parent)));
+        }
+
+        Ok(parts
+            .into_iter()
+            .zip(nodes)
+            .map(|(part, node)| {
+                let parent = node.and_then(|n| ids.get(n)).copied().unwrap_or(root);
+                let name = part.name.clone();
+                let object = new_game_obj_ref(part);
+                let id =
+                    graph.add_renderable(&name, object.clone(), Transform::default(), Some(parent));
+                (id, object)
+            })
+            .collect())
+    }
+
+    /// Upload every part of `model`, each with its own material. Parts shaded
+    /// the same way share one shader, so it's only linked and reloaded once.
+    fn upload_parts(gl: &GlRef, model: ModelData) -> Result<Vec<Model>, String> {
+        let mut materials: Vec<Option<Material>> = vec![None; model.materials.len()];
+        let mut shaders: Vec<(ShadingModel, ShaderRef)> = Vec::new();
+        let mut objects: Vec<Model> = Vec::with_capacity(model.parts.len());
+
+        for mut part in model.parts {
+            let shading = part
+                .material
+                .and_then(|index| model.materials.get(index))
+                .map(|data| data.shading)
+                .unwrap_or_default();
+            let shader = match shaders.iter().find(|(s, _)| *s == shading) {
+                Some((_, shader)) => shader.clone(),
+                None => {
+                    let shader = new_shader_ref(object_shader(gl, shading));
+                    shaders.push((shading, shader.clone()));
+                    shader
+                }
+            };
+
+            let material = match part.material {
+                Some(index) => {
+                    let data = model
+                        .materials
+                        .get(index)
+                        .ok_or_else(|| format!("Part '{}' has no material {}", part.name, index))?;
+
+                    match &materials[index] {
+                        Some(material) => {
+                            let mut material = material.clone();
+                            material.shader = shader.clone();
+                            material
+                        }
+                        None => {
+                            let material = Material::from_data(gl.clone(), shader.clone(), data)
+                                .map_err(|e| format!("Material '{}': {}", data.name, e))?;
+                            materials[index] = Some(material.clone());
+                            material
+                        }
+                    }
+                }
+                None => Material::new(gl.clone(), shader.clone()),
+            };
+
+            part.mesh
+                .upload(gl, shader)
+                .map_err(|e| format!("Failed to upload '{}': {}", part.name, e))?;
+
+            objects.push(Model::new(&part.name, material, part.mesh));
+        }
+
+        Ok(objects)
+    }
+}
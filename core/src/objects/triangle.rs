@@ -1,5 +1,5 @@
 use crate::{
-    game::{Renderable, Transform},
+    game::{GameObject, Physical, Renderable, Transform},
     graphics::{Material, Mesh, Vertex},
     objects::calculate_normals,
 };
@@ -9,6 +9,7 @@ pub struct Triangle {
     pub material: Material,
     pub mesh: Mesh,
     pub transform: Transform,
+    pub velocity: Vec3,
 }
 
 impl Renderable for Triangle {
@@ -37,11 +38,39 @@ impl Renderable for Triangle {
     }
 
     fn animate(&mut self, dt: f32) {
-        let rotation_y = glam::Quat::from_rotation_y(0.5 * dt as f32);
+        let rotation_y = glam::Quat::from_rotation_y(0.5 * dt);
         self.transform.rotation = rotation_y * self.transform.rotation;
     }
 }
 
+impl Physical for Triangle {
+    fn update(&mut self, dt: f32) {
+        self.transform.position += self.velocity * dt;
+    }
+
+    fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    fn set_velocity(&mut self, velocity: Vec3) {
+        self.velocity = velocity;
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+}
+
+impl GameObject for Triangle {
+    fn name(&self) -> &str {
+        "triangle"
+    }
+}
+
 impl Triangle {
     pub fn new(material: Material) -> Self {
         let mut vertices: Vec<Vertex> = vec![
@@ -53,18 +82,13 @@ impl Triangle {
 
         calculate_normals(&mut vertices, &indices);
 
-        let mesh = Mesh {
-            vao: None,
-            vbo: None,
-            ibo: None,
-            vertices,
-            indices,
-        };
+        let mesh = Mesh::new(vertices, indices);
 
         Self {
             material,
             mesh,
             transform: Transform::default(),
+            velocity: Vec3::ZERO,
         }
     }
 }
@@ -0,0 +1,1338 @@
+//! Scene description files.
+//!
+//! A [`SceneFile`] lists the camera, lights, materials and objects of a scene. It is
+//! stored as RON or JSON, picked by the file extension. Relative paths inside a
+//! scene are resolved against the directory of the scene file.
+//!
+//! ```ron
+//! (
+//!     camera: (position: (0.0, 1.0, 5.0), fov: 45.0),
+//!     lights: [
+//!         (name: "sun", kind: Directional, direction: (-1.0, -1.0, -1.0), shadow_resolution: 2048),
+//!         (name: "lamp", kind: Point, position: (1.0, 2.0, 0.0), color: (1.0, 0.8, 0.6)),
+//!     ],
+//!     materials: [(name: "red", diffuse: (1.0, 0.0, 0.0))],
+//!     objects: [
+//!         (name: "box", mesh: Cube, material: "red"),
+//!         (name: "teapot", mesh: File("teapot.obj"), position: (2.0, 0.0, 0.0)),
+//!     ],
+//! )
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glam::{EulerRot, Quat, Vec3};
+use log::warn;
+use ron::extensions::Extensions;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::game::shadow_manager::{MAX_SHADOW_RESOLUTION, MIN_SHADOW_RESOLUTION};
+use crate::game::{Camera, GameObject, LightKind, LightSource, NodeId, SceneGraph, Transform};
+use crate::graphics::types::GameObjectRef;
+use crate::graphics::{
+    GlRef, Material, TextureBuilder, new_game_obj_ref, new_shader_ref, new_texture_ref,
+    object_shader,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::loaders::load_model;
+use crate::loaders::{MaterialData, ShadingModel, TextureSource};
+use crate::objects::{Cube, Light, Model, Triangle};
+
+/// Extensions [`load_scene_file`] knows how to read, in lower case.
+pub const SCENE_EXTENSIONS: &[&str] = &["ron", "json"];
+
+#[derive(Debug)]
+pub enum SceneError {
+    /// The file could not be read or written.
+    Io { path: String, message: String },
+    /// The text is not a valid scene. `message` includes the position if the parser knows it.
+    Parse { message: String },
+    /// An entry of the scene has an invalid value. `entry` names it, e.g. `object 'teapot'`.
+    Invalid { entry: String, message: String },
+    /// The file extension isn't one of [`SCENE_EXTENSIONS`].
+    UnsupportedFormat(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { path, message } => {
+                write!(f, "Failed to access '{}': {}", path, message)
+            }
+            SceneError::Parse { message } => write!(f, "Invalid scene: {}", message),
+            SceneError::Invalid { entry, message } => write!(f, "{}: {}", entry, message),
+            SceneError::UnsupportedFormat(path) => {
+                write!(f, "Unsupported scene format: '{}'", path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneFormat {
+    Ron,
+    Json,
+}
+
+impl SceneFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "ron" => Some(SceneFormat::Ron),
+            "json" => Some(SceneFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneFile {
+    pub camera: CameraDesc,
+    /// Lights beyond [`max_lights`](crate::game::light_manager::max_lights) are
+    /// shown but light nothing. A scene without lights gets the default one.
+    pub lights: Vec<LightDesc>,
+    pub materials: Vec<MaterialDesc>,
+    pub objects: Vec<ObjectDesc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraDesc {
+    pub position: Vec3,
+    /// Degrees, 0 looks down -Z.
+    pub yaw: f32,
+    /// Degrees, positive looks down.
+    pub pitch: f32,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    pub orthographic: bool,
+    /// World units from the bottom to the top of the orthographic view.
+    pub ortho_height: f32,
+    /// The point the camera orbits, see [`CameraDesc::focus`].
+    pub target: Vec3,
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0.0, 0.0, 5.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            fov: 45.0,
+            near: 0.1,
+            far: 100.0,
+            orthographic: false,
+            // As tall as the perspective view is at the target
+            ortho_height: 10.0 * 22.5f32.to_radians().tan(),
+            target: Vec3::ZERO,
+        }
+    }
+}
+
+impl CameraDesc {
+    /// Describe where `camera` is and how it sees.
+    pub fn from_camera(camera: &Camera, orthographic: bool) -> Self {
+        Self {
+            position: camera.transform.position,
+            yaw: camera.yaw,
+            pitch: camera.pitch,
+            fov: camera.frustum.fov,
+            near: camera.frustum.near,
+            far: camera.frustum.far,
+            orthographic,
+            ortho_height: camera.frustum.ortho_height,
+            target: camera.target,
+        }
+    }
+
+    /// Move `camera` here. The projection is left to the caller.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.transform.position = self.position;
+        camera.yaw = self.yaw;
+        camera.pitch = self.pitch;
+        camera.frustum.fov = self.fov;
+        camera.frustum.near = self.near;
+        camera.frustum.far = self.far;
+        camera.frustum.ortho_height = self.ortho_height;
+        camera.target = self.focus();
+        camera.update_local_vectors();
+    }
+
+    /// Where the camera looks, as a unit vector. Matches [`Camera::forward`].
+    pub fn forward(&self) -> Vec3 {
+        let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+        Vec3::new(
+            yaw.sin() * pitch.cos(),
+            -pitch.sin(),
+            -yaw.cos() * pitch.cos(),
+        )
+    }
+
+    /// How far ahead [`CameraDesc::focus`] is.
+    pub fn focus_distance(&self) -> f32 {
+        let offset = self.target - self.position;
+        let distance = offset.dot(self.forward());
+        if distance > 1e-3 {
+            distance
+        } else {
+            // Nothing to orbit in front, make up a point
+            offset.length().max(1.0)
+        }
+    }
+
+    /// The target moved onto the line of sight, so orbiting it doesn't turn the camera.
+    pub fn focus(&self) -> Vec3 {
+        self.position + self.forward() * self.focus_distance()
+    }
+
+    /// World units from the bottom to the top of the view at the focus.
+    pub fn view_height(&self) -> f32 {
+        if self.orthographic {
+            self.ortho_height
+        } else {
+            2.0 * self.focus_distance() * (self.fov * 0.5).to_radians().tan()
+        }
+    }
+
+    /// The same view in the other projection, showing the focus just as big.
+    /// Perspective views move along the line of sight to do that.
+    pub fn with_projection(&self, orthographic: bool) -> Self {
+        let mut desc = self.clone();
+        if orthographic == self.orthographic {
+            return desc;
+        }
+
+        desc.orthographic = orthographic;
+        if orthographic {
+            desc.ortho_height = self.view_height();
+        } else {
+            let distance = self.ortho_height * 0.5 / (self.fov * 0.5).to_radians().tan();
+            desc.position = self.focus() - self.forward() * distance;
+            desc.far += (distance - self.focus_distance()).max(0.0);
+        }
+        desc
+    }
+
+    /// The same focus seen from `yaw` and `pitch`, at the same distance.
+    pub fn looking_from(&self, yaw: f32, pitch: f32) -> Self {
+        let mut desc = self.clone();
+        desc.yaw = yaw;
+        desc.pitch = pitch;
+        desc.target = self.focus();
+        desc.position = desc.target - desc.forward() * self.focus_distance();
+        desc
+    }
+
+    /// Check values the parser can't, calling the camera `entry` in errors.
+    pub fn validate(&self, entry: &str) -> Result<(), SceneError> {
+        let invalid = |message: &str| SceneError::Invalid {
+            entry: entry.to_string(),
+            message: message.to_string(),
+        };
+
+        if !(self.fov > 0.0 && self.fov < 180.0) {
+            return Err(invalid("fov must be between 0 and 180 degrees"));
+        }
+        if !(self.near > 0.0 && self.far > self.near) {
+            return Err(invalid("near must be positive and less than far"));
+        }
+        if !self.position.is_finite() || !self.yaw.is_finite() || !self.pitch.is_finite() {
+            return Err(invalid("position and angles must be finite"));
+        }
+        if !self.target.is_finite() {
+            return Err(invalid("target must be finite"));
+        }
+        if !(self.ortho_height > 0.0 && self.ortho_height.is_finite()) {
+            return Err(invalid("orthographic height must be positive"));
+        }
+        Ok(())
+    }
+}
+
+/// A named camera view to come back to, kept in [`BOOKMARKS_FILE`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bookmark {
+    pub name: String,
+    /// The number key, 1 to 9, that goes to the bookmark, if any.
+    #[serde(default)]
+    pub slot: Option<usize>,
+    pub camera: CameraDesc,
+}
+
+/// Where the viewer keeps its bookmarks, relative to the working directory.
+pub const BOOKMARKS_FILE: &str = "bookmarks.ron";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightDesc {
+    pub name: String,
+    pub kind: LightKind,
+    pub position: Vec3,
+    /// The way directional and spot lights shine.
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub ambient: f32,
+    pub specular: f32,
+    /// Constant, linear and quadratic falloff over distance.
+    pub attenuation: Vec3,
+    /// Spot light cone angles in degrees, full strength inside `inner_cone`.
+    pub inner_cone: f32,
+    pub outer_cone: f32,
+    /// Directional and spot lights only.
+    pub cast_shadows: bool,
+    pub shadow_bias: f32,
+    /// Shadow map width and height in texels.
+    pub shadow_resolution: u32,
+    /// Size of the lamp cube drawn at the light.
+    pub scale: f32,
+    /// Image on the lamp cube, the built-in lamp texture if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lamp_texture: Option<PathBuf>,
+}
+
+impl Default for LightDesc {
+    fn default() -> Self {
+        let mut desc = Self {
+            name: "sun".to_string(),
+            kind: LightKind::Point,
+            position: Vec3::new(1.0, 1.0, 1.0),
+            direction: Vec3::ZERO,
+            color: Vec3::ZERO,
+            intensity: 0.0,
+            ambient: 0.0,
+            specular: 0.0,
+            attenuation: Vec3::ZERO,
+            inner_cone: 0.0,
+            outer_cone: 0.0,
+            cast_shadows: false,
+            shadow_bias: 0.0,
+            shadow_resolution: 0,
+            scale: 0.25,
+            lamp_texture: None,
+        };
+        desc.set_source(&LightSource::default());
+        desc
+    }
+}
+
+impl LightDesc {
+    pub fn source(&self) -> LightSource {
+        LightSource {
+            kind: self.kind,
+            color: self.color,
+            intensity: self.intensity,
+            ambient: self.ambient,
+            specular: self.specular,
+            direction: self.direction,
+            attenuation: self.attenuation,
+            inner_cone: self.inner_cone,
+            outer_cone: self.outer_cone,
+            cast_shadows: self.cast_shadows,
+            shadow_bias: self.shadow_bias,
+            shadow_resolution: self.shadow_resolution,
+        }
+    }
+
+    pub fn set_source(&mut self, source: &LightSource) {
+        self.kind = source.kind;
+        self.color = source.color;
+        self.intensity = source.intensity;
+        self.ambient = source.ambient;
+        self.specular = source.specular;
+        self.direction = source.direction;
+        self.attenuation = source.attenuation;
+        self.inner_cone = source.inner_cone;
+        self.outer_cone = source.outer_cone;
+        self.cast_shadows = source.cast_shadows;
+        self.shadow_bias = source.shadow_bias;
+        self.shadow_resolution = source.shadow_resolution;
+    }
+}
+
+/// A material, with the parameters an MTL entry has for Phong shading and
+/// glTF's for metallic-roughness shading.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialDesc {
+    pub name: String,
+    pub shading: ShadingModel,
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub illum: u32,
+    pub emissive: Vec3,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diffuse_map: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specular_map: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal_map: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emissive_map: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metallic_roughness_map: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occlusion_map: Option<PathBuf>,
+}
+
+impl Default for MaterialDesc {
+    fn default() -> Self {
+        Self::from_data(&MaterialData::new(""))
+    }
+}
+
+impl MaterialDesc {
+    /// Convert imported material data. Embedded textures have no path and are dropped.
+    pub fn from_data(data: &MaterialData) -> Self {
+        let map = |source: &Option<TextureSource>| match source {
+            Some(TextureSource::File(path)) => Some(path.clone()),
+            _ => None,
+        };
+
+        Self {
+            name: data.name.clone(),
+            shading: data.shading,
+            ambient: data.ambient,
+            diffuse: data.diffuse,
+            specular: data.specular,
+            shininess: data.shininess,
+            opacity: data.opacity,
+            illum: data.illum,
+            emissive: data.emissive,
+            metallic: data.metallic,
+            roughness: data.roughness,
+            occlusion_strength: data.occlusion_strength,
+            normal_scale: data.normal_scale,
+            diffuse_map: map(&data.diffuse_map),
+            specular_map: map(&data.specular_map),
+            normal_map: map(&data.normal_map),
+            emissive_map: map(&data.emissive_map),
+            metallic_roughness_map: map(&data.metallic_roughness_map),
+            occlusion_map: map(&data.occlusion_map),
+        }
+    }
+
+    pub fn to_data(&self) -> MaterialData {
+        let map = |path: &Option<PathBuf>| path.clone().map(TextureSource::File);
+
+        MaterialData {
+            shading: self.shading,
+            ambient: self.ambient,
+            diffuse: self.diffuse,
+            specular: self.specular,
+            shininess: self.shininess,
+            opacity: self.opacity,
+            illum: self.illum,
+            emissive: self.emissive,
+            metallic: self.metallic,
+            roughness: self.roughness,
+            occlusion_strength: self.occlusion_strength,
+            normal_scale: self.normal_scale,
+            diffuse_map: map(&self.diffuse_map),
+            specular_map: map(&self.specular_map),
+            normal_map: map(&self.normal_map),
+            emissive_map: map(&self.emissive_map),
+            metallic_roughness_map: map(&self.metallic_roughness_map),
+            occlusion_map: map(&self.occlusion_map),
+            ..MaterialData::new(&self.name)
+        }
+    }
+}
+
+/// Where an object's geometry comes from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MeshDesc {
+    Cube,
+    Triangle,
+    /// Any model [`load_model`](crate::loaders::load_model) can read.
+    File(PathBuf),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObjectDesc {
+    pub name: String,
+    pub mesh: MeshDesc,
+    /// Name of an entry in [`SceneFile::materials`]. Model files keep their own
+    /// materials when this is `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+    pub position: Vec3,
+    /// Euler angles in degrees, intrinsic X-Y-Z order.
+    pub rotation: Vec3,
+    pub scale: Vec3,
+    pub velocity: Vec3,
+    /// Play the primitive's built-in animation.
+    pub animate: bool,
+    /// Nodes of the object changed in the viewer, applied after it's built.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeDesc>,
+}
+
+impl Default for ObjectDesc {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            mesh: MeshDesc::Cube,
+            material: None,
+            position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+            scale: Vec3::ONE,
+            velocity: Vec3::ZERO,
+            animate: false,
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl ObjectDesc {
+    pub fn rotation_quat(&self) -> Quat {
+        euler_to_quat(self.rotation)
+    }
+
+    pub fn set_rotation_quat(&mut self, rotation: Quat) {
+        self.rotation = quat_to_euler(rotation);
+    }
+
+    /// Where the object's root node goes.
+    pub fn transform(&self) -> Transform {
+        Transform {
+            position: self.position,
+            rotation: self.rotation_quat(),
+            scale: self.scale,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: &Transform) {
+        self.position = transform.position;
+        self.set_rotation_quat(transform.rotation);
+        self.scale = transform.scale;
+    }
+}
+
+/// A node below an object's root, such as a part of a model file, as it was
+/// moved or recolored in the viewer. Its transform replaces the one the node
+/// was built with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeDesc {
+    /// Child indices from the object's root down to the node, empty for the root.
+    pub path: Vec<usize>,
+    pub position: Vec3,
+    /// Euler angles in degrees, intrinsic X-Y-Z order.
+    pub rotation: Vec3,
+    pub scale: Vec3,
+    /// Replaces the colors and factors of the material drawn at the node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<MaterialFactors>,
+}
+
+impl Default for NodeDesc {
+    fn default() -> Self {
+        Self {
+            path: Vec::new(),
+            position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+            scale: Vec3::ONE,
+            material: None,
+        }
+    }
+}
+
+impl NodeDesc {
+    pub fn transform(&self) -> Transform {
+        Transform {
+            position: self.position,
+            rotation: euler_to_quat(self.rotation),
+            scale: self.scale,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: &Transform) {
+        self.position = transform.position;
+        self.rotation = quat_to_euler(transform.rotation);
+        self.scale = transform.scale;
+    }
+}
+
+/// What the viewer's material editor changes. Texture maps and the shading
+/// model stay as the material was loaded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialFactors {
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub emissive: Vec3,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+}
+
+impl Default for MaterialFactors {
+    fn default() -> Self {
+        Self::from_data(&MaterialData::new(""))
+    }
+}
+
+impl MaterialFactors {
+    pub fn from_material(material: &Material) -> Self {
+        Self::from_data(&MaterialData::from_material(material))
+    }
+
+    fn from_data(data: &MaterialData) -> Self {
+        Self {
+            ambient: data.ambient,
+            diffuse: data.diffuse,
+            specular: data.specular,
+            shininess: data.shininess,
+            opacity: data.opacity,
+            emissive: data.emissive,
+            metallic: data.metallic,
+            roughness: data.roughness,
+            occlusion_strength: data.occlusion_strength,
+            normal_scale: data.normal_scale,
+        }
+    }
+
+    pub fn apply(&self, material: &mut Material) {
+        material.ambient = self.ambient;
+        material.diffuse = self.diffuse;
+        material.specular = self.specular;
+        material.shininess = self.shininess;
+        material.opacity = self.opacity;
+        material.emissive = self.emissive;
+        material.metallic = self.metallic;
+        material.roughness = self.roughness;
+        material.occlusion_strength = self.occlusion_strength;
+        material.normal_scale = self.normal_scale;
+    }
+}
+
+fn euler_to_quat(degrees: Vec3) -> Quat {
+    Quat::from_euler(
+        EulerRot::XYZ,
+        degrees.x.to_radians(),
+        degrees.y.to_radians(),
+        degrees.z.to_radians(),
+    )
+}
+
+fn quat_to_euler(rotation: Quat) -> Vec3 {
+    let (x, y, z) = rotation.to_euler(EulerRot::XYZ);
+    Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees())
+}
+
+impl SceneFile {
+    /// The scene shown when nothing else is loaded: a spinning cube under one lamp.
+    pub fn demo() -> Self {
+        Self {
+            camera: CameraDesc::default(),
+            lights: vec![LightDesc::default()],
+            materials: Vec::new(),
+            objects: vec![ObjectDesc {
+                name: "cube".to_string(),
+                animate: true,
+                ..Default::default()
+            }],
+        }
+    }
+
+    pub fn parse(text: &str, format: SceneFormat) -> Result<Self, SceneError> {
+        let scene: SceneFile = match format {
+            SceneFormat::Ron => ron_options()
+                .from_str(text)
+                .map_err(|e| SceneError::Parse {
+                    message: e.to_string(),
+                })?,
+            SceneFormat::Json => serde_json::from_str(text).map_err(|e| SceneError::Parse {
+                message: e.to_string(),
+            })?,
+        };
+
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Make every relative path absolute by joining it to `base_dir`.
+    pub fn resolve_paths(&mut self, base_dir: &Path) {
+        self.for_each_path(|path| {
+            if path.is_relative() {
+                *path = base_dir.join(&*path);
+            }
+        });
+    }
+
+    /// Make paths below the absolute `base_dir` relative to it, so the scene can be
+    /// moved together with its files. Other paths stay as they are.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn relative_to(&mut self, base_dir: &Path) {
+        self.for_each_path(|path| {
+            let absolute = std::path::absolute(&*path).unwrap_or_else(|_| path.clone());
+            if let Ok(relative) = absolute.strip_prefix(base_dir) {
+                *path = relative.to_path_buf();
+            }
+        });
+    }
+
+    fn for_each_path(&mut self, mut f: impl FnMut(&mut PathBuf)) {
+        for light in &mut self.lights {
+            light.lamp_texture.iter_mut().for_each(&mut f);
+        }
+        for material in &mut self.materials {
+            [
+                &mut material.diffuse_map,
+                &mut material.specular_map,
+                &mut material.normal_map,
+                &mut material.emissive_map,
+                &mut material.metallic_roughness_map,
+                &mut material.occlusion_map,
+            ]
+            .into_iter()
+            .flatten()
+            .for_each(&mut f);
+        }
+        for object in &mut self.objects {
+            if let MeshDesc::File(path) = &mut object.mesh {
+                f(path);
+            }
+        }
+    }
+
+    pub fn to_text(&self, format: SceneFormat) -> Result<String, SceneError> {
+        let error = |message: String| SceneError::Parse { message };
+
+        match format {
+            SceneFormat::Ron => {
+                let config = PrettyConfig::new().struct_names(false);
+                ron_options()
+                    .to_string_pretty(self, config)
+                    .map_err(|e| error(e.to_string()))
+            }
+            SceneFormat::Json => {
+                serde_json::to_string_pretty(self).map_err(|e| error(e.to_string()))
+            }
+        }
+    }
+
+    /// Check values the parser can't, naming the entry at fault.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        let invalid = |entry: String, message: &str| SceneError::Invalid {
+            entry,
+            message: message.to_string(),
+        };
+
+        self.camera.validate("camera")?;
+
+        for (index, light) in self.lights.iter().enumerate() {
+            let entry = || entry_name("light", &light.name, index);
+            if !light.position.is_finite() || !light.direction.is_finite() {
+                return Err(invalid(entry(), "position and direction must be finite"));
+            }
+            if light.kind != LightKind::Point && light.direction.length_squared() < 1e-12 {
+                return Err(invalid(entry(), "direction can't be zero"));
+            }
+            if light.scale <= 0.0 || !light.scale.is_finite() {
+                return Err(invalid(entry(), "scale must be positive"));
+            }
+            let non_negative = |x: f32| x.is_finite() && x >= 0.0;
+            if !light.color.is_finite()
+                || light.color.min_element() < 0.0
+                || !non_negative(light.intensity)
+            {
+                return Err(invalid(entry(), "color and intensity can't be negative"));
+            }
+            if !non_negative(light.ambient) || !non_negative(light.specular) {
+                return Err(invalid(entry(), "ambient and specular can't be negative"));
+            }
+            if !light.attenuation.is_finite()
+                || light.attenuation.min_element() < 0.0
+                || light.attenuation.max_element() <= 0.0
+            {
+                return Err(invalid(
+                    entry(),
+                    "attenuation can't be negative or all zero",
+                ));
+            }
+            if light.kind == LightKind::Spot
+                && !(0.0 <= light.inner_cone
+                    && light.inner_cone <= light.outer_cone
+                    && light.outer_cone < 90.0)
+            {
+                return Err(invalid(
+                    entry(),
+                    "cone angles need 0 <= inner_cone <= outer_cone < 90",
+                ));
+            }
+            if !non_negative(light.shadow_bias) {
+                return Err(invalid(entry(), "shadow_bias can't be negative"));
+            }
+            if !(MIN_SHADOW_RESOLUTION..=MAX_SHADOW_RESOLUTION).contains(&light.shadow_resolution) {
+                return Err(invalid(
+                    entry(),
+                    &format!(
+                        "shadow_resolution must be between {} and {}",
+                        MIN_SHADOW_RESOLUTION, MAX_SHADOW_RESOLUTION
+                    ),
+                ));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for (index, material) in self.materials.iter().enumerate() {
+            let entry = || entry_name("material", &material.name, index);
+            if material.name.is_empty() {
+                return Err(invalid(
+                    entry(),
+                    "materials need a name to be used by objects",
+                ));
+            }
+            if !names.insert(material.name.as_str()) {
+                return Err(invalid(entry(), "name is used by another material"));
+            }
+            if !(0.0..=1.0).contains(&material.opacity) {
+                return Err(invalid(entry(), "opacity must be between 0 and 1"));
+            }
+            if !(0.0..=1.0).contains(&material.metallic)
+                || !(0.0..=1.0).contains(&material.roughness)
+            {
+                return Err(invalid(
+                    entry(),
+                    "metallic and roughness must be between 0 and 1",
+                ));
+            }
+            if !(0.0..=1.0).contains(&material.occlusion_strength) {
+                return Err(invalid(
+                    entry(),
+                    "occlusion strength must be between 0 and 1",
+                ));
+            }
+            if !material.normal_scale.is_finite() {
+                return Err(invalid(entry(), "normal scale must be finite"));
+            }
+        }
+
+        for (index, object) in self.objects.iter().enumerate() {
+            let entry = || entry_name("object", &object.name, index);
+            if let Some(material) = &object.material
+                && !names.contains(material.as_str())
+            {
+                return Err(SceneError::Invalid {
+                    entry: entry(),
+                    message: format!("unknown material '{}'", material),
+                });
+            }
+            if !object.position.is_finite()
+                || !object.rotation.is_finite()
+                || !object.velocity.is_finite()
+            {
+                return Err(invalid(entry(), "transform must be finite"));
+            }
+            if !object.scale.cmpgt(Vec3::ZERO).all() || !object.scale.is_finite() {
+                return Err(invalid(entry(), "scale must be positive"));
+            }
+            if let MeshDesc::File(path) = &object.mesh
+                && path.as_os_str().is_empty()
+            {
+                return Err(invalid(entry(), "mesh file path is empty"));
+            }
+            for node in &object.nodes {
+                if !node.position.is_finite() || !node.rotation.is_finite() {
+                    return Err(invalid(entry(), "node transforms must be finite"));
+                }
+                if !node.scale.cmpgt(Vec3::ZERO).all() || !node.scale.is_finite() {
+                    return Err(invalid(entry(), "node scales must be positive"));
+                }
+                if let Some(material) = &node.material
+                    && !(0.0..=1.0).contains(&material.opacity)
+                {
+                    return Err(invalid(entry(), "node opacity must be between 0 and 1"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `material: "red"` reads better than `material: Some("red")`.
+fn ron_options() -> ron::Options {
+    ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME)
+}
+
+/// How errors refer to an entry: by name, or by position if it has none.
+pub fn entry_name(kind: &str, name: &str, index: usize) -> String {
+    if name.is_empty() {
+        format!("{} #{}", kind, index)
+    } else {
+        format!("{} '{}'", kind, name)
+    }
+}
+
+/// Read and validate a scene file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_scene_file(path: impl AsRef<Path>) -> Result<SceneFile, SceneError> {
+    let path = path.as_ref();
+    let format = SceneFormat::from_path(path)
+        .ok_or_else(|| SceneError::UnsupportedFormat(path.display().to_string()))?;
+    let text = fs::read_to_string(path).map_err(|e| SceneError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    SceneFile::parse(&text, format)
+}
+
+/// Write `scene` to `path` in the format its extension asks for.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_scene_file(path: impl AsRef<Path>, scene: &SceneFile) -> Result<(), SceneError> {
+    let path = path.as_ref();
+    let format = SceneFormat::from_path(path)
+        .ok_or_else(|| SceneError::UnsupportedFormat(path.display().to_string()))?;
+    let text = scene.to_text(format)?;
+
+    fs::write(path, text).map_err(|e| SceneError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Read the bookmarks in the RON file at `path`. A missing file has none, and
+/// bookmarks with an impossible camera are left out.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_bookmarks(path: impl AsRef<Path>) -> Result<Vec<Bookmark>, SceneError> {
+    let path = path.as_ref();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(SceneError::Io {
+                path: path.display().to_string(),
+                message: e.to_string(),
+            });
+        }
+    };
+
+    let mut bookmarks: Vec<Bookmark> =
+        ron_options()
+            .from_str(&text)
+            .map_err(|e| SceneError::Parse {
+                message: e.to_string(),
+            })?;
+    let mut index = 0;
+    bookmarks.retain(|bookmark| {
+        let valid = bookmark
+            .camera
+            .validate(&entry_name("bookmark", &bookmark.name, index))
+            .inspect_err(|e| warn!("Skipping bookmark: {}", e))
+            .is_ok();
+        index += 1;
+        valid
+    });
+    Ok(bookmarks)
+}
+
+/// Write `bookmarks` to `path` as RON.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_bookmarks(path: impl AsRef<Path>, bookmarks: &[Bookmark]) -> Result<(), SceneError> {
+    let path = path.as_ref();
+    let config = PrettyConfig::new().struct_names(false);
+    let text = ron_options()
+        .to_string_pretty(bookmarks, config)
+        .map_err(|e| SceneError::Parse {
+            message: e.to_string(),
+        })?;
+
+    fs::write(path, text).map_err(|e| SceneError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Upload the objects `desc` describes and add them to `graph` below a new root
+/// node, placed by the object's transform. Primitives hang from that node
+/// directly, model files keep their own hierarchy below it. Returns the root
+/// and the objects in it.
+///
+/// Paths must already be resolved, see [`SceneFile::resolve_paths`].
+pub(crate) fn build_object(
+    gl: &GlRef,
+    desc: &ObjectDesc,
+    materials: &[MaterialDesc],
+    graph: &mut SceneGraph,
+) -> Result<(NodeId, Vec<GameObjectRef>), String> {
+    let (root, objects) = build_parts(gl, desc, materials, graph)?;
+
+    for node in &desc.nodes {
+        // The model file may have changed since the scene was saved
+        let Some(id) = node_at(graph, root, &node.path) else {
+            warn!(
+                "'{}' has no node at {:?}, skipping it",
+                desc.name, node.path
+            );
+            continue;
+        };
+        *graph.transform_mut(id) = node.transform();
+        if let Some(material) = &node.material
+            && let Some(renderable) = &graph.node(id).and_then(|n| n.renderable.clone())
+        {
+            material.apply(renderable.borrow_mut().material_mut());
+        }
+    }
+
+    Ok((root, objects))
+}
+
+/// The node `path`, a list of child indices, leads to from `root`.
+pub(crate) fn node_at(graph: &SceneGraph, root: NodeId, path: &[usize]) -> Option<NodeId> {
+    path.iter().try_fold(root, |id, &index| {
+        graph.node(id)?.children().get(index).copied()
+    })
+}
+
+/// The child indices that lead from `root` to `id`, `None` if `id` isn't below it.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn node_path(graph: &SceneGraph, root: NodeId, id: NodeId) -> Option<Vec<usize>> {
+    let mut path = Vec::new();
+    let mut current = id;
+    while current != root {
+        let parent = graph.node(current)?.parent()?;
+        let index = graph
+            .node(parent)?
+            .children()
+            .iter()
+            .position(|&c| c == current)?;
+        path.push(index);
+        current = parent;
+    }
+    path.reverse();
+    Some(path)
+}
+
+fn build_parts(
+    gl: &GlRef,
+    desc: &ObjectDesc,
+    materials: &[MaterialDesc],
+    graph: &mut SceneGraph,
+) -> Result<(NodeId, Vec<GameObjectRef>), String> {
+    let material = match &desc.material {
+        Some(name) => Some(
+            materials
+                .iter()
+                .find(|m| &m.name == name)
+                .ok_or_else(|| format!("unknown material '{}'", name))?
+                .to_data(),
+        ),
+        None => None,
+    };
+
+    let primitive = match &desc.mesh {
+        MeshDesc::File(path) => return build_file(gl, desc, path, material, graph),
+        primitive => primitive,
+    };
+
+    let shading = material.as_ref().map(|m| m.shading).unwrap_or_default();
+    let shader = new_shader_ref(object_shader(gl, shading));
+    let material = match &material {
+        Some(data) => Material::from_data(gl.clone(), shader.clone(), data)
+            .map_err(|e| format!("material '{}': {}", data.name, e))?,
+        None => Material::new(gl.clone(), shader.clone()),
+    };
+
+    let object = match (primitive, desc.animate) {
+        (MeshDesc::Cube, true) => upload(gl, Cube::new(material), desc)?,
+        (MeshDesc::Triangle, true) => upload(gl, Triangle::new(material), desc)?,
+        // Without the animation a primitive is just a mesh
+        (MeshDesc::Cube, false) => {
+            let mesh = Cube::new(material.clone()).mesh;
+            upload(gl, Model::new(&desc.name, material, mesh), desc)?
+        }
+        _ => {
+            let mesh = Triangle::new(material.clone()).mesh;
+            upload(gl, Model::new(&desc.name, material, mesh), desc)?
+        }
+    };
+
+    let root = graph.add_renderable(&desc.name, object.clone(), desc.transform(), None);
+    Ok((root, vec![object]))
+}
+
+/// Upload `object`'s mesh with its material's shader and give it `desc`'s velocity.
+fn upload<T: GameObject + 'static>(
+    gl: &GlRef,
+    mut object: T,
+    desc: &ObjectDesc,
+) -> Result<GameObjectRef, String> {
+    let shader = object.material().shader.clone();
+    object.mesh_mut().upload(gl, shader)?;
+    object.set_velocity(desc.velocity);
+
+    Ok(new_game_obj_ref(object))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn build_file(
+    gl: &GlRef,
+    desc: &ObjectDesc,
+    path: &Path,
+    material: Option<MaterialData>,
+    graph: &mut SceneGraph,
+) -> Result<(NodeId, Vec<GameObjectRef>), String> {
+    let mut model =
+        load_model(path).map_err(|e| format!("failed to load '{}': {}", path.display(), e))?;
+
+    // A scene material replaces all of the file's own
+    if let Some(material) = material {
+        model.materials = vec![material];
+        for part in &mut model.parts {
+            part.material = Some(0);
+        }
+    }
+
+    let root = graph.add_node(&desc.name, desc.transform(), None);
+    let parts = match Model::add_to_graph(gl, model, graph, root) {
+        Ok(parts) => parts,
+        Err(e) => {
+            graph.remove(root);
+            return Err(e);
+        }
+    };
+
+    let objects = parts
+        .into_iter()
+        .map(|(_, part)| {
+            part.borrow_mut().set_velocity(desc.velocity);
+            part
+        })
+        .collect();
+    Ok((root, objects))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn build_file(
+    _gl: &GlRef,
+    _desc: &ObjectDesc,
+    path: &Path,
+    _material: Option<MaterialData>,
+    _graph: &mut SceneGraph,
+) -> Result<(NodeId, Vec<GameObjectRef>), String> {
+    Err(format!("Cannot read '{}' on WASM", path.display()))
+}
+
+/// The lamp `desc` describes, uploaded and ready to draw.
+pub(crate) fn build_light(gl: &GlRef, desc: &LightDesc) -> Result<Light, String> {
+    let mut light = Light::lamp(gl);
+
+    if let Some(path) = &desc.lamp_texture {
+        let texture =
+            TextureBuilder::new(gl.clone()).load_source(&TextureSource::File(path.clone()))?;
+        light.material.texture = Some(new_texture_ref(texture));
+    }
+
+    light.transform.position = desc.position;
+    light.transform.scale = Vec3::splat(desc.scale);
+    light.source = desc.source();
+
+    Ok(light)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file in the temporary directory that's removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("{}-{}", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn bookmarks_round_trip() {
+        let file = TempFile::new("bookmarks.ron");
+        let bookmarks = vec![
+            Bookmark {
+                name: "Front".to_string(),
+                slot: Some(3),
+                camera: CameraDesc::default(),
+            },
+            Bookmark {
+                name: "Panel only".to_string(),
+                slot: None,
+                camera: CameraDesc {
+                    yaw: 45.0,
+                    ..CameraDesc::default()
+                },
+            },
+        ];
+
+        save_bookmarks(&file.0, &bookmarks).unwrap();
+        let loaded = load_bookmarks(&file.0).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].name, "Front");
+        assert_eq!(loaded[0].slot, Some(3));
+        assert_eq!(loaded[1].slot, None);
+        assert_eq!(loaded[1].camera.yaw, 45.0);
+    }
+
+    #[test]
+    fn bookmarks_with_a_bad_camera_are_skipped() {
+        let file = TempFile::new("bad-bookmarks.ron");
+        let mut bookmarks = vec![
+            Bookmark {
+                name: "Good".to_string(),
+                slot: Some(1),
+                camera: CameraDesc::default(),
+            };
+            2
+        ];
+        bookmarks[0].name = "Behind".to_string();
+        bookmarks[0].camera.near = -0.5;
+
+        save_bookmarks(&file.0, &bookmarks).unwrap();
+        let loaded = load_bookmarks(&file.0).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].name, "Good");
+    }
+
+    #[test]
+    fn paths_resolve_against_the_scene() {
+        let base = Path::new("/scenes/garden");
+        let map = |name: &str| Some(PathBuf::from(format!("textures/{}.png", name)));
+        let mut scene = SceneFile {
+            lights: vec![LightDesc {
+                lamp_texture: map("lamp"),
+                ..LightDesc::default()
+            }],
+            materials: vec![MaterialDesc {
+                diffuse_map: map("diffuse"),
+                specular_map: map("specular"),
+                normal_map: map("normal"),
+                emissive_map: map("emissive"),
+                metallic_roughness_map: map("metallic_roughness"),
+                occlusion_map: map("occlusion"),
+                ..MaterialDesc::default()
+            }],
+            objects: vec![ObjectDesc {
+                mesh: MeshDesc::File(PathBuf::from("/models/tree.obj")),
+                ..ObjectDesc::default()
+            }],
+            ..SceneFile::default()
+        };
+        let original = scene.clone();
+
+        scene.resolve_paths(base);
+        let material = &scene.materials[0];
+        let maps = [
+            (&material.diffuse_map, "diffuse"),
+            (&material.specular_map, "specular"),
+            (&material.normal_map, "normal"),
+            (&material.emissive_map, "emissive"),
+            (&material.metallic_roughness_map, "metallic_roughness"),
+            (&material.occlusion_map, "occlusion"),
+            (&scene.lights[0].lamp_texture, "lamp"),
+        ];
+        for (path, name) in maps {
+            assert_eq!(path, &map(name).map(|p| base.join(p)), "{}", name);
+        }
+        // Absolute paths are left alone
+        assert!(
+            matches!(&scene.objects[0].mesh, MeshDesc::File(p) if p == Path::new("/models/tree.obj"))
+        );
+
+        scene.relative_to(base);
+        assert_eq!(
+            scene.to_text(SceneFormat::Ron).unwrap(),
+            original.to_text(SceneFormat::Ron).unwrap()
+        );
+    }
+
+    #[test]
+    fn node_paths() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node("root", Transform::default(), None);
+        let a = graph.add_node("a", Transform::default(), Some(root));
+        let b = graph.add_node("b", Transform::default(), Some(root));
+        let c = graph.add_node("c", Transform::default(), Some(b));
+        let elsewhere = graph.add_node("elsewhere", Transform::default(), None);
+
+        assert_eq!(node_path(&graph, root, root), Some(vec![]));
+        assert_eq!(node_path(&graph, root, a), Some(vec![0]));
+        assert_eq!(node_path(&graph, root, c), Some(vec![1, 0]));
+        assert_eq!(node_path(&graph, root, elsewhere), None);
+
+        assert_eq!(node_at(&graph, root, &[1, 0]), Some(c));
+        assert_eq!(node_at(&graph, root, &[]), Some(root));
+        assert_eq!(node_at(&graph, root, &[2]), None);
+    }
+
+    #[test]
+    fn edited_nodes_round_trip() {
+        let mut scene = SceneFile::demo();
+        let mut node = NodeDesc {
+            path: vec![0, 2],
+            material: Some(MaterialFactors {
+                diffuse: Vec3::X,
+                ..MaterialFactors::default()
+            }),
+            ..NodeDesc::default()
+        };
+        node.set_transform(&Transform {
+            position: Vec3::new(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_y(90f32.to_radians()),
+            ..Default::default()
+        });
+        scene.objects[0].nodes.push(node);
+
+        for format in [SceneFormat::Ron, SceneFormat::Json] {
+            let text = scene.to_text(format).unwrap();
+            let loaded = SceneFile::parse(&text, format).unwrap();
+            let node = &loaded.objects[0].nodes[0];
+            assert_eq!(node.path, [0, 2]);
+            assert_eq!(node.position, Vec3::new(1.0, 2.0, 3.0));
+            assert!((node.rotation.y - 90.0).abs() < 1e-3);
+            assert_eq!(node.material.as_ref().unwrap().diffuse, Vec3::X);
+        }
+
+        // Nothing edited, nothing written
+        let text = SceneFile::demo().to_text(SceneFormat::Ron).unwrap();
+        assert!(!text.contains("nodes"));
+    }
+
+    #[test]
+    fn missing_bookmarks_file() {
+        let file = TempFile::new("no-bookmarks.ron");
+        assert!(load_bookmarks(&file.0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+//! What [`ViewPort`](crate::ViewPort) needs from the thing it draws into.
+//!
+//! A winit [`Window`] is the usual surface. [`FakeSurface`] keeps everything in
+//! memory so a scene can be driven in tests or headless runs without a window.
+
+use std::cell::Cell;
+
+use winit::dpi::PhysicalPosition;
+use winit::window::{CursorGrabMode, Window};
+
+pub trait Surface {
+    /// Drawable size in physical pixels.
+    fn size(&self) -> (u32, u32);
+
+    /// Keep the cursor inside the surface and hide it, or release it again.
+    fn set_cursor_grab(&self, grab: bool);
+
+    /// Move the cursor, in physical pixels from the top left corner.
+    fn set_cursor_position(&self, x: u32, y: u32);
+
+    /// Width over height, or 1 while the surface is minimized.
+    fn aspect_ratio(&self) -> f32 {
+        match self.size() {
+            (0, _) | (_, 0) => 1.0,
+            (width, height) => width as f32 / height as f32,
+        }
+    }
+}
+
+impl Surface for Window {
+    fn size(&self) -> (u32, u32) {
+        let size = self.inner_size();
+        (size.width, size.height)
+    }
+
+    fn set_cursor_grab(&self, grab: bool) {
+        // Not every platform can confine the cursor, locking it works just as well
+        _ = if grab {
+            Window::set_cursor_grab(self, CursorGrabMode::Confined)
+                .or_else(|_| Window::set_cursor_grab(self, CursorGrabMode::Locked))
+        } else {
+            Window::set_cursor_grab(self, CursorGrabMode::None)
+        };
+        self.set_cursor_visible(!grab);
+    }
+
+    fn set_cursor_position(&self, x: u32, y: u32) {
+        _ = Window::set_cursor_position(self, PhysicalPosition::new(x, y));
+    }
+}
+
+/// An in-memory surface that records what was asked of it.
+pub struct FakeSurface {
+    size: Cell<(u32, u32)>,
+    grabbed: Cell<bool>,
+    cursor: Cell<Option<(u32, u32)>>,
+}
+
+impl FakeSurface {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: Cell::new((width, height)),
+            grabbed: Cell::new(false),
+            cursor: Cell::new(None),
+        }
+    }
+
+    /// Change the size. Call [`ViewPort::resize`](crate::ViewPort::resize) afterwards,
+    /// like a window's resize event would.
+    pub fn set_size(&self, width: u32, height: u32) {
+        self.size.set((width, height));
+    }
+
+    pub fn is_grabbed(&self) -> bool {
+        self.grabbed.get()
+    }
+
+    /// Where the cursor was last moved to, if it was moved at all.
+    pub fn cursor_position(&self) -> Option<(u32, u32)> {
+        self.cursor.get()
+    }
+}
+
+impl Surface for FakeSurface {
+    fn size(&self) -> (u32, u32) {
+        self.size.get()
+    }
+
+    fn set_cursor_grab(&self, grab: bool) {
+        self.grabbed.set(grab);
+    }
+
+    fn set_cursor_position(&self, x: u32, y: u32) {
+        self.cursor.set(Some((x, y)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aspect_ratio_follows_the_size() {
+        let surface = FakeSurface::new(1600, 900);
+        assert_eq!(surface.aspect_ratio(), 16.0 / 9.0);
+
+        // Minimized windows report a zero size, which mustn't reach a projection
+        surface.set_size(0, 900);
+        assert_eq!(surface.aspect_ratio(), 1.0);
+        surface.set_size(1600, 0);
+        assert_eq!(surface.aspect_ratio(), 1.0);
+    }
+}
@@ -1,47 +1,354 @@
 #[cfg(not(target_arch = "wasm32"))]
-use dear_imgui_rs::{TreeNodeFlags, Ui};
+use dear_imgui_rs::{Condition, TreeNodeFlags, Ui, WindowFlags};
+#[cfg(not(target_arch = "wasm32"))]
+use glam::{EulerRot, Quat};
 use glam::{Mat4, Vec2, Vec3, vec2, vec4};
 use glow::HasContext;
 #[cfg(not(target_arch = "wasm32"))]
 use log::error;
-use log::info;
-use winit::dpi::PhysicalPosition;
+use log::{info, warn};
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::rc::Rc;
 use winit::event::MouseButton;
-use winit::event_loop::ActiveEventLoop;
 use winit::keyboard::KeyCode;
-use winit::window::CursorGrabMode;
 use winit_input_helper::WinitInputHelper;
 
-use crate::game::{Camera, PhysicsManager, Projection, RenderManager};
+use crate::exporters::ExportScene;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::exporters::{EXPORT_EXTENSIONS, ExportOptions, save_scene};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game::LightKind;
+use crate::game::camera_animation::TRANSITION_TIME;
+use crate::game::picking::pick;
+use crate::game::{
+    Aabb, Camera, CameraMode, CameraTransition, Hit, NodeId, PhysicsManager, Projection, Ray,
+    RenderManager, SceneGraph, Transform, Turntable,
+};
+use crate::graphics::depth::DepthMode;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::depth::reverse_z_supported;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::framebuffer::read_pixels;
 #[cfg(not(target_arch = "wasm32"))]
-use crate::graphics::Shader;
-use crate::graphics::types::{LightObjectRef, new_light_obj_ref};
-use crate::graphics::{
-    GlRef, LIGHT_CUBE_FRAG_PATH, LIGHT_CUBE_FRAG_SRC, LIGHT_CUBE_VERT_PATH, LIGHT_CUBE_VERT_SRC,
-    Material, ShaderRef, Texture, TextureRef, WindowRef, new_game_obj_ref, new_shader_ref,
-    new_texture_ref,
+use crate::graphics::hot_reload::{ReloadError, SHADER_DIR, ShaderWatcher};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::reflection::{is_sampler, type_name};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::render_target::RenderTarget;
+use crate::graphics::types::{GameObjectRef, LightObjectRef, new_light_obj_ref};
+use crate::graphics::{GlRef, PhysicalRef, RenderableRef, SurfaceRef};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::graphics::{Material, Shader, ShaderRef, UniformValue};
+use crate::loaders::ModelData;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::loaders::{MODEL_EXTENSIONS, ShadingModel, load_model};
+use crate::objects::Model;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::scene::{
+    BOOKMARKS_FILE, NodeDesc, SCENE_EXTENSIONS, SceneFormat, load_bookmarks, load_scene_file,
+    node_path, save_bookmarks, save_scene_file,
+};
+use crate::scene::{
+    Bookmark, CameraDesc, LightDesc, MaterialDesc, MaterialFactors, MeshDesc, ObjectDesc,
+    SceneFile, build_light, build_object, entry_name, node_at,
 };
-use crate::loaded_shader;
-use crate::objects::{Cube, Light};
+
+/// How a loaded model is placed in the scene.
+pub struct ModelOptions {
+    /// Uniform scale applied to every part.
+    pub scale: f32,
+    /// Camera position; the camera still looks at the model. `None` frames the whole model.
+    pub camera_position: Option<Vec3>,
+}
+
+/// Shadow map sizes offered in the "Lights" panel.
+#[cfg(not(target_arch = "wasm32"))]
+const SHADOW_RESOLUTIONS: [u32; 5] = [256, 512, 1024, 2048, 4096];
+
+/// Most entries kept in the "Open" dialog's recent files list.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_RECENT_FILES: usize = 10;
+
+/// Keys that go to the bookmark of the same number, or save it with Ctrl.
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Pixels the cursor may move between pressing and releasing a button for it
+/// to count as a click rather than a drag.
+const CLICK_DISTANCE: f32 = 4.0;
+
+/// Named views offered as presets, as yaw and pitch in degrees. The last is isometric.
+const VIEW_PRESETS: [(&str, f32, f32); 4] = [
+    ("Front", 0.0, 0.0),
+    ("Side", -90.0, 0.0),
+    ("Top", 0.0, 90.0),
+    ("Iso", -45.0, 35.264),
+];
+
+/// State of the "Open" dialog.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct OpenDialog {
+    path: String,
+    recent: Vec<PathBuf>,
+    error: Option<String>,
+}
+
+/// State of the "Export" dialog.
+#[cfg(not(target_arch = "wasm32"))]
+struct ExportDialog {
+    path: String,
+    options: ExportOptions,
+    error: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ExportDialog {
+    fn default() -> Self {
+        Self {
+            path: "export.obj".to_string(),
+            options: ExportOptions::default(),
+            error: None,
+        }
+    }
+}
+
+/// State of the "Save Scene" dialog.
+#[cfg(not(target_arch = "wasm32"))]
+struct SceneDialog {
+    path: String,
+    error: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SceneDialog {
+    fn default() -> Self {
+        Self {
+            path: "scene.ron".to_string(),
+            error: None,
+        }
+    }
+}
+
+/// State of the turntable's sequence capture.
+#[cfg(not(target_arch = "wasm32"))]
+struct CaptureDialog {
+    directory: String,
+    /// Frames in a full turn.
+    frames: i32,
+    /// Next frame to save, `None` when not capturing.
+    frame: Option<u32>,
+    error: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for CaptureDialog {
+    fn default() -> Self {
+        Self {
+            directory: "turntable".to_string(),
+            frames: 120,
+            frame: None,
+            error: None,
+        }
+    }
+}
+
+/// State of the "Environment" panel.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct EnvironmentDialog {
+    path: String,
+    /// File the current environment came from.
+    loaded: Option<PathBuf>,
+    background_path: String,
+    /// Image or directory the current background came from.
+    background_loaded: Option<PathBuf>,
+    error: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl OpenDialog {
+    fn add_recent(&mut self, path: &Path) {
+        self.recent.retain(|p| p != path);
+        self.recent.insert(0, path.to_path_buf());
+        self.recent.truncate(MAX_RECENT_FILES);
+    }
+}
+
+impl Default for ModelOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            camera_position: None,
+        }
+    }
+}
+
+/// One object of the scene file and everything that was built for it.
+struct SceneObject {
+    desc: ObjectDesc,
+    /// Root of the object in the scene graph, placed by `desc`'s transform.
+    node: NodeId,
+    parts: Vec<GameObjectRef>,
+}
+
+/// One light of the scene file and the lamp that was built for it.
+struct SceneLight {
+    desc: LightDesc,
+    light: LightObjectRef,
+}
+
+impl SceneLight {
+    fn new(gl: &GlRef, desc: LightDesc) -> Result<Self, String> {
+        let light = build_light(gl, &desc)?;
+        Ok(Self {
+            desc,
+            light: new_light_obj_ref(light),
+        })
+    }
+
+    /// `desc` updated with how the light is now.
+    fn snapshot(&self) -> LightDesc {
+        let light = self.light.borrow();
+        let mut desc = LightDesc {
+            position: light.transform().position,
+            scale: light.transform().scale.x,
+            ..self.desc.clone()
+        };
+        desc.set_source(light.source());
+        desc
+    }
+}
+
+/// A scene that is uploaded but not shown yet.
+struct BuiltScene {
+    camera: CameraDesc,
+    lights: Vec<SceneLight>,
+    materials: Vec<MaterialDesc>,
+    objects: Vec<SceneObject>,
+}
+
+impl BuiltScene {
+    /// Build every entry of `scene` into `graph` up front, so one that fails leaves
+    /// the current scene untouched. Errors name the entry at fault.
+    fn new(
+        gl: &GlRef,
+        mut scene: SceneFile,
+        base_dir: &Path,
+        graph: &mut SceneGraph,
+    ) -> Result<Self, String> {
+        scene.validate().map_err(|e| e.to_string())?;
+        scene.resolve_paths(base_dir);
+
+        // A scene without lights would be black
+        if scene.lights.is_empty() {
+            scene.lights.push(LightDesc::default());
+        }
+
+        let mut lights = Vec::with_capacity(scene.lights.len());
+        for (index, desc) in scene.lights.into_iter().enumerate() {
+            let name = entry_name("light", &desc.name, index);
+            lights.push(SceneLight::new(gl, desc).map_err(|e| format!("{}: {}", name, e))?);
+        }
+
+        let mut objects: Vec<SceneObject> = Vec::with_capacity(scene.objects.len());
+        for (index, desc) in scene.objects.into_iter().enumerate() {
+            match build_object(gl, &desc, &scene.materials, graph) {
+                Ok((node, parts)) => objects.push(SceneObject { desc, node, parts }),
+                Err(e) => {
+                    for object in objects {
+                        graph.remove(object.node);
+                    }
+                    return Err(format!(
+                        "{}: {}",
+                        entry_name("object", &desc.name, index),
+                        e
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            camera: scene.camera,
+            lights,
+            materials: scene.materials,
+            objects,
+        })
+    }
+}
 
 pub struct ViewPort {
-    window: WindowRef,
+    surface: SurfaceRef,
     gl: GlRef,
 
     camera: Camera,
     enable_2d: bool,
     capture_mouse: bool,
+    /// The GUI is under the cursor, so the orbit controls leave the mouse alone.
+    gui_has_mouse: bool,
+    /// A text field has focus, so keys are typed rather than shortcuts.
+    gui_has_keyboard: bool,
     last_mouse_pos: Vec2,
+    exit_requested: bool,
 
     render_manager: RenderManager,
     physics_manager: PhysicsManager,
     projection_matrix: Mat4,
     view_matrix: Mat4,
-    sun: LightObjectRef,
+    lights: Vec<SceneLight>,
+    objects: Vec<SceneObject>,
+    // What the scene file said, for saving and the reset buttons
+    scene_camera: CameraDesc,
+    scene_materials: Vec<MaterialDesc>,
+    /// Saved views, kept in [`BOOKMARKS_FILE`] on native.
+    bookmarks: Vec<Bookmark>,
+    /// Easing towards a bookmark, the camera follows it until it's done.
+    camera_transition: Option<CameraTransition>,
+    turntable: Turntable,
+    /// Fit the clip planes around the scene every frame.
+    auto_clip: bool,
+    /// Where the main pass draws with reverse-Z, the window has no float depth.
+    #[cfg(not(target_arch = "wasm32"))]
+    render_target: Option<RenderTarget>,
+    #[cfg(not(target_arch = "wasm32"))]
+    capture_dialog: CaptureDialog,
+    #[cfg(not(target_arch = "wasm32"))]
+    open_dialog: OpenDialog,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_dialog: ExportDialog,
+    #[cfg(not(target_arch = "wasm32"))]
+    scene_dialog: SceneDialog,
+    #[cfg(not(target_arch = "wasm32"))]
+    environment_dialog: EnvironmentDialog,
+    /// Node shown in the "Scene" panel's editor, and framed by "Frame Selection".
+    selected_node: Option<NodeId>,
+    /// The selection was picked in the viewport, so the "Scene" panel opens up to it.
+    #[cfg(not(target_arch = "wasm32"))]
+    reveal_selection: bool,
+    /// Where the left button went down, for telling clicks from drags.
+    click_start: Option<Vec2>,
+    /// Reloads shaders when their files change, `None` when there's nothing to watch.
+    #[cfg(not(target_arch = "wasm32"))]
+    shader_watcher: Option<ShaderWatcher>,
+    /// Shaders that failed to reload and haven't since, shown over the viewport.
+    #[cfg(not(target_arch = "wasm32"))]
+    shader_errors: Vec<ReloadError>,
 }
 
 impl ViewPort {
-    pub fn new(window: WindowRef, gl: GlRef, (width, height): (u32, u32)) -> Self {
+    pub fn new(surface: SurfaceRef, gl: GlRef, (width, height): (u32, u32)) -> Self {
         unsafe {
             info!("Initial viewport: {}/{}", width, height);
 
@@ -49,100 +356,462 @@ impl ViewPort {
             gl.enable(glow::DEPTH_TEST);
         }
 
-        let mut camera = Camera::new(0.1, 100.0);
+        let camera = Camera::new(0.1, 100.0);
         let mut renderer = RenderManager::new(gl.clone()).unwrap();
-        let mut physics_manager = PhysicsManager::new();
-
-        let light_shader: ShaderRef = {
-            let mut shader = crate::graphics::Shader::new(gl.clone());
-            let _ = shader.add(
-                glow::FRAGMENT_SHADER,
-                LIGHT_CUBE_FRAG_SRC,
-                LIGHT_CUBE_FRAG_PATH,
-            );
-            let _ = shader.add(
-                glow::VERTEX_SHADER,
-                LIGHT_CUBE_VERT_SRC,
-                LIGHT_CUBE_VERT_PATH,
-            );
-            let _ = shader.link();
+        let physics_manager = PhysicsManager::new();
+
+        let scene = BuiltScene::new(&gl, SceneFile::demo(), Path::new(""), &mut renderer.graph)
+            .expect("Failed to build the demo scene");
+
+        let mut view_port = ViewPort {
+            surface,
+            gl,
 
-            shader.add_attribute("i_position");
-            shader.add_attribute("i_uv");
+            camera,
+            render_manager: renderer,
+            physics_manager,
+            enable_2d: false,
+            capture_mouse: false,
+            gui_has_mouse: false,
+            gui_has_keyboard: false,
+            last_mouse_pos: Vec2::ZERO,
+            exit_requested: false,
 
-            new_shader_ref(shader)
+            projection_matrix: Mat4::IDENTITY,
+            view_matrix: Mat4::IDENTITY,
+            lights: Vec::new(),
+            objects: Vec::new(),
+            scene_camera: CameraDesc::default(),
+            scene_materials: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            bookmarks: load_bookmarks(BOOKMARKS_FILE)
+                .inspect_err(|e| warn!("Failed to load bookmarks: {}", e))
+                .unwrap_or_default(),
+            #[cfg(target_arch = "wasm32")]
+            bookmarks: Vec::new(),
+            camera_transition: None,
+            turntable: Turntable::default(),
+            auto_clip: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            render_target: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            capture_dialog: CaptureDialog::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            open_dialog: OpenDialog::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            export_dialog: ExportDialog::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            scene_dialog: SceneDialog::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            environment_dialog: EnvironmentDialog::default(),
+            selected_node: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            reveal_selection: false,
+            click_start: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            shader_watcher: ShaderWatcher::new(Path::new(SHADER_DIR))
+                .inspect_err(|e| info!("Shaders won't reload on their own: {}", e))
+                .ok(),
+            #[cfg(not(target_arch = "wasm32"))]
+            shader_errors: Vec::new(),
         };
-        let mut light_material = Material::new(gl.clone(), light_shader.clone());
-        let light_texture: TextureRef = {
-            let tex = Texture::from_bytes(
-                gl.clone(),
-                include_bytes!("objects/textures/redstone_lamp.png"),
-            )
-            .expect("Failed to load texture");
-            new_texture_ref(tex)
+        view_port.show_scene(scene);
+
+        // Calculate initial projection matrix using the passed dimensions
+        let aspect = width as f32 / height as f32;
+        view_port.projection_matrix = view_port
+            .camera
+            .get_camera_projection_matrix(Projection::Perspective(aspect));
+
+        view_port
+    }
+
+    /// Open a scene or model file in place of the current scene, depending on
+    /// its extension.
+    ///
+    /// Failures are also kept for the "Open" dialog to show.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open(&mut self, path: &Path) -> Result<(), String> {
+        let result = if SceneFormat::from_path(path).is_some() {
+            self.open_scene(path)
+        } else {
+            load_model(path)
+                .map_err(|e| e.to_string())
+                .and_then(|model| self.load_model(model, &ModelOptions::default()))
         };
-        light_material.texture = Some(light_texture);
 
-        let mut light = Light::new(light_material);
-        light
-            .mesh
-            .upload(&gl, light_shader)
-            .expect("Failed to upload mesh");
+        match &result {
+            Ok(_) => {
+                self.open_dialog.error = None;
+                self.open_dialog.add_recent(path);
+            }
+            Err(e) => self.open_dialog.error = Some(format!("{}: {}", path.display(), e)),
+        }
 
-        light.transform.position = Vec3::new(1.0, 1.0, 1.0);
-        light.transform.scale = Vec3::new(0.25, 0.25, 0.25);
+        result
+    }
 
-        let light_ref = new_light_obj_ref(light);
-        renderer.add_renderable(light_ref.clone());
+    /// Replace the current model with `model` and point the camera at it.
+    pub fn load_model(&mut self, model: ModelData, options: &ModelOptions) -> Result<(), String> {
+        // Models that didn't come from a file can't be saved in a scene
+        let desc = ObjectDesc {
+            name: model.name.clone(),
+            mesh: MeshDesc::File(model.source.clone().unwrap_or_default()),
+            scale: Vec3::splat(options.scale),
+            ..Default::default()
+        };
 
-        let obj_shader: ShaderRef = {
-            let shader = loaded_shader!(gl.clone());
-            new_shader_ref(shader)
+        // The parts move together, scaled about the model's origin
+        let graph = &mut self.render_manager.graph;
+        let node = graph.add_node(&desc.name, desc.transform(), None);
+        let parts = match Model::add_to_graph(&self.gl, model, graph, node) {
+            Ok(parts) => parts.into_iter().map(|(_, part)| part).collect(),
+            Err(e) => {
+                graph.remove(node);
+                return Err(e);
+            }
         };
-        let obj_material = Material::new(gl.clone(), obj_shader.clone());
 
-        let mut cube = Cube::new(obj_material);
-        cube.mesh
-            .upload(&gl, obj_shader)
-            .expect("Failed to upload mesh");
+        self.clear_model();
+        self.scene_materials.clear();
+        self.add_object(SceneObject { desc, node, parts });
 
-        let cube_ref = new_game_obj_ref(cube);
+        let object = &self.objects[0];
+        info!(
+            "Loaded model '{}' ({} parts)",
+            object.desc.name,
+            object.parts.len()
+        );
 
-        renderer.add_renderable(cube_ref.clone());
-        physics_manager.add_physical(cube_ref);
+        if let Some(bounds) = self.model_bounds() {
+            self.frame_bounds(&bounds, options.camera_position);
+        }
 
-        camera.transform.position = Vec3::new(0.0, 0.0, 5.0);
+        Ok(())
+    }
 
-        // Calculate initial projection matrix using the passed dimensions
-        let aspect = width as f32 / height as f32;
-        let projection_matrix =
-            camera.get_camera_projection_matrix(Projection::Perspective(aspect));
+    /// Replace the current scene with `scene`. Relative paths in it are resolved
+    /// against `base_dir`.
+    pub fn load_scene(&mut self, scene: SceneFile, base_dir: &Path) -> Result<(), String> {
+        let scene = BuiltScene::new(&self.gl, scene, base_dir, &mut self.render_manager.graph)?;
+        self.show_scene(scene);
+        self.set_projection_matrix();
+        self.update_mouse_capture_state();
 
-        ViewPort {
-            window,
-            gl,
+        info!("Loaded scene ({} objects)", self.objects.len());
+        Ok(())
+    }
 
-            camera,
-            render_manager: renderer,
-            physics_manager,
-            enable_2d: false,
-            capture_mouse: false,
-            last_mouse_pos: Vec2::ZERO,
+    /// Light the scene with an equirectangular `.hdr` and show it behind
+    /// everything. The current environment stays if it fails.
+    ///
+    /// Failures are also kept for the "Environment" panel to show.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_environment(&mut self, path: &Path) -> Result<(), String> {
+        let dialog = &mut self.environment_dialog;
+        dialog.path = path.display().to_string();
 
-            projection_matrix,
-            view_matrix: Mat4::IDENTITY,
-            sun: light_ref,
+        match self.render_manager.environment.load_file(path) {
+            Ok(()) => {
+                info!("Loaded environment '{}'", path.display());
+                dialog.loaded = Some(path.to_path_buf());
+                dialog.error = None;
+                Ok(())
+            }
+            Err(e) => {
+                dialog.error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    /// Show a cubemap behind everything in place of the environment, from a
+    /// directory of faces, a cross or an equirectangular image. The current
+    /// background stays if it fails.
+    ///
+    /// Failures are also kept for the "Environment" panel to show.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_background(&mut self, path: &Path) -> Result<(), String> {
+        let dialog = &mut self.environment_dialog;
+        dialog.background_path = path.display().to_string();
+
+        match self.render_manager.environment.load_background(path) {
+            Ok(()) => {
+                info!("Loaded background '{}'", path.display());
+                dialog.background_loaded = Some(path.to_path_buf());
+                dialog.error = None;
+                Ok(())
+            }
+            Err(e) => {
+                dialog.error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    /// Read a scene file and replace the current scene with it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_scene(&mut self, path: &Path) -> Result<(), String> {
+        let scene = load_scene_file(path).map_err(|e| e.to_string())?;
+        self.load_scene(scene, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Describe the current scene. The camera, lights and object placement are
+    /// saved as they are now.
+    pub fn scene_file(&self) -> SceneFile {
+        let lights = self.lights.iter().map(SceneLight::snapshot).collect();
+
+        let objects = self
+            .objects
+            .iter()
+            .filter(|object| match &object.desc.mesh {
+                MeshDesc::File(path) if path.as_os_str().is_empty() => {
+                    warn!(
+                        "'{}' wasn't loaded from a file, leaving it out of the scene",
+                        object.desc.name
+                    );
+                    false
+                }
+                _ => true,
+            })
+            .map(|object| {
+                let graph = &self.render_manager.graph;
+                let mut desc = object.desc.clone();
+                desc.set_transform(graph.transform(object.node));
+                for node in &mut desc.nodes {
+                    let Some(id) = node_at(graph, object.node, &node.path) else {
+                        continue;
+                    };
+                    node.set_transform(graph.transform(id));
+                    if node.material.is_some()
+                        && let Some(renderable) = graph.node(id).and_then(|n| n.renderable.as_ref())
+                    {
+                        let material =
+                            MaterialFactors::from_material(renderable.borrow().material());
+                        node.material = Some(material);
+                    }
+                }
+                desc
+            })
+            .collect();
+
+        SceneFile {
+            camera: self.camera_desc(),
+            lights,
+            materials: self.scene_materials.clone(),
+            objects,
+        }
+    }
+
+    /// Write the current scene to `path` as RON or JSON, picked by its extension.
+    /// Paths below the scene's directory are stored relative to it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_scene(&self, path: &Path) -> Result<(), String> {
+        let mut scene = self.scene_file();
+        if let Some(dir) = std::path::absolute(path)
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf))
+        {
+            scene.relative_to(&dir);
+        }
+
+        save_scene_file(path, &scene).map_err(|e| e.to_string())?;
+        info!("Saved scene to '{}'", path.display());
+        Ok(())
+    }
+
+    /// Swap the current scene for `scene`, without touching the projection.
+    fn show_scene(&mut self, scene: BuiltScene) {
+        self.clear_model();
+
+        for light in std::mem::take(&mut self.lights) {
+            self.remove_light(&light);
+        }
+        for light in scene.lights {
+            self.add_light(light);
+        }
+
+        for object in scene.objects {
+            self.add_object(object);
+        }
+
+        scene.camera.apply(&mut self.camera);
+        self.enable_2d = scene.camera.orthographic;
+        self.scene_camera = scene.camera;
+        self.scene_materials = scene.materials;
+    }
+
+    /// Draw `light`'s lamp and light the scene with it.
+    fn add_light(&mut self, light: SceneLight) {
+        self.render_manager.graph.add_renderable(
+            &light.desc.name,
+            light.light.clone(),
+            Transform::default(),
+            None,
+        );
+        self.render_manager.lights.add_light(light.light.clone());
+        self.lights.push(light);
+    }
+
+    /// Take `light` out of the graph and the light manager. It stays in `self.lights`.
+    fn remove_light(&mut self, light: &SceneLight) {
+        let lamp: RenderableRef = light.light.clone();
+        self.render_manager.remove_renderable(&lamp);
+        self.render_manager.lights.remove_light(&light.light);
+    }
+
+    /// Start simulating `object`, which is in the scene graph already.
+    fn add_object(&mut self, object: SceneObject) {
+        for part in &object.parts {
+            self.physics_manager.add_physical(part.clone());
+        }
+        self.objects.push(object);
+    }
+
+    /// Snapshot of the current scene for the exporters, named after the graph's nodes.
+    pub fn export_scene(&self) -> ExportScene {
+        let graph = &self.render_manager.graph;
+        let mut scene = ExportScene::new();
+
+        for object in &self.objects {
+            for (id, node) in graph.subtree(object.node) {
+                if let Some(renderable) = &node.renderable {
+                    scene.add_renderable(&node.name, &*renderable.borrow(), graph.world_matrix(id));
+                }
+            }
+        }
+        scene
+    }
+
+    /// Write the current model to `path`, picking the format from its extension.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_model(&self, path: &Path, options: &ExportOptions) -> Result<(), String> {
+        save_scene(path, &self.export_scene(), options).map_err(|e| e.to_string())?;
+        info!("Exported model to '{}'", path.display());
+        Ok(())
+    }
+
+    /// Remove every object from the scene. The camera and lights stay.
+    pub fn clear_model(&mut self) {
+        for object in self.objects.drain(..) {
+            self.render_manager.graph.remove(object.node);
+            for part in object.parts {
+                let physical: PhysicalRef = part;
+                self.physics_manager.remove_physical(&physical);
+            }
+        }
+    }
+
+    /// World-space bounds of everything in the scene.
+    pub fn model_bounds(&self) -> Option<Aabb> {
+        self.objects
+            .iter()
+            .filter_map(|o| self.render_manager.graph.bounds(o.node))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Move the camera so `bounds` fills the view, or to `position` looking at its center.
+    fn frame_bounds(&mut self, bounds: &Aabb, position: Option<Vec3>) {
+        self.camera
+            .frame(bounds, self.surface.aspect_ratio(), position);
+        self.set_projection_matrix();
+
+        // Keep the key light just outside the model, like the default scene has it
+        if let Some(key) = self.lights.first() {
+            let radius = bounds.radius().max(1e-3);
+            key.light.borrow_mut().transform_mut().position = bounds.center() + Vec3::splat(radius);
+        }
+    }
+
+    /// Fit the selected node, or everything when nothing is, into the view
+    /// without turning the camera. The orbit controls then turn around it.
+    pub fn frame_selection(&mut self) {
+        let graph = &self.render_manager.graph;
+        let bounds = self
+            .selected_node
+            .filter(|id| graph.contains(*id))
+            .and_then(|id| graph.bounds(id))
+            .or_else(|| self.model_bounds());
+        if let Some(bounds) = bounds {
+            self.fit_bounds(&bounds);
+        }
+    }
+
+    /// Fit everything in the scene into the view without turning the camera.
+    pub fn frame_all(&mut self) {
+        if let Some(bounds) = self.model_bounds() {
+            self.fit_bounds(&bounds);
+        }
+    }
+
+    fn fit_bounds(&mut self, bounds: &Aabb) {
+        self.camera.fit(bounds, self.surface.aspect_ratio());
+        self.set_projection_matrix();
+    }
+
+    /// Draw with `depth` from now on. Reverse-Z falls back to standard depth
+    /// where there's no clip control or float depth target to go with it.
+    pub fn set_depth_mode(&mut self, depth: DepthMode) {
+        let depth = if depth == DepthMode::Reversed && !self.reverse_z_available() {
+            warn!("Reverse-Z isn't supported here, keeping standard depth");
+            DepthMode::Standard
+        } else {
+            depth
+        };
+
+        self.camera.frustum.depth = depth;
+        self.set_projection_matrix();
+        info!("Depth: {:?}", depth);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reverse_z_available(&self) -> bool {
+        reverse_z_supported()
+    }
+
+    // WebGL2 has neither clip control nor a way to fake it
+    #[cfg(target_arch = "wasm32")]
+    fn reverse_z_available(&self) -> bool {
+        false
+    }
+
+    /// Where the camera is and how it sees, to save or ease from. Clip planes
+    /// fitted to the scene stay out of it, as they only suit this frame and an
+    /// orthographic near plane may be behind the camera.
+    fn camera_desc(&self) -> CameraDesc {
+        let mut desc = CameraDesc::from_camera(&self.camera, self.enable_2d);
+        if self.auto_clip {
+            desc.near = self.scene_camera.near;
+            desc.far = self.scene_camera.far;
+        }
+        desc
+    }
+
+    /// Fit the clip planes to the scene, if they follow it.
+    fn clip_to_scene(&mut self) {
+        if !self.auto_clip {
+            return;
+        }
+        if let Some(bounds) = self.model_bounds() {
+            self.camera.clip_to_bounds(&bounds, self.enable_2d);
+            self.set_projection_matrix();
         }
     }
 
-    // Set projection matrix based on current window size, fov, and mode (2D/3D)
+    fn set_camera_mode(&mut self, mode: CameraMode) {
+        self.camera.set_mode(mode);
+        self.update_mouse_capture_state();
+        info!("Camera mode: {}", mode.name());
+    }
+
+    // Set projection matrix based on current surface size, fov, and mode (2D/3D)
     fn set_projection_matrix(&mut self) {
-        let size = self.window.inner_size();
-        if size.width == 0 || size.height == 0 {
+        let (width, height) = self.surface.size();
+        if width == 0 || height == 0 {
             return;
         }
 
-        let aspect = size.width as f32 / size.height as f32;
+        let aspect = width as f32 / height as f32;
 
         let projection = if self.enable_2d {
             Projection::Orthographic(aspect)
@@ -154,27 +823,18 @@ impl ViewPort {
     }
 
     fn update_mouse_capture_state(&mut self) {
-        // Only confine and hide cursor in 3D mode with capture enabled
-        let confine = self.capture_mouse && !self.enable_2d;
-        _ = if confine {
-            self.window
-                .set_cursor_grab(CursorGrabMode::Confined)
-                .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Locked))
-        } else {
-            self.window.set_cursor_grab(CursorGrabMode::None)
-        };
-        self.window.set_cursor_visible(!confine);
+        self.surface.set_cursor_grab(self.cursor_confined());
     }
 
-    pub fn handle_input(
-        &mut self,
-        _dt: f32,
-        input: &WinitInputHelper,
-        _event_loop: &ActiveEventLoop,
-    ) {
+    // Only confine and hide cursor in 3D mode with capture enabled, orbiting drags a visible cursor
+    fn cursor_confined(&self) -> bool {
+        self.capture_mouse && !self.enable_2d && self.camera.mode == CameraMode::Fps
+    }
+
+    pub fn handle_input(&mut self, _dt: f32, input: &WinitInputHelper) {
         #[cfg(not(target_arch = "wasm32"))]
         if input.key_pressed(KeyCode::Escape) {
-            _event_loop.exit();
+            self.exit_requested = true;
         }
         if input.key_pressed(KeyCode::F1) {
             self.capture_mouse = !self.capture_mouse;
@@ -182,49 +842,281 @@ impl ViewPort {
             info!("Capturing mouse: {}", self.capture_mouse);
         }
         if input.key_pressed(KeyCode::F2) {
-            self.enable_2d = true;
-            self.set_projection_matrix();
-            self.update_mouse_capture_state();
+            self.switch_projection(true);
         }
         if input.key_pressed(KeyCode::F3) {
-            self.enable_2d = false;
-            self.set_projection_matrix();
-            self.update_mouse_capture_state();
+            self.switch_projection(false);
+        }
+        if input.key_pressed(KeyCode::F4) {
+            let mode = match self.camera.mode {
+                CameraMode::Fps => CameraMode::Orbit,
+                CameraMode::Orbit => CameraMode::Fps,
+            };
+            self.set_camera_mode(mode);
+        }
+        if !self.gui_has_keyboard {
+            self.handle_view_keys(input);
         }
         #[cfg(not(target_arch = "wasm32"))]
         if input.key_pressed(KeyCode::KeyR) {
             info!("Reloading Shaders");
+            self.reload_shaders(|_| true);
+        }
 
-            self.render_manager.render_targets.iter().for_each(|o| {
-                let mut obj = o.borrow_mut();
-                let shader = obj.material_mut().shader_mut();
-
-                match Shader::reload_shader(self.gl.clone(), shader) {
-                    Ok(_) => info!("Successfully reloaded shader: {:?}", shader.handle),
-                    Err(e) => error!("Failed to reload shader: {}", e),
-                }
-            });
+        if !self.gui_has_mouse && !self.cursor_confined() {
+            self.handle_click(input);
         }
 
-        if self.capture_mouse {
+        if self.camera.mode == CameraMode::Orbit {
+            if !self.gui_has_mouse {
+                self.handle_orbit(input);
+            }
+        } else if self.capture_mouse {
             self.handle_mouse(input);
         }
     }
 
-    fn normalize_cursor(&mut self, cursor: Vec2) -> Vec3 {
-        let size = self.window.inner_size();
-        // https://antongerdelan.net/opengl/raycasting.html
-        let ndc = vec2(
-            (2.0 * cursor.x) / size.width as f32 - 1.0,
-            1.0 - (2.0 * cursor.y) / size.height as f32,
-        );
-        let clip = vec4(ndc.x, ndc.y, -1.0, 1.0);
-        let mut eye = self.projection_matrix.inverse() * clip;
-        eye.z = -1.0;
-        eye.w = 0.0;
-        (self.view_matrix.inverse() * eye).truncate()
-    }
-
+    /// Framing, bookmarks and the turntable.
+    fn handle_view_keys(&mut self, input: &WinitInputHelper) {
+        if input.key_pressed(KeyCode::KeyF) {
+            self.frame_selection();
+        }
+        if input.key_pressed(KeyCode::Home) {
+            self.frame_all();
+        }
+        if input.key_pressed(KeyCode::KeyT) {
+            self.turntable.playing = !self.turntable.playing;
+        }
+        if input.key_pressed(KeyCode::Numpad5) {
+            self.switch_projection(!self.enable_2d);
+        }
+
+        let preset_keys = [
+            KeyCode::Numpad1,
+            KeyCode::Numpad3,
+            KeyCode::Numpad7,
+            KeyCode::Numpad9,
+        ];
+        for (key, (_, yaw, pitch)) in preset_keys.into_iter().zip(VIEW_PRESETS) {
+            if input.key_pressed(key) {
+                self.view_preset(yaw, pitch);
+            }
+        }
+
+        for (index, key) in BOOKMARK_KEYS.into_iter().enumerate() {
+            if !input.key_pressed(key) {
+                continue;
+            }
+            let slot = index + 1;
+            if input.held_control() {
+                self.save_to_slot(slot);
+            } else if let Some(index) = self.bookmark_in_slot(slot) {
+                self.go_to_bookmark(index);
+            }
+        }
+    }
+
+    /// Index of the bookmark number key `slot` goes to.
+    fn bookmark_in_slot(&self, slot: usize) -> Option<usize> {
+        self.bookmarks.iter().position(|b| b.slot == Some(slot))
+    }
+
+    /// Ease the camera over to bookmark `index`, if there is one.
+    fn go_to_bookmark(&mut self, index: usize) {
+        let Some(bookmark) = self.bookmarks.get(index) else {
+            return;
+        };
+
+        self.start_transition(bookmark.camera.clone());
+    }
+
+    /// Ease into the other projection, keeping the focus point the same size.
+    fn switch_projection(&mut self, orthographic: bool) {
+        let view = self.camera_desc();
+        self.start_transition(view.with_projection(orthographic));
+    }
+
+    /// Ease over to an orthographic view of the focus point from `yaw` and `pitch`.
+    fn view_preset(&mut self, yaw: f32, pitch: f32) {
+        let view = self.camera_desc();
+        self.start_transition(view.looking_from(yaw, pitch).with_projection(true));
+    }
+
+    fn start_transition(&mut self, to: CameraDesc) {
+        let from = self.camera_desc();
+        self.camera_transition = Some(CameraTransition::new(from, to, TRANSITION_TIME));
+        self.turntable.playing = false;
+    }
+
+    /// Keep the current view under number key `slot`, replacing what was there.
+    fn save_to_slot(&mut self, slot: usize) {
+        match self.bookmark_in_slot(slot) {
+            Some(index) => self.save_bookmark(index),
+            None => self.add_bookmark(Some(slot)),
+        }
+    }
+
+    /// Keep the current view as bookmark `index`.
+    fn save_bookmark(&mut self, index: usize) {
+        let camera = self.camera_desc();
+        let bookmark = &mut self.bookmarks[index];
+        bookmark.camera = camera;
+        info!("Saved bookmark '{}'", bookmark.name);
+        self.write_bookmarks();
+    }
+
+    /// Keep the current view as a new bookmark, under number key `slot` if given.
+    fn add_bookmark(&mut self, slot: Option<usize>) {
+        let name = format!("Bookmark {}", slot.unwrap_or(self.bookmarks.len() + 1));
+        info!("Saved bookmark '{}'", name);
+        self.bookmarks.push(Bookmark {
+            name,
+            slot,
+            camera: self.camera_desc(),
+        });
+        self.write_bookmarks();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_bookmarks(&self) {
+        if let Err(e) = save_bookmarks(BOOKMARKS_FILE, &self.bookmarks) {
+            error!("Failed to save bookmarks: {}", e);
+        }
+    }
+
+    /// Bookmarks only last as long as the page on the web.
+    #[cfg(target_arch = "wasm32")]
+    fn write_bookmarks(&self) {}
+
+    /// Put the camera where `view` says, switching projections if it asks to.
+    fn show_camera(&mut self, view: &CameraDesc) {
+        view.apply(&mut self.camera);
+        if self.enable_2d != view.orthographic {
+            self.enable_2d = view.orthographic;
+            self.update_mouse_capture_state();
+        }
+        self.set_projection_matrix();
+    }
+
+    /// Left drag orbits, right or middle drag (or shift and left drag) pans,
+    /// and scrolling dollies.
+    fn handle_orbit(&mut self, input: &WinitInputHelper) {
+        let (_, scroll) = input.scroll_diff();
+        if scroll != 0.0 && self.enable_2d {
+            self.zoom_ortho(input, scroll);
+        } else if scroll != 0.0 {
+            self.camera.dolly(scroll);
+        }
+
+        let (dx, dy) = input.cursor_diff();
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+
+        let left = input.mouse_held(MouseButton::Left);
+        let pan = input.mouse_held(MouseButton::Right)
+            || input.mouse_held(MouseButton::Middle)
+            || (left && input.held_shift());
+        if pan {
+            let (_, height) = self.surface.size();
+            let view_height = if self.enable_2d {
+                self.camera.frustum.ortho_height
+            } else {
+                self.camera.focus_height()
+            };
+            self.camera.pan(dx, dy, view_height / height.max(1) as f32);
+        } else if left {
+            self.camera.orbit(dx, dy);
+        }
+    }
+
+    /// Select what's under the cursor when the left button is clicked without
+    /// dragging, so orbiting doesn't change the selection.
+    fn handle_click(&mut self, input: &WinitInputHelper) {
+        let Some((x, y)) = input.cursor() else {
+            return;
+        };
+        let cursor = vec2(x, y);
+
+        if input.mouse_pressed(MouseButton::Left) {
+            self.click_start = Some(cursor);
+        }
+        if input.mouse_released(MouseButton::Left)
+            && self
+                .click_start
+                .take()
+                .is_some_and(|start| start.distance(cursor) <= CLICK_DISTANCE)
+        {
+            self.pick(cursor);
+        }
+    }
+
+    /// Select the object under `cursor`, or nothing when there's only
+    /// background there. `cursor` is in pixels from the top left.
+    pub fn pick(&mut self, cursor: Vec2) -> Option<Hit> {
+        let ray = self.cursor_ray(cursor);
+        let roots = self.objects.iter().map(|o| o.node);
+        let hit = pick(&self.render_manager.graph, roots, &ray);
+
+        self.selected_node = hit.map(|h| h.node);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.reveal_selection = hit.is_some();
+        }
+        if let Some(hit) = &hit {
+            let name = self
+                .render_manager
+                .graph
+                .node(hit.node)
+                .map_or("", |n| n.name.as_str());
+            info!("Picked '{}' at {}", name, hit.point);
+        }
+        hit
+    }
+
+    /// The ray through `cursor` in world space, from the camera or, in an
+    /// orthographic view, from the near plane straight ahead.
+    fn cursor_ray(&mut self, cursor: Vec2) -> Ray {
+        let through = self.normalize_cursor(cursor);
+        let camera = self.view_matrix.inverse();
+        let position = camera.transform_point3(Vec3::ZERO);
+
+        if self.enable_2d {
+            // The direction there is the view's plus the cursor's offset across it
+            let forward = camera.transform_vector3(Vec3::NEG_Z);
+            Ray::new(position + through - forward, forward)
+        } else {
+            Ray::new(position, through)
+        }
+    }
+
+    fn normalize_cursor(&mut self, cursor: Vec2) -> Vec3 {
+        let (width, height) = self.surface.size();
+        // https://antongerdelan.net/opengl/raycasting.html
+        let ndc = vec2(
+            (2.0 * cursor.x) / width as f32 - 1.0,
+            1.0 - (2.0 * cursor.y) / height as f32,
+        );
+        let clip = vec4(ndc.x, ndc.y, -1.0, 1.0);
+        let mut eye = self.projection_matrix.inverse() * clip;
+        eye.z = -1.0;
+        eye.w = 0.0;
+        (self.view_matrix.inverse() * eye).truncate()
+    }
+
+    /// Zoom the orthographic view towards the cursor, or the middle without one.
+    fn zoom_ortho(&mut self, input: &WinitInputHelper, amount: f32) {
+        let (width, height) = self.surface.size();
+        let cursor = input.cursor().map_or(Vec2::ZERO, |(x, y)| {
+            vec2(2.0 * x / width as f32 - 1.0, 1.0 - 2.0 * y / height as f32)
+        });
+        self.camera
+            .zoom_ortho(amount, cursor, self.surface.aspect_ratio());
+        self.set_projection_matrix();
+    }
+
+    // Written so a NaN mouse movement counts as none
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
     fn handle_mouse(&mut self, input: &WinitInputHelper) {
         let scroll_diff = {
             let d = input.scroll_diff();
@@ -235,40 +1127,44 @@ impl ViewPort {
             vec2(d.0, d.1)
         };
 
-        if scroll_diff.y != 0.0 {
+        if scroll_diff.y != 0.0 && self.enable_2d {
+            self.zoom_ortho(input, scroll_diff.y);
+        } else if scroll_diff.y != 0.0 {
             self.camera.frustum.zoom(-scroll_diff.y);
             self.set_projection_matrix();
         }
 
         if self.enable_2d {
             // Get the initial mouse position on first press
-            if input.mouse_pressed(MouseButton::Left) {
-                if let Some(cursor) = input.cursor() {
-                    self.last_mouse_pos = vec2(cursor.0, cursor.1);
-                }
+            if input.mouse_pressed(MouseButton::Left)
+                && let Some(cursor) = input.cursor()
+            {
+                self.last_mouse_pos = vec2(cursor.0, cursor.1);
             }
 
             // Handle moving mouse (diff from origin)
-            if input.mouse_held(MouseButton::Left) {
-                if let Some(cursor) = input.cursor() {
-                    let current = vec2(cursor.0, cursor.1);
-                    let diff = self.last_mouse_pos - current;
+            if input.mouse_held(MouseButton::Left)
+                && let Some(cursor) = input.cursor()
+            {
+                let current = vec2(cursor.0, cursor.1);
+                let diff = self.last_mouse_pos - current;
 
-                    if !(diff.length() > 0.0) {
-                        return;
-                    }
-
-                    let last_world_pos = self.normalize_cursor(self.last_mouse_pos).truncate();
-                    let diff_world_pos =
-                        self.normalize_cursor(self.last_mouse_pos + diff).truncate();
+                if !(diff.length() > 0.0) {
+                    return;
+                }
 
-                    let diff = last_world_pos - diff_world_pos;
+                let last_world_pos = self.normalize_cursor(self.last_mouse_pos).truncate();
+                let diff_world_pos = self.normalize_cursor(self.last_mouse_pos + diff).truncate();
 
-                    self.sun.borrow_mut().transform_mut().position.x += diff.x;
-                    self.sun.borrow_mut().transform_mut().position.y += diff.y;
+                let diff = last_world_pos - diff_world_pos;
 
-                    self.last_mouse_pos = current;
+                if let Some(key) = self.lights.first() {
+                    let mut light = key.light.borrow_mut();
+                    light.transform_mut().position.x += diff.x;
+                    light.transform_mut().position.y += diff.y;
                 }
+
+                self.last_mouse_pos = current;
             }
         } else {
             if !(mouse_diff.length() > 0.0) {
@@ -276,13 +1172,60 @@ impl ViewPort {
             }
 
             self.camera.turn(mouse_diff.x, -mouse_diff.y);
-            let size = self.window.inner_size();
-            _ = self
-                .window
-                .set_cursor_position(PhysicalPosition::new(size.width / 2, size.height / 2));
+            let (width, height) = self.surface.size();
+            self.surface.set_cursor_position(width / 2, height / 2);
+        }
+    }
+
+    /// Rebuild the shaders whose files the watcher saw change.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_changed_shaders(&mut self) {
+        let Some(watcher) = &mut self.shader_watcher else {
+            return;
+        };
+
+        let changed = watcher.changed();
+        if !changed.is_empty() {
+            self.reload_shaders(|shader| changed.iter().any(|path| shader.uses(path)));
         }
     }
 
+    /// Rebuild the shaders `pick` picks. The ones that fail keep their last good
+    /// program, and their errors are kept for the overlay.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_shaders(&mut self, pick: impl Fn(&Shader) -> bool) {
+        let mut reloaded = Vec::new();
+        let mut errors: Vec<ReloadError> = Vec::new();
+
+        self.render_manager.for_each_shader(|shader| {
+            if !pick(shader) {
+                return;
+            }
+            match shader.reload() {
+                Ok(()) => {
+                    info!("Reloaded shader {}", shader.label());
+                    reloaded.push(shader.label());
+                }
+                // Every model has its own copy of the same shader, one error is enough
+                Err(e) if errors.iter().any(|other| other.shader == e.shader) => {}
+                Err(e) => {
+                    error!("Failed to reload shader: {}", e);
+                    errors.push(e);
+                }
+            }
+        });
+
+        self.shader_errors.retain(|old| {
+            !reloaded.contains(&old.shader) && !errors.iter().any(|e| e.shader == old.shader)
+        });
+        self.shader_errors.extend(errors);
+    }
+
+    /// Whether the user asked to quit, for the event loop to act on.
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
         unsafe {
             self.gl.viewport(0, 0, width as i32, height as i32);
@@ -292,15 +1235,33 @@ impl ViewPort {
     }
 
     pub fn update(&mut self, dt: f32) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_changed_shaders();
+
+        if let Some(transition) = &mut self.camera_transition {
+            let view = transition.update(dt);
+            if transition.is_finished() {
+                self.camera_transition = None;
+            }
+            self.show_camera(&view);
+        } else if !self.capturing() {
+            self.turntable.update(&mut self.camera, dt);
+        }
+        self.clip_to_scene();
+
         // Update physics before rendering
         self.physics_manager.update(dt);
         self.render_manager.update(dt);
     }
 
     pub fn render(&mut self, clear_color: [f32; 4]) {
+        let depth = self.camera.frustum.depth;
+        #[cfg(not(target_arch = "wasm32"))]
+        self.bind_render_target(depth);
+
         unsafe {
-            self.gl
-                .clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+            self.gl.clear(glow::COLOR_BUFFER_BIT);
+            depth.clear(&self.gl);
             self.gl.clear_color(
                 clear_color[0],
                 clear_color[1],
@@ -309,14 +1270,115 @@ impl ViewPort {
             );
         }
 
+        // Captured frames show the model as it is
+        self.render_manager.highlight.node = self.selected_node.filter(|_| !self.capturing());
+
         self.view_matrix = self.camera.get_camera_view_matrix();
         let pv = self.projection_matrix * self.view_matrix;
-        self.render_manager.draw(&pv, &self.camera, &self.sun);
+        self.render_manager.draw(&pv, &self.camera);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(target) = &self.render_target {
+            target.present();
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.capture_frame();
+    }
+
+    /// Draw into the float depth target for reverse-Z, made to the window's
+    /// size as needed, or straight into the window otherwise.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn bind_render_target(&mut self, depth: DepthMode) {
+        if depth == DepthMode::Standard {
+            self.render_target = None;
+            return;
+        }
+
+        let (width, height) = self.surface.size();
+        let stale = self
+            .render_target
+            .as_ref()
+            .is_none_or(|t| (t.width, t.height) != (width, height));
+        if stale {
+            // Drop the old one first, the two needn't be alive at once
+            self.render_target = None;
+            match RenderTarget::new(self.gl.clone(), width, height) {
+                Ok(target) => self.render_target = Some(target),
+                Err(e) => {
+                    error!("Failed to create the reverse-Z target: {}", e);
+                    self.set_depth_mode(DepthMode::Standard);
+                    return;
+                }
+            }
+        }
+        if let Some(target) = &self.render_target {
+            target.bind();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn capturing(&self) -> bool {
+        self.capture_dialog.frame.is_some()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn capturing(&self) -> bool {
+        false
+    }
+
+    /// Save the frame just drawn, before the GUI goes over it, and turn the
+    /// turntable one step for the next.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn capture_frame(&mut self) {
+        let Some(frame) = self.capture_dialog.frame else {
+            return;
+        };
+        let frames = self.capture_dialog.frames.max(1) as u32;
+
+        let (width, height) = self.surface.size();
+        let image = read_pixels(&self.gl, None, width, height);
+        let path =
+            Path::new(&self.capture_dialog.directory).join(format!("frame_{:04}.png", frame));
+        if let Err(e) = image.save(&path) {
+            let message = format!("Failed to write '{}': {}", path.display(), e);
+            error!("{}", message);
+            self.capture_dialog.error = Some(message);
+            self.capture_dialog.frame = None;
+            return;
+        }
+
+        self.turntable.step(&mut self.camera, frames);
+        self.capture_dialog.frame = Some(frame + 1).filter(|next| *next < frames);
+        if !self.capturing() {
+            info!(
+                "Captured {} frames to '{}'",
+                frames, self.capture_dialog.directory
+            );
+        }
+    }
+
+    /// Start saving a full turn of the turntable, one frame per redraw.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_capture(&mut self) {
+        let directory = Path::new(&self.capture_dialog.directory);
+        if let Err(e) = std::fs::create_dir_all(directory) {
+            self.capture_dialog.error =
+                Some(format!("Failed to create '{}': {}", directory.display(), e));
+            return;
+        }
+
+        self.capture_dialog.error = None;
+        self.capture_dialog.frame = Some(0);
+        self.camera_transition = None;
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn gui(&mut self, ui: &mut Ui) {
-        ui.window("Viewport").build(|| {
+        let window = ui.window("Viewport").flags(WindowFlags::MENU_BAR);
+        window.build(|| {
+            self.file_menu(ui);
+
             if ui.collapsing_header("Camera", TreeNodeFlags::COLLAPSING_HEADER) {
                 ui.text(format!(
                     "Position: ({}, {}, {})",
@@ -333,38 +1395,1037 @@ impl ViewPort {
 
                 ui.separator();
 
+                let mut mode = CameraMode::ALL
+                    .iter()
+                    .position(|m| *m == self.camera.mode)
+                    .unwrap_or(0);
+                if ui.combo("Mode", &mut mode, &CameraMode::ALL, |m| m.name().into()) {
+                    self.set_camera_mode(CameraMode::ALL[mode]);
+                }
+                if self.camera.mode == CameraMode::Orbit {
+                    let mut target = self.camera.target.to_array();
+                    if ui.input_float3("Target", &mut target).build() {
+                        self.camera.target = target.into();
+                        self.camera.look_at(self.camera.target);
+                    }
+                    ui.text(format!("Distance: {}", self.camera.orbit_distance()));
+                }
+                let mut orthographic = self.enable_2d as usize;
+                if ui.combo(
+                    "Projection",
+                    &mut orthographic,
+                    &["Perspective", "Orthographic"],
+                    |p| (*p).into(),
+                ) {
+                    self.switch_projection(orthographic == 1);
+                }
+                self.depth_controls(ui);
+                if self.enable_2d
+                    && ui
+                        .drag_float_config("View Height")
+                        .speed(0.01)
+                        .range(1e-4, 1e5)
+                        .build(ui, &mut self.camera.frustum.ortho_height)
+                {
+                    self.set_projection_matrix();
+                }
+                for (index, (name, yaw, pitch)) in VIEW_PRESETS.into_iter().enumerate() {
+                    if index > 0 {
+                        ui.same_line();
+                    }
+                    if ui.small_button(name) {
+                        self.view_preset(yaw, pitch);
+                    }
+                }
+
+                if ui.small_button("Frame Selection") {
+                    self.frame_selection();
+                }
+                ui.same_line();
+                if ui.small_button("Frame All") {
+                    self.frame_all();
+                }
+
+                ui.separator();
+
+                self.turntable_panel(ui);
+
+                ui.separator();
+
                 if ui.small_button("Reset##Camera") {
-                    self.camera.transform.position = Vec3::new(0.0, 0.0, 5.0);
-                    self.camera.pitch = 0.0;
-                    self.camera.yaw = 0.0;
-                    self.camera.frustum.fov = 45.0;
+                    self.scene_camera.apply(&mut self.camera);
                     self.set_projection_matrix();
                 }
             }
 
-            if ui.collapsing_header("Sun", TreeNodeFlags::COLLAPSING_HEADER) {
-                let mut sun = self.sun.borrow_mut();
-                let transform = sun.transform_mut();
+            if ui.collapsing_header("Bookmarks", TreeNodeFlags::COLLAPSING_HEADER) {
+                self.bookmarks_panel(ui);
+            }
+
+            if ui.collapsing_header("Scene", TreeNodeFlags::COLLAPSING_HEADER) {
+                self.scene_panel(ui);
+            }
+
+            if ui.collapsing_header("Lights", TreeNodeFlags::COLLAPSING_HEADER) {
+                self.lights_panel(ui);
+            }
+
+            if ui.collapsing_header("Shadows", TreeNodeFlags::COLLAPSING_HEADER) {
+                self.shadows_panel(ui);
+            }
+
+            if ui.collapsing_header("Environment", TreeNodeFlags::COLLAPSING_HEADER) {
+                self.environment_panel(ui);
+            }
+
+            if ui.collapsing_header("Shaders", TreeNodeFlags::COLLAPSING_HEADER) {
+                self.shaders_panel(ui);
+            }
+        });
+
+        self.shader_errors_overlay(ui);
+        self.gui_has_mouse = ui.io().want_capture_mouse();
+        self.gui_has_keyboard = ui.io().want_capture_keyboard();
+    }
+
+    /// Reverse-Z and how the clip planes are placed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn depth_controls(&mut self, ui: &Ui) {
+        let mut reversed = self.camera.frustum.depth == DepthMode::Reversed;
+        let disabled = ui.begin_disabled_with_cond(!self.reverse_z_available());
+        if ui.checkbox("Reverse-Z", &mut reversed) {
+            self.set_depth_mode(if reversed {
+                DepthMode::Reversed
+            } else {
+                DepthMode::Standard
+            });
+        }
+        disabled.end();
+        if !self.reverse_z_available() {
+            ui.same_line();
+            ui.text_disabled("(needs glClipControl)");
+        }
 
-                let mut position = transform.position.to_array();
+        if !self.enable_2d
+            && ui.checkbox("Infinite Far Plane", &mut self.camera.frustum.infinite_far)
+        {
+            self.set_projection_matrix();
+        }
+        if ui.checkbox("Auto Near/Far", &mut self.auto_clip) && !self.auto_clip {
+            // Back to what the scene asked for
+            self.camera.frustum.near = self.scene_camera.near;
+            self.camera.frustum.far = self.scene_camera.far;
+            self.set_projection_matrix();
+        }
+        ui.text(format!(
+            "Near: {:.4}  Far: {:.4}",
+            self.camera.frustum.near, self.camera.frustum.far
+        ));
+    }
 
-                if ui.input_float3("Position", &mut position).build() {
-                    transform.position = position.into();
+    /// What went wrong with the shaders that failed to reload, with the lines
+    /// the compiler complained about.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn shader_errors_overlay(&mut self, ui: &Ui) {
+        if self.shader_errors.is_empty() {
+            return;
+        }
+
+        let red = [1.0, 0.3, 0.3, 1.0];
+        let mut dismiss = false;
+        ui.window("Shader Errors")
+            .size([560.0, 240.0], Condition::FirstUseEver)
+            .build(|| {
+                ui.text_disabled("The last working version of each shader is still drawn with");
+
+                for error in &self.shader_errors {
+                    ui.separator();
+                    ui.text(&error.shader);
+
+                    if error.lines.is_empty() {
+                        ui.text_colored(red, error.to_string());
+                    }
+                    let path = error.path.as_deref().unwrap_or_default();
+                    for line in &error.lines {
+                        ui.text_colored(red, format!("{}:{}: {}", path, line.line, line.message));
+                        if !line.source.is_empty() {
+                            ui.text_disabled(format!("    {}", line.source));
+                        }
+                    }
                 }
 
                 ui.separator();
+                dismiss = ui.button("Dismiss");
+            });
+
+        if dismiss {
+            self.shader_errors.clear();
+        }
+    }
 
-                ui.slider_f32("Ambient", &mut sun.ambient_mut(), 0.0, 1.0);
-                ui.slider_f32("Specular", &mut sun.specular_mut(), 0.0, 1.0);
+    /// Turntable controls, and capturing a spin of it as an image sequence.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn turntable_panel(&mut self, ui: &Ui) {
+        ui.checkbox("Turntable", &mut self.turntable.playing);
+        ui.slider_f32("Speed", &mut self.turntable.speed, -180.0, 180.0);
 
+        let dialog = &mut self.capture_dialog;
+        ui.input_text("Directory##Capture", &mut dialog.directory)
+            .build();
+        if ui.input_int("Frames", &mut dialog.frames) {
+            dialog.frames = dialog.frames.clamp(1, 3600);
+        }
+        match dialog.frame {
+            Some(frame) => {
+                ui.text(format!("Capturing frame {}/{}", frame + 1, dialog.frames));
+                if ui.small_button("Stop##Capture") {
+                    dialog.frame = None;
+                }
+            }
+            None => {
+                if ui.small_button("Capture Sequence") {
+                    self.start_capture();
+                }
+            }
+        }
+        if let Some(error) = &self.capture_dialog.error {
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+        }
+    }
+
+    /// The saved bookmarks, with buttons to go to, update and remove them.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn bookmarks_panel(&mut self, ui: &Ui) {
+        let mut go_to = None;
+        let mut update = None;
+        let mut remove = None;
+        let mut changed = false;
+
+        for (index, bookmark) in self.bookmarks.iter_mut().enumerate() {
+            let _id = ui.push_id(index as i32);
+            if ui.small_button("Go") {
+                go_to = Some(index);
+            }
+            ui.same_line();
+            if ui.small_button("Update") {
+                update = Some(index);
+            }
+            ui.same_line();
+            if ui.small_button("Delete") {
+                remove = Some(index);
+            }
+            ui.same_line();
+            match bookmark.slot {
+                Some(slot) => ui.text(slot.to_string()),
+                None => ui.text_disabled("-"),
+            }
+            ui.same_line();
+            // Saved once the name is done being typed
+            ui.input_text("##Name", &mut bookmark.name).build();
+            changed |= ui.is_item_deactivated_after_edit();
+        }
+
+        if ui.small_button("Add Bookmark") {
+            // Under the first free number key, if there is one
+            let slot =
+                (1..=BOOKMARK_KEYS.len()).find(|&slot| self.bookmark_in_slot(slot).is_none());
+            self.add_bookmark(slot);
+        }
+        ui.same_line();
+        ui.text_disabled("1-9 to go, Ctrl+1-9 to save");
+
+        if let Some(index) = go_to {
+            self.go_to_bookmark(index);
+        }
+        if let Some(index) = update {
+            self.save_bookmark(index);
+        }
+        if let Some(index) = remove {
+            self.bookmarks.remove(index);
+            changed = true;
+        }
+        if changed {
+            self.write_bookmarks();
+        }
+    }
+
+    /// An editor for every light, and buttons to add and remove them.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn lights_panel(&mut self, ui: &Ui) {
+        let mut remove = None;
+
+        for (index, light) in self.lights.iter().enumerate() {
+            let _id = ui.push_id(index as i32);
+            let Some(_node) = ui
+                .tree_node_config(&light.desc.name)
+                .default_open(true)
+                .push()
+            else {
+                continue;
+            };
+
+            let mut lamp = light.light.borrow_mut();
+
+            let mut position = lamp.transform().position.to_array();
+            if ui.input_float3("Position", &mut position).build() {
+                lamp.transform_mut().position = position.into();
+            }
+
+            let source = lamp.source_mut();
+
+            let mut kind = LightKind::ALL
+                .iter()
+                .position(|k| *k == source.kind)
+                .unwrap_or(0);
+            if ui.combo("Kind", &mut kind, &LightKind::ALL, |k| k.name().into()) {
+                source.kind = LightKind::ALL[kind];
+            }
+
+            if source.kind != LightKind::Point {
+                let mut direction = source.direction.to_array();
+                if ui.drag_float3("Direction", &mut direction) {
+                    source.direction = direction.into();
+                }
+            }
+
+            ui.separator();
+
+            let mut color = source.color.to_array();
+            if ui.color_edit3("Color", &mut color) {
+                source.color = color.into();
+            }
+            ui.slider_f32("Intensity", &mut source.intensity, 0.0, 10.0);
+            ui.slider_f32("Ambient", &mut source.ambient, 0.0, 1.0);
+            ui.slider_f32("Specular", &mut source.specular, 0.0, 1.0);
+
+            if source.kind != LightKind::Directional {
+                let mut attenuation = source.attenuation.to_array();
+                if ui.input_float3("Attenuation", &mut attenuation).build() {
+                    source.attenuation = Vec3::from(attenuation).max(Vec3::ZERO);
+                }
+            }
+
+            if source.kind == LightKind::Spot {
+                ui.slider_f32("Inner Cone", &mut source.inner_cone, 0.0, 90.0);
+                ui.slider_f32(
+                    "Outer Cone",
+                    &mut source.outer_cone,
+                    source.inner_cone,
+                    90.0,
+                );
+            }
+
+            if source.kind != LightKind::Point {
+                ui.checkbox("Cast Shadows", &mut source.cast_shadows);
+                if source.cast_shadows {
+                    ui.slider_f32("Shadow Bias", &mut source.shadow_bias, 0.0, 0.02);
+
+                    let mut resolution = SHADOW_RESOLUTIONS
+                        .iter()
+                        .position(|r| *r >= source.shadow_resolution)
+                        .unwrap_or(SHADOW_RESOLUTIONS.len() - 1);
+                    if ui.combo("Shadow Map", &mut resolution, &SHADOW_RESOLUTIONS, |r| {
+                        format!("{0}x{0}", r).into()
+                    }) {
+                        source.shadow_resolution = SHADOW_RESOLUTIONS[resolution];
+                    }
+                }
+            }
+
+            ui.separator();
+
+            if ui.small_button("Reset") {
+                *source = light.desc.source();
+                lamp.transform_mut().position = light.desc.position;
+            }
+            ui.same_line();
+            if ui.small_button("Remove") {
+                remove = Some(index);
+            }
+        }
+
+        if let Some(index) = remove {
+            let light = self.lights.remove(index);
+            self.remove_light(&light);
+        }
+
+        ui.separator();
+
+        if ui.button("Add Light") {
+            let desc = LightDesc {
+                name: format!("light {}", self.lights.len()),
+                position: self.camera.transform.position,
+                ..Default::default()
+            };
+            match SceneLight::new(&self.gl, desc) {
+                Ok(light) => self.add_light(light),
+                Err(e) => error!("Failed to add a light: {}", e),
+            }
+        }
+    }
+
+    /// The global shadow switch and the shadow map debug view.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn shadows_panel(&mut self, ui: &Ui) {
+        let shadows = &mut self.render_manager.shadows;
+        ui.checkbox("Enabled##Shadows", &mut shadows.enabled);
+
+        // Named after the light each map belongs to, the light manager has them in the same order
+        let mut names = vec!["None".to_string()];
+        names.extend(shadows.casters().map(|index| {
+            self.lights
+                .get(index)
+                .map_or_else(|| format!("light {}", index), |l| l.desc.name.clone())
+        }));
+
+        let mut shown = shadows
+            .debug_view
+            .map_or(0, |slot| slot + 1)
+            .min(names.len() - 1);
+        ui.combo("Show Map", &mut shown, &names, |name| name.as_str().into());
+        shadows.debug_view = shown.checked_sub(1);
+
+        if names.len() == 1 {
+            ui.text_disabled("No directional or spot light casts shadows");
+        }
+    }
+
+    /// Picking the HDR environment and the background, and how they're shown.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn environment_panel(&mut self, ui: &Ui) {
+        let current = match &self.environment_dialog.loaded {
+            Some(path) if self.render_manager.environment.environment().is_some() => {
+                path.display().to_string()
+            }
+            _ => "None".to_string(),
+        };
+        ui.text(format!("Current: {}", current));
+
+        let submitted = ui
+            .input_text("Path##Environment", &mut self.environment_dialog.path)
+            .enter_returns_true(true)
+            .build();
+
+        if (ui.button("Load##Environment") || submitted)
+            && !self.environment_dialog.path.trim().is_empty()
+        {
+            let path = PathBuf::from(self.environment_dialog.path.trim());
+            if let Err(e) = self.load_environment(&path) {
+                error!("Failed to load environment '{}': {}", path.display(), e);
+            }
+        }
+        ui.same_line();
+        if ui.button("Clear##Environment") {
+            self.render_manager.environment.set_environment(None);
+            self.environment_dialog.loaded = None;
+        }
+
+        ui.separator();
+
+        // A plain image behind the scene, the environment still does the lighting
+        let background = match &self.environment_dialog.background_loaded {
+            Some(path) if self.render_manager.environment.background.is_some() => {
+                path.display().to_string()
+            }
+            _ => "None".to_string(),
+        };
+        ui.text(format!("Background: {}", background));
+
+        let submitted = ui
+            .input_text(
+                "Path##Background",
+                &mut self.environment_dialog.background_path,
+            )
+            .enter_returns_true(true)
+            .build();
+
+        if (ui.button("Load##Background") || submitted)
+            && !self.environment_dialog.background_path.trim().is_empty()
+        {
+            let path = PathBuf::from(self.environment_dialog.background_path.trim());
+            if let Err(e) = self.load_background(&path) {
+                error!("Failed to load background '{}': {}", path.display(), e);
+            }
+        }
+        ui.same_line();
+        if ui.button("Clear##Background") {
+            self.render_manager.environment.background = None;
+            self.environment_dialog.background_loaded = None;
+        }
+
+        if let Some(error) = &self.environment_dialog.error {
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+        }
+
+        ui.separator();
+
+        let environment = &mut self.render_manager.environment;
+        ui.checkbox("Skybox", &mut environment.show_skybox);
+        ui.checkbox("Lighting", &mut environment.lighting);
+        ui.slider_f32("Exposure", &mut environment.exposure, -8.0, 8.0);
+        ui.slider_f32("Rotation", &mut environment.rotation, 0.0, 360.0);
+    }
+
+    /// The scene graph as a tree, and the local transform and materials of the
+    /// selected node.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn scene_panel(&mut self, ui: &Ui) {
+        let graph = &mut self.render_manager.graph;
+
+        let reveal = self.selected_node.filter(|_| self.reveal_selection);
+        self.reveal_selection = false;
+        for root in graph.roots() {
+            node_tree(ui, graph, *root, &mut self.selected_node, reveal);
+        }
+
+        let mut color = self.render_manager.highlight.color.to_array();
+        if ui.color_edit4("Highlight", &mut color) {
+            self.render_manager.highlight.color = color.into();
+        }
+
+        let Some(id) = self.selected_node.filter(|id| graph.contains(*id)) else {
+            return;
+        };
+
+        ui.separator();
+
+        let transform = graph.transform(id);
+        let mut position = transform.position.to_array();
+        let (x, y, z) = transform.rotation.to_euler(EulerRot::XYZ);
+        let mut rotation = [x.to_degrees(), y.to_degrees(), z.to_degrees()];
+        let mut scale = transform.scale.to_array();
+
+        let mut moved = false;
+        if ui.drag_float3("Position##Node", &mut position) {
+            graph.transform_mut(id).position = position.into();
+            moved = true;
+        }
+        if ui.drag_float3("Rotation##Node", &mut rotation) {
+            let [x, y, z] = rotation.map(f32::to_radians);
+            graph.transform_mut(id).rotation = Quat::from_euler(EulerRot::XYZ, x, y, z);
+            moved = true;
+        }
+        if ui.drag_float3("Scale##Node", &mut scale) {
+            graph.transform_mut(id).scale = Vec3::from(scale).max(Vec3::splat(1e-3));
+            moved = true;
+        }
+        if moved {
+            mark_edited(&mut self.objects, graph, id, false);
+        }
+
+        let parts: Vec<(NodeId, String, RenderableRef)> = graph
+            .subtree(id)
+            .filter_map(|(id, node)| Some((id, node.name.clone(), node.renderable.clone()?)))
+            .collect();
+        for (index, (part, name, renderable)) in parts.iter().enumerate() {
+            let _id = ui.push_id(index as i32);
+            let mut renderable = renderable.borrow_mut();
+            let material = renderable.material_mut();
+
+            ui.separator();
+            let edited = if parts.len() == 1 {
+                ui.text(format!("Material: {}", material.name));
+                material_editor(ui, material)
+            } else if let Some(_node) = ui
+                .tree_node_config(format!("{} ({})", name, material.name))
+                .push()
+            {
+                material_editor(ui, material)
+            } else {
+                false
+            };
+            if edited {
+                mark_edited(&mut self.objects, graph, *part, true);
+            }
+        }
+    }
+
+    /// The uniforms of the shaders the node selected in the "Scene" panel is
+    /// drawn with, each with an editor. Edited uniforms keep their value until reset.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn shaders_panel(&mut self, ui: &Ui) {
+        let graph = &self.render_manager.graph;
+        let Some(id) = self.selected_node.filter(|id| graph.contains(*id)) else {
+            ui.text_disabled("Select an object in the \"Scene\" panel");
+            return;
+        };
+
+        // Parts can share a shader, each is listed once under the first part using it
+        let mut shaders: Vec<(String, ShaderRef)> = Vec::new();
+        for (_, node) in graph.subtree(id) {
+            let Some(renderable) = &node.renderable else {
+                continue;
+            };
+            let shader = renderable.borrow().material().shader.clone();
+            if !shaders.iter().any(|(_, s)| Rc::ptr_eq(s, &shader)) {
+                shaders.push((node.name.clone(), shader));
+            }
+        }
+
+        if shaders.is_empty() {
+            ui.text_disabled("Nothing is drawn for this node");
+        }
+
+        for (index, (name, shader)) in shaders.iter().enumerate() {
+            let _id = ui.push_id(index as i32);
+            let Some(_node) = ui
+                .tree_node_config(format!("{} ({})", name, shader.label()))
+                .push()
+            else {
+                continue;
+            };
+
+            for uniform in shader.uniforms().iter() {
+                for element in uniform.element_names() {
+                    uniform_editor(ui, shader, &element, uniform.kind);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn file_menu(&mut self, ui: &Ui) {
+        let mut open = false;
+        let mut export = false;
+        let mut save_scene = false;
+        let mut selected: Option<PathBuf> = None;
+
+        if let Some(_menu_bar) = ui.begin_menu_bar()
+            && let Some(_menu) = ui.begin_menu("File")
+        {
+            open = ui.menu_item("Open...");
+
+            if let Some(_recent) =
+                ui.begin_menu_with_enabled("Recent", !self.open_dialog.recent.is_empty())
+            {
+                for path in &self.open_dialog.recent {
+                    if ui.menu_item(path.display().to_string()) {
+                        selected = Some(path.clone());
+                    }
+                }
+            }
+
+            ui.separator();
+            save_scene = ui.menu_item("Save Scene...");
+            export = ui.menu_item("Export...");
+        }
+
+        if open {
+            self.open_dialog.error = None;
+            ui.open_popup("Open");
+        }
+
+        if let Some(_popup) = ui.begin_modal_popup("Open") {
+            ui.text(format!("Models: {}", MODEL_EXTENSIONS.join(", ")));
+            ui.text(format!("Scenes: {}", SCENE_EXTENSIONS.join(", ")));
+
+            let submitted = ui
+                .input_text("Path", &mut self.open_dialog.path)
+                .enter_returns_true(true)
+                .build();
+
+            if (ui.button("Open") || submitted) && !self.open_dialog.path.trim().is_empty() {
+                selected = Some(PathBuf::from(self.open_dialog.path.trim()));
+            }
+            ui.same_line();
+            if ui.button("Cancel") {
+                ui.close_current_popup();
+            }
+
+            if !self.open_dialog.recent.is_empty() {
                 ui.separator();
+                ui.text("Recent");
+                for path in &self.open_dialog.recent {
+                    if ui.selectable(path.display().to_string()) {
+                        selected = Some(path.clone());
+                    }
+                }
+            }
 
-                if ui.small_button("Reset##Sun") {
-                    sun.transform_mut().position = Vec3::new(1.0, 1.0, 1.0);
-                    *sun.ambient_mut() = 0.2;
-                    *sun.specular_mut() = 0.5;
+            if let Some(error) = &self.open_dialog.error {
+                ui.separator();
+                ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+            }
+
+            // Stay open on failure so the path can be fixed
+            if let Some(path) = selected.take() {
+                self.open_dialog.path = path.display().to_string();
+                if self.open(&path).is_ok() {
+                    ui.close_current_popup();
                 }
             }
-        });
+        }
+
+        if let Some(path) = selected
+            && let Err(e) = self.open(&path)
+        {
+            error!("Failed to open '{}': {}", path.display(), e);
+        }
+
+        if save_scene {
+            self.scene_dialog.error = None;
+            ui.open_popup("Save Scene");
+        }
+
+        if let Some(_popup) = ui.begin_modal_popup("Save Scene") {
+            ui.text(format!("Supported: {}", SCENE_EXTENSIONS.join(", ")));
+
+            let submitted = ui
+                .input_text("Path", &mut self.scene_dialog.path)
+                .enter_returns_true(true)
+                .build();
+
+            if (ui.button("Save") || submitted) && !self.scene_dialog.path.trim().is_empty() {
+                let path = PathBuf::from(self.scene_dialog.path.trim());
+                match self.save_scene(&path) {
+                    Ok(_) => ui.close_current_popup(),
+                    Err(e) => self.scene_dialog.error = Some(e),
+                }
+            }
+            ui.same_line();
+            if ui.button("Cancel") {
+                ui.close_current_popup();
+            }
+
+            if let Some(error) = &self.scene_dialog.error {
+                ui.separator();
+                ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+            }
+        }
+
+        if export {
+            self.export_dialog.error = None;
+            ui.open_popup("Export Model");
+        }
+
+        if let Some(_popup) = ui.begin_modal_popup("Export Model") {
+            ui.text(format!("Supported: {}", EXPORT_EXTENSIONS.join(", ")));
+
+            let submitted = ui
+                .input_text("Path", &mut self.export_dialog.path)
+                .enter_returns_true(true)
+                .build();
+            ui.checkbox(
+                "Bake transforms",
+                &mut self.export_dialog.options.bake_transforms,
+            );
+
+            if (ui.button("Export") || submitted) && !self.export_dialog.path.trim().is_empty() {
+                let path = PathBuf::from(self.export_dialog.path.trim());
+                match self.export_model(&path, &self.export_dialog.options) {
+                    Ok(_) => ui.close_current_popup(),
+                    Err(e) => self.export_dialog.error = Some(e),
+                }
+            }
+            ui.same_line();
+            if ui.button("Cancel") {
+                ui.close_current_popup();
+            }
+
+            if let Some(error) = &self.export_dialog.error {
+                ui.separator();
+                ui.text_colored([1.0, 0.3, 0.3, 1.0], error);
+            }
+        }
+    }
+}
+
+/// An editor for the uniform `name` of `shader`, showing what it holds now.
+/// Changes override the value the renderer sets, until "Reset" is pressed.
+#[cfg(not(target_arch = "wasm32"))]
+fn uniform_editor(ui: &Ui, shader: &Shader, name: &str, kind: u32) {
+    let _id = ui.push_id(name);
+    let label = format!("{} ({})", name, type_name(kind));
+
+    let Some(value) = shader.uniform_value(name) else {
+        ui.text_disabled(&label);
+        return;
+    };
+
+    let mut edited = None;
+    match value {
+        UniformValue::Int(mut v) if kind == glow::BOOL => {
+            let mut on = v != 0;
+            if ui.checkbox(&label, &mut on) {
+                v = on as i32;
+                edited = Some(UniformValue::Int(v));
+            }
+        }
+        // A sampler's value is its texture unit, not worth editing
+        UniformValue::Int(v) if is_sampler(kind) => {
+            ui.text(format!("{}: unit {}", label, v));
+        }
+        UniformValue::Int(mut v) => {
+            if ui.input_int(&label, &mut v) {
+                edited = Some(UniformValue::Int(v));
+            }
+        }
+        UniformValue::UInt(v) => {
+            let mut signed = v as i32;
+            if ui.input_int(&label, &mut signed) {
+                edited = Some(UniformValue::UInt(signed.max(0) as u32));
+            }
+        }
+        UniformValue::Float(mut v) => {
+            if ui.drag_float_config(&label).speed(0.01).build(ui, &mut v) {
+                edited = Some(UniformValue::Float(v));
+            }
+        }
+        UniformValue::Vec2(mut v) => {
+            if ui
+                .drag_float_config(&label)
+                .speed(0.01)
+                .build_array(ui, &mut v)
+            {
+                edited = Some(UniformValue::Vec2(v));
+            }
+        }
+        UniformValue::Vec3(mut v) => {
+            if ui
+                .drag_float_config(&label)
+                .speed(0.01)
+                .build_array(ui, &mut v)
+            {
+                edited = Some(UniformValue::Vec3(v));
+            }
+        }
+        UniformValue::Vec4(mut v) => {
+            if ui
+                .drag_float_config(&label)
+                .speed(0.01)
+                .build_array(ui, &mut v)
+            {
+                edited = Some(UniformValue::Vec4(v));
+            }
+        }
+        // Matrices are set every frame from the camera and transforms
+        UniformValue::Mat3(_) | UniformValue::Mat4(_) => {
+            ui.text_disabled(&label);
+        }
+    }
+
+    if let Some(value) = edited
+        && let Err(e) = shader.override_uniform(name, value)
+    {
+        error!("Failed to set uniform: {}", e);
+    }
+
+    if shader.is_overridden(name) {
+        ui.same_line();
+        if ui.small_button("Reset") {
+            shader.clear_override(name);
+        }
+    }
+}
+
+/// Draw `id` and its children as tree nodes, selecting the one clicked. The
+/// nodes above `reveal` are opened.
+#[cfg(not(target_arch = "wasm32"))]
+fn node_tree(
+    ui: &Ui,
+    graph: &SceneGraph,
+    id: NodeId,
+    selected: &mut Option<NodeId>,
+    reveal: Option<NodeId>,
+) {
+    let Some(node) = graph.node(id) else {
+        return;
+    };
+
+    let name = if node.name.is_empty() {
+        "(unnamed)"
+    } else {
+        &node.name
+    };
+    let mut config = ui.tree_node_config(format!("{}##{:?}", name, id));
+    if reveal.is_some_and(|r| r != id && graph.subtree(id).any(|(below, _)| below == r)) {
+        config = config.opened(true, Condition::Always);
+    }
+    let token = config
+        .leaf(node.children().is_empty())
+        .selected(*selected == Some(id))
+        .open_on_arrow(true)
+        .push();
+
+    if ui.is_item_clicked() {
+        *selected = Some(id);
+    }
+
+    if let Some(_token) = token {
+        for child in node.children() {
+            node_tree(ui, graph, *child, selected, reveal);
+        }
+    }
+}
+
+/// Note that node `id` was changed in the scene panel, so [`ViewPort::scene_file`]
+/// saves it with the object it belongs to. `material` says its material was
+/// edited, not just its transform.
+#[cfg(not(target_arch = "wasm32"))]
+fn mark_edited(objects: &mut [SceneObject], graph: &SceneGraph, id: NodeId, material: bool) {
+    for object in objects {
+        let Some(path) = node_path(graph, object.node, id) else {
+            continue;
+        };
+        // The root is placed by the object itself
+        if path.is_empty() && !material {
+            return;
+        }
+
+        let index = match object.desc.nodes.iter().position(|n| n.path == path) {
+            Some(index) => index,
+            None => {
+                object.desc.nodes.push(NodeDesc {
+                    path,
+                    ..Default::default()
+                });
+                object.desc.nodes.len() - 1
+            }
+        };
+        // The values themselves are read when the scene is saved
+        let node = &mut object.desc.nodes[index];
+        if material && node.material.is_none() {
+            node.material = Some(MaterialFactors::default());
+        }
+        return;
+    }
+}
+
+/// Edit the colors and factors `material`'s shading model uses. Returns
+/// whether anything changed.
+#[cfg(not(target_arch = "wasm32"))]
+fn material_editor(ui: &Ui, material: &mut Material) -> bool {
+    let edit_color = |label: &str, color: &mut Vec3| {
+        let mut rgb = color.to_array();
+        let changed = ui.color_edit3(label, &mut rgb);
+        if changed {
+            *color = rgb.into();
+        }
+        changed
+    };
+
+    let mut changed = false;
+    match material.shading {
+        ShadingModel::Phong => {
+            ui.text_disabled("Blinn-Phong");
+            changed |= edit_color("Ambient", &mut material.ambient);
+            changed |= edit_color("Diffuse", &mut material.diffuse);
+            changed |= edit_color("Specular##Material", &mut material.specular);
+            changed |= ui.slider_f32("Shininess", &mut material.shininess, 1.0, 512.0);
+        }
+        ShadingModel::MetallicRoughness => {
+            ui.text_disabled("Metallic-roughness");
+            changed |= edit_color("Base Color", &mut material.diffuse);
+            changed |= ui.slider_f32("Metallic", &mut material.metallic, 0.0, 1.0);
+            changed |= ui.slider_f32("Roughness", &mut material.roughness, 0.0, 1.0);
+            if material.occlusion_map.is_some() {
+                changed |= ui.slider_f32("Occlusion", &mut material.occlusion_strength, 0.0, 1.0);
+            }
+        }
+    }
+
+    changed |= edit_color("Emissive", &mut material.emissive);
+    changed |= ui.slider_f32("Opacity", &mut material.opacity, 0.0, 1.0);
+    if material.normal_map.is_some() {
+        changed |= ui.slider_f32("Normal Scale", &mut material.normal_scale, 0.0, 2.0);
+    }
+    changed
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use glutin::api::egl::context::PossiblyCurrentContext;
+    use glutin::api::egl::device::Device;
+    use glutin::api::egl::display::Display;
+    use glutin::config::{ConfigSurfaceTypes, ConfigTemplateBuilder};
+    use glutin::context::{ContextApi, ContextAttributesBuilder, Version};
+    use glutin::prelude::*;
+    use winit::event::{DeviceEvent, WindowEvent};
+
+    use super::*;
+    use crate::FakeSurface;
+    use crate::graphics::new_gl_ref;
+
+    /// A viewport on a [`FakeSurface`], drawing through a surfaceless EGL context
+    /// that has to outlive it. `None` without an EGL device to make one on.
+    fn view_port(
+        width: u32,
+        height: u32,
+    ) -> Option<(ViewPort, Rc<FakeSurface>, PossiblyCurrentContext)> {
+        let device = Device::query_devices().ok()?.next()?;
+        let display = unsafe { Display::with_device(&device, None) }.ok()?;
+        let template = ConfigTemplateBuilder::new()
+            .with_surface_type(ConfigSurfaceTypes::empty())
+            .build();
+        let config = unsafe { display.find_configs(template) }.ok()?.next()?;
+        let attributes = ContextAttributesBuilder::new()
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+            .build(None);
+        let context = unsafe { display.create_context(&config, &attributes) }
+            .ok()?
+            .make_current_surfaceless()
+            .ok()?;
+        let gl =
+            unsafe { glow::Context::from_loader_function_cstr(|s| display.get_proc_address(s)) };
+
+        let surface = Rc::new(FakeSurface::new(width, height));
+        let view_port = ViewPort::new(surface.clone(), new_gl_ref(gl), (width, height));
+        Some((view_port, surface, context))
+    }
+
+    /// Input holding one frame's mouse movement.
+    fn mouse_moved(x: f64, y: f64) -> WinitInputHelper {
+        let mut input = WinitInputHelper::new();
+        input.step();
+        input.process_window_event(&WindowEvent::Focused(true));
+        input.process_device_event(&DeviceEvent::MouseMotion { delta: (x, y) });
+        input.end_step();
+        input
+    }
+
+    #[test]
+    fn captured_mouse_grabs_and_recenters_the_cursor() {
+        let Some((mut view_port, surface, _context)) = view_port(320, 240) else {
+            eprintln!("No EGL device, skipping");
+            return;
+        };
+        assert!(!surface.is_grabbed());
+
+        view_port.capture_mouse = true;
+        view_port.set_camera_mode(CameraMode::Fps);
+        assert!(surface.is_grabbed());
+
+        let yaw = view_port.camera.yaw;
+        view_port.handle_input(0.0, &mouse_moved(10.0, 0.0));
+        assert_ne!(view_port.camera.yaw, yaw);
+        assert_eq!(surface.cursor_position(), Some((160, 120)));
+
+        // Orbiting drags a visible cursor around
+        view_port.set_camera_mode(CameraMode::Orbit);
+        assert!(!surface.is_grabbed());
+        view_port.set_camera_mode(CameraMode::Fps);
+        view_port.switch_projection(true);
+        view_port.update(10.0);
+        assert!(view_port.enable_2d);
+        assert!(!surface.is_grabbed());
+    }
+
+    #[test]
+    fn resize_follows_the_surface() {
+        let Some((mut view_port, surface, _context)) = view_port(320, 240) else {
+            eprintln!("No EGL device, skipping");
+            return;
+        };
+        let aspect = |m: Mat4| m.y_axis.y / m.x_axis.x;
+
+        surface.set_size(640, 240);
+        view_port.resize(640, 240);
+        let projection = view_port.projection_matrix;
+        assert!((aspect(projection) - 640.0 / 240.0).abs() < 1e-4);
+
+        // Minimizing keeps the last projection rather than dividing by zero
+        surface.set_size(0, 0);
+        view_port.resize(0, 0);
+        assert_eq!(view_port.projection_matrix, projection);
     }
 }
@@ -0,0 +1,160 @@
+//! Command line arguments for the native viewer.
+
+use core::game::light_manager::MAX_LIGHTS_LIMIT;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: native [OPTIONS] [model.obj/gltf/glb/stl/ply]
+       native --render <out.png> [--size <WxH>] [OPTIONS] <model>
+
+Without a model the built-in demo scene is shown.
+
+Options:
+  --texture <file>   Use this image as the diffuse map of every part
+  --scale <s>        Uniform scale applied to the model (default 1)
+  --camera <x,y,z>   Camera position; the camera looks at the model
+  --render <file>    Render the model to a PNG without opening a window
+  --size <WxH>       Size of the rendered image (default 512x512)
+  --turntable <n>    Render n frames of a full turn around the model instead,
+                     numbered after the '--render' name (out_0000.png, ...)
+  --max-lights <n>   Most lights drawn at once, up to 16 (default 8)
+  --environment <f>  Light the scene with an equirectangular .hdr image
+  --skybox <path>    Show a cubemap behind the scene: a directory of faces,
+                     a cross or an equirectangular image
+  -h, --help         Print this message";
+
+pub struct Args {
+    pub model: Option<PathBuf>,
+    pub texture: Option<PathBuf>,
+    pub scale: f32,
+    pub camera: Option<[f32; 3]>,
+    pub render: Option<PathBuf>,
+    pub size: Option<(u32, u32)>,
+    pub turntable: Option<u32>,
+    pub max_lights: Option<usize>,
+    pub environment: Option<PathBuf>,
+    pub skybox: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parse the arguments after the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args {
+            model: None,
+            texture: None,
+            scale: 1.0,
+            camera: None,
+            render: None,
+            size: None,
+            turntable: None,
+            max_lights: None,
+            environment: None,
+            skybox: None,
+            help: false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("'{}' needs a value", name))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => parsed.help = true,
+                "--texture" => parsed.texture = Some(PathBuf::from(value("--texture")?)),
+                "--scale" => {
+                    let text = value("--scale")?;
+                    parsed.scale = text
+                        .parse()
+                        .ok()
+                        .filter(|s: &f32| s.is_finite() && *s > 0.0)
+                        .ok_or_else(|| format!("Invalid scale '{}'", text))?;
+                }
+                "--camera" => {
+                    let text = value("--camera")?;
+                    parsed.camera = Some(parse_vec3(&text).ok_or_else(|| {
+                        format!("Invalid camera position '{}', expected x,y,z", text)
+                    })?);
+                }
+                "--render" => parsed.render = Some(PathBuf::from(value("--render")?)),
+                "--size" => {
+                    let text = value("--size")?;
+                    parsed.size = Some(parse_size(&text).ok_or_else(|| {
+                        format!("Invalid size '{}', expected WIDTHxHEIGHT", text)
+                    })?);
+                }
+                "--turntable" => {
+                    let text = value("--turntable")?;
+                    parsed.turntable = Some(
+                        text.parse()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| format!("Invalid frame count '{}'", text))?,
+                    );
+                }
+                "--max-lights" => {
+                    let text = value("--max-lights")?;
+                    parsed.max_lights = Some(
+                        text.parse()
+                            .ok()
+                            .filter(|n| (1..=MAX_LIGHTS_LIMIT).contains(n))
+                            .ok_or_else(|| {
+                                format!(
+                                    "Invalid light count '{}', expected 1 to {}",
+                                    text, MAX_LIGHTS_LIMIT
+                                )
+                            })?,
+                    );
+                }
+                "--environment" => {
+                    parsed.environment = Some(PathBuf::from(value("--environment")?));
+                }
+                "--skybox" => {
+                    parsed.skybox = Some(PathBuf::from(value("--skybox")?));
+                }
+                _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+                _ if parsed.model.is_some() => {
+                    return Err(format!("Unexpected argument '{}'", arg));
+                }
+                _ => parsed.model = Some(PathBuf::from(arg)),
+            }
+        }
+
+        if parsed.model.is_none()
+            && (parsed.texture.is_some() || parsed.camera.is_some() || parsed.scale != 1.0)
+        {
+            return Err("'--texture', '--scale' and '--camera' need a model".to_string());
+        }
+
+        if parsed.render.is_some() && parsed.model.is_none() {
+            return Err("'--render' needs a model".to_string());
+        }
+        if parsed.size.is_some() && parsed.render.is_none() {
+            return Err("'--size' only applies to '--render'".to_string());
+        }
+        if parsed.turntable.is_some() && parsed.render.is_none() {
+            return Err("'--turntable' only applies to '--render'".to_string());
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn parse_vec3(text: &str) -> Option<[f32; 3]> {
+    let values: Vec<f32> = text
+        .split(',')
+        .map(|v| v.trim().parse().ok().filter(|v: &f32| v.is_finite()))
+        .collect::<Option<_>>()?;
+
+    values.try_into().ok()
+}
+
+fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.split_once(['x', 'X'])?;
+    let width: u32 = width.trim().parse().ok()?;
+    let height: u32 = height.trim().parse().ok()?;
+
+    (width > 0 && height > 0).then_some((width, height))
+}
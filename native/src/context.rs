@@ -129,14 +129,14 @@ impl NativeContext {
         // Render viewport UI
         view_port.gui(ui);
 
-        self.platform.prepare_render_with_ui(&ui, window);
+        self.platform.prepare_render_with_ui(ui, window);
 
         let draw_data = self.context.render();
         self.renderer
             .new_frame()
             .expect("Error preparing imgui frame");
         self.renderer
-            .render(&draw_data)
+            .render(draw_data)
             .expect("Failed to render imgui");
     }
 }
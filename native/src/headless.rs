@@ -0,0 +1,202 @@
+//! Offscreen rendering without a window.
+//!
+//! Uses an EGL device with a surfaceless context, which Mesa provides even on
+//! machines without a GPU (llvmpipe). Everything is drawn into a framebuffer
+//! object, so no display server is needed.
+
+use std::path::{Path, PathBuf};
+
+use glutin::api::egl::context::PossiblyCurrentContext;
+use glutin::api::egl::device::Device;
+use glutin::api::egl::display::Display;
+use glutin::config::{ConfigSurfaceTypes, ConfigTemplateBuilder};
+use glutin::context::{ContextApi, ContextAttributesBuilder, Version};
+use glutin::prelude::*;
+use log::info;
+
+use core::game::Turntable;
+use core::graphics::{GlRef, new_gl_ref};
+use core::loaders::ModelData;
+use core::{HeadlessRenderer, ModelOptions};
+
+/// Image size used by `--render` when `--size` isn't given.
+pub const DEFAULT_RENDER_SIZE: (u32, u32) = (512, 512);
+
+/// Background of rendered images, transparent so thumbnails can sit on anything.
+const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
+
+/// A current GL context that isn't tied to any window.
+pub struct HeadlessContext {
+    pub gl: GlRef,
+
+    // Kept alive for as long as `gl` is used
+    _context: PossiblyCurrentContext,
+    _display: Display,
+}
+
+impl HeadlessContext {
+    pub fn new() -> Result<Self, String> {
+        let devices: Vec<Device> = Device::query_devices()
+            .map_err(|e| format!("Failed to query EGL devices: {}", e))?
+            .collect();
+
+        // Prefer real hardware, but the software device works everywhere
+        let device = devices
+            .iter()
+            .find(|d| !d.extensions().contains("EGL_MESA_device_software"))
+            .or(devices.first())
+            .ok_or("No EGL devices found")?;
+        let software = device.extensions().contains("EGL_MESA_device_software");
+        info!(
+            "Headless EGL device: {}",
+            if software {
+                "software"
+            } else {
+                device.name().unwrap_or("hardware")
+            }
+        );
+
+        let display = unsafe { Display::with_device(device, None) }
+            .map_err(|e| format!("Failed to open EGL display: {}", e))?;
+
+        let template = ConfigTemplateBuilder::new()
+            .with_surface_type(ConfigSurfaceTypes::empty())
+            .build();
+        let config = unsafe { display.find_configs(template) }
+            .map_err(|e| format!("Failed to find an EGL config: {}", e))?
+            .next()
+            .ok_or("No EGL config without a surface")?;
+
+        let attributes = ContextAttributesBuilder::new()
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+            .build(None);
+        let context = unsafe { display.create_context(&config, &attributes) }
+            .map_err(|e| format!("Failed to create GL context: {}", e))?
+            .make_current_surfaceless()
+            .map_err(|e| format!("Failed to make GL context current: {}", e))?;
+
+        let gl =
+            unsafe { glow::Context::from_loader_function_cstr(|s| display.get_proc_address(s)) };
+        let gl = new_gl_ref(gl);
+
+        Ok(Self {
+            gl,
+            _context: context,
+            _display: display,
+        })
+    }
+}
+
+/// A renderer at `size` with `model` loaded, lit by `environment` and in
+/// front of `skybox` if there are any.
+fn load_renderer(
+    context: &HeadlessContext,
+    model: ModelData,
+    options: &ModelOptions,
+    environment: Option<&Path>,
+    skybox: Option<&Path>,
+    size: (u32, u32),
+) -> Result<HeadlessRenderer, String> {
+    let mut renderer = HeadlessRenderer::new(context.gl.clone(), size)?;
+    if let Some(path) = environment {
+        renderer
+            .render_manager
+            .environment
+            .load_file(path)
+            .map_err(|e| format!("Failed to load environment: {}", e))?;
+    }
+    if let Some(path) = skybox {
+        renderer
+            .render_manager
+            .environment
+            .load_background(path)
+            .map_err(|e| format!("Failed to load skybox: {}", e))?;
+    }
+    renderer.load_model(model, options)?;
+    Ok(renderer)
+}
+
+/// Render `model` into a `width`x`height` PNG at `output`, lit by `environment`
+/// and in front of `skybox` if there are any.
+pub fn render_to_file(
+    model: ModelData,
+    options: &ModelOptions,
+    environment: Option<&Path>,
+    skybox: Option<&Path>,
+    (width, height): (u32, u32),
+    output: &Path,
+) -> Result<(), String> {
+    let context = HeadlessContext::new()?;
+    let mut renderer = load_renderer(
+        &context,
+        model,
+        options,
+        environment,
+        skybox,
+        (width, height),
+    )?;
+    let image = renderer.render(CLEAR_COLOR);
+
+    image
+        .save(output)
+        .map_err(|e| format!("Failed to write '{}': {}", output.display(), e))?;
+    info!(
+        "Rendered {}x{} image to '{}'",
+        width,
+        height,
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Like [`render_to_file`], but `frames` images of the camera going once
+/// around the model, numbered after `output`: `out.png` becomes `out_0000.png`
+/// and on.
+pub fn render_turntable(
+    model: ModelData,
+    options: &ModelOptions,
+    environment: Option<&Path>,
+    skybox: Option<&Path>,
+    (width, height): (u32, u32),
+    frames: u32,
+    output: &Path,
+) -> Result<(), String> {
+    let context = HeadlessContext::new()?;
+    let mut renderer = load_renderer(
+        &context,
+        model,
+        options,
+        environment,
+        skybox,
+        (width, height),
+    )?;
+    let turntable = Turntable::default();
+
+    for frame in 0..frames {
+        let path = numbered_path(output, frame);
+        renderer
+            .render(CLEAR_COLOR)
+            .save(&path)
+            .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+        turntable.step(&mut renderer.camera, frames);
+    }
+    info!(
+        "Rendered {} {}x{} frames to '{}'",
+        frames,
+        width,
+        height,
+        numbered_path(output, 0).display()
+    );
+
+    Ok(())
+}
+
+/// `output` with `_` and `frame` in four digits after its stem.
+fn numbered_path(output: &Path, frame: u32) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = output
+        .extension()
+        .map_or("png".into(), |e| e.to_string_lossy());
+    output.with_file_name(format!("{}_{:04}.{}", stem, frame, extension))
+}
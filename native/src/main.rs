@@ -1,9 +1,14 @@
 #![allow(non_snake_case)]
 
+mod args;
 mod context;
+// Needs EGL devices, which macOS and Windows don't have
+#[cfg(all(unix, not(target_os = "macos")))]
+mod headless;
 
 use std::error::Error;
 use std::num::NonZeroU32;
+use std::path::Path;
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -23,8 +28,12 @@ use winit::window::WindowAttributes;
 use winit_input_helper::WinitInputHelper;
 
 use app::{App, FPS, HEIGHT, WIDTH};
-use core::{PlatformBackend, State, ViewPort};
+use core::game::light_manager;
+use core::graphics::depth::load_clip_control;
+use core::loaders::{ModelData, TextureSource, load_model};
+use core::{ModelOptions, PlatformBackend, State, ViewPort};
 
+use args::{Args, USAGE};
 use context::NativeContext;
 
 /// Native platform backend using glutin/OpenGL.
@@ -80,6 +89,7 @@ impl PlatformBackend for NativeBackend {
 
             let glContext = glContext.make_current(&glSurface).unwrap();
             let gl = glow::Context::from_loader_function_cstr(|s| glDisplay.get_proc_address(s));
+            load_clip_control(&gl, |s| glDisplay.get_proc_address(s));
 
             (window, gl, glSurface, glContext)
         };
@@ -160,7 +170,64 @@ impl PlatformBackend for NativeBackend {
     }
 }
 
+/// Parse everything the command line asks for before a window is opened.
+fn load(args: &Args) -> Result<Option<ModelData>, String> {
+    let Some(path) = &args.model else {
+        return Ok(None);
+    };
+
+    let mut model =
+        load_model(path).map_err(|e| format!("Failed to load '{}': {}", path.display(), e))?;
+
+    if let Some(texture) = &args.texture {
+        if !texture.is_file() {
+            return Err(format!("Texture '{}' does not exist", texture.display()));
+        }
+        model.set_texture(TextureSource::File(texture.clone()));
+    }
+
+    Ok(Some(model))
+}
+
+/// Draw `model` offscreen into `output`, or a turntable sequence named after it.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn render(
+    model: ModelData,
+    options: &ModelOptions,
+    args: &Args,
+    output: &Path,
+) -> Result<(), String> {
+    let size = args.size.unwrap_or(headless::DEFAULT_RENDER_SIZE);
+    let environment = args.environment.as_deref();
+    let skybox = args.skybox.as_deref();
+    match args.turntable {
+        Some(frames) => {
+            headless::render_turntable(model, options, environment, skybox, size, frames, output)
+        }
+        None => headless::render_to_file(model, options, environment, skybox, size, output),
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn render(_: ModelData, _: &ModelOptions, _: &Args, _: &Path) -> Result<(), String> {
+    Err("Headless rendering is not supported on this platform".to_string())
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
+}
+
 fn main() {
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n", USAGE);
+        exit_with(&e)
+    });
+    if args.help {
+        println!("{}", USAGE);
+        return;
+    }
+
     // Setup tracing subscriber for logging
     let timer = parse("[year]-[month padding:zero]-[day padding:zero] [hour]:[minute]:[second]")
         .expect("Tracing time format is invalid");
@@ -176,6 +243,53 @@ fn main() {
         .compact()
         .init();
 
-    let event_loop = EventLoop::new().unwrap();
-    App::<NativeBackend>::run(event_loop);
+    // Shaders are sized for this many lights when they're built
+    if let Some(max_lights) = args.max_lights {
+        light_manager::set_max_lights(max_lights);
+    }
+
+    let model = load(&args).unwrap_or_else(|e| exit_with(&e));
+    let options = ModelOptions {
+        scale: args.scale,
+        camera_position: args.camera.map(Into::into),
+    };
+
+    if let Some(output) = &args.render {
+        let Some(model) = model else {
+            exit_with("'--render' needs a model");
+        };
+        if let Err(e) = render(model, &options, &args, output) {
+            exit_with(&e);
+        }
+        return;
+    }
+
+    let event_loop = EventLoop::new()
+        .unwrap_or_else(|e| exit_with(&format!("Failed to create event loop: {}", e)));
+    let environment = args.environment.clone();
+    let skybox = args.skybox.clone();
+    if model.is_none() && environment.is_none() && skybox.is_none() {
+        App::<NativeBackend>::run(event_loop);
+        return;
+    }
+
+    let result = App::<NativeBackend>::run_with(
+        event_loop,
+        Box::new(move |state: &mut State| {
+            if let Some(path) = &environment {
+                state.view_port.load_environment(path)?;
+            }
+            if let Some(path) = &skybox {
+                state.view_port.load_background(path)?;
+            }
+            match model {
+                Some(model) => state.view_port.load_model(model, &options),
+                None => Ok(()),
+            }
+        }),
+    );
+
+    if let Err(e) = result {
+        exit_with(&e);
+    }
 }
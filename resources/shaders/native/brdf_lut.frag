@@ -0,0 +1,64 @@
+#version 330
+
+#define PI 3.14159265359
+#define SAMPLES 256u
+
+in vec2 f_uv;
+
+out vec4 o_color;
+
+vec2 hammersley(uint i, uint count) {
+	uint bits = i;
+	bits = (bits << 16u) | (bits >> 16u);
+	bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+	bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+	bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+	bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+	return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+vec3 importance_sample(vec2 xi, float roughness) {
+	float a = roughness * roughness;
+	float phi = 2.0 * PI * xi.x;
+	float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+	float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+	return vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+// Schlick-GGX with the k used for image based lighting
+float geometry(float n_dot_v, float n_dot_l, float roughness) {
+	float k = roughness * roughness / 2.0;
+	float view = n_dot_v / (n_dot_v * (1.0 - k) + k);
+	float light = n_dot_l / (n_dot_l * (1.0 - k) + k);
+	return view * light;
+}
+
+// Scale and bias to the fresnel term of the split sum, for the n.v along x
+// and the roughness along y
+void main(void) {
+	float n_dot_v = max(f_uv.x, 1e-3);
+	float roughness = f_uv.y;
+	vec3 view = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+	float scale = 0.0;
+	float bias = 0.0;
+	for (uint i = 0u; i < SAMPLES; i++) {
+		vec3 halfway = importance_sample(hammersley(i, SAMPLES), roughness);
+		vec3 light = normalize(2.0 * dot(view, halfway) * halfway - view);
+
+		float n_dot_l = max(light.z, 0.0);
+		float n_dot_h = max(halfway.z, 0.0);
+		float v_dot_h = max(dot(view, halfway), 0.0);
+
+		if (n_dot_l > 0.0) {
+			float g = geometry(n_dot_v, n_dot_l, roughness);
+			float visibility = g * v_dot_h / max(n_dot_h * n_dot_v, 1e-4);
+			float fresnel = pow(1.0 - v_dot_h, 5.0);
+
+			scale += (1.0 - fresnel) * visibility;
+			bias += fresnel * visibility;
+		}
+	}
+
+	o_color = vec4(scale / float(SAMPLES), bias / float(SAMPLES), 0.0, 1.0);
+}
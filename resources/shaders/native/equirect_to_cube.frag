@@ -0,0 +1,17 @@
+#version 330
+
+#define PI 3.14159265359
+
+in vec3 f_dir;
+
+uniform sampler2D u_equirect;
+
+out vec4 o_color;
+
+void main(void) {
+	vec3 dir = normalize(f_dir);
+
+	// Longitude around Y, latitude up from the horizon, with the image's top row up
+	vec2 uv = vec2(atan(dir.z, dir.x) / (2.0 * PI) + 0.5, 0.5 - asin(clamp(dir.y, -1.0, 1.0)) / PI);
+	o_color = vec4(texture(u_equirect, uv).rgb, 1.0);
+}
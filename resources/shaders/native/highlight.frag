@@ -0,0 +1,10 @@
+#version 330
+
+uniform vec4 u_color;
+
+out vec4 o_color;
+
+// A flat tint blended over the selected object
+void main(void) {
+	o_color = u_color;
+}
@@ -1,39 +1,189 @@
 #version 330
 
 in vec3 f_pos;
-in vec3 f_color;
+in vec4 f_color;
 in vec3 f_normal;
 in vec2 f_uv;
+in vec4 f_tangent;
+
+struct Material {
+	vec3 ambient;
+	vec3 diffuse;
+	vec3 specular;
+	float shininess;
+	float opacity;
+	bool lit;
+	vec3 emissive;
+	float normal_scale;
+	bool has_normal_map;
+};
+
+#ifndef MAX_LIGHTS
+#define MAX_LIGHTS 8
+#endif
+
+// Matches MAX_SHADOW_MAPS in shadow_manager.rs
+#define MAX_SHADOWS 4
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+struct Light {
+	int kind;
+	vec3 position;
+	vec3 direction;
+	vec3 color;
+	float intensity;
+	float ambient;
+	float specular;
+	vec3 attenuation; // constant, linear, quadratic
+	float inner_cone; // cosines of the cone angles
+	float outer_cone;
+	int shadow; // index into u_shadow_maps, or -1 for none
+	float shadow_bias;
+};
 
 uniform sampler2D u_texture;
+uniform sampler2D u_specular_map;
+uniform sampler2D u_normal_map;
+uniform sampler2D u_emissive_map;
+uniform Material u_material;
 
 // Lighting
-uniform float u_ambient;
-uniform float u_specular;
-uniform vec3 u_light_pos;
+uniform Light u_lights[MAX_LIGHTS];
+uniform int u_light_count;
 uniform vec3 u_view_pos;
 
+// Shadows
+uniform sampler2DShadow u_shadow_maps[MAX_SHADOWS];
+uniform mat4 u_light_space[MAX_SHADOWS];
+
 out vec4 o_color;
 
-void main(void) {
+// 3x3 PCF, on top of the 2x2 the depth comparison filters already
+float pcf(sampler2DShadow map, vec3 coord) {
+	vec2 texel = 1.0 / vec2(textureSize(map, 0));
+	float lit = 0.0;
+	for (int x = -1; x <= 1; x++) {
+		for (int y = -1; y <= 1; y++) {
+			lit += texture(map, vec3(coord.xy + vec2(x, y) * texel, coord.z));
+		}
+	}
+	return lit / 9.0;
+}
+
+// How much of `light` reaches the fragment, 0 in full shadow
+float shadow(Light light, float n_dot_l) {
+	if (light.shadow < 0) {
+		return 1.0;
+	}
+
+	vec4 clip = u_light_space[light.shadow] * vec4(f_pos, 1.0);
+	vec3 coord = clip.xyz / clip.w * 0.5 + 0.5;
+	if (any(lessThan(coord, vec3(0.0))) || any(greaterThan(coord, vec3(1.0)))) {
+		return 1.0;
+	}
+
+	// Surfaces facing away from the light need more bias to avoid acne
+	coord.z -= light.shadow_bias * (2.0 - n_dot_l);
+
+	// Sampler arrays can only be indexed with constants
+	if (light.shadow == 0) return pcf(u_shadow_maps[0], coord);
+	if (light.shadow == 1) return pcf(u_shadow_maps[1], coord);
+	if (light.shadow == 2) return pcf(u_shadow_maps[2], coord);
+	return pcf(u_shadow_maps[3], coord);
+}
+
+// Normal map in the mesh's tangent frame, or in one built from screen space
+// derivatives when the mesh has no tangents
+vec3 perturb_normal(vec3 normal) {
+	vec3 sampled = texture(u_normal_map, f_uv).xyz * 2.0 - 1.0;
+	sampled.xy *= u_material.normal_scale;
+
+	// MikkTSpace wants the interpolated tangent as it is, not renormalized
+	if (dot(f_tangent.xyz, f_tangent.xyz) > 1e-12) {
+		vec3 bitangent = (f_tangent.w < 0.0 ? -1.0 : 1.0) * cross(normal, f_tangent.xyz);
+		return normalize(mat3(f_tangent.xyz, bitangent, normal) * sampled);
+	}
+
+	vec3 dp1 = dFdx(f_pos);
+	vec3 dp2 = dFdy(f_pos);
+	vec2 duv1 = dFdx(f_uv);
+	vec2 duv2 = dFdy(f_uv);
+
+	vec3 dp2_perp = cross(dp2, normal);
+	vec3 dp1_perp = cross(normal, dp1);
+	vec3 tangent = dp2_perp * duv1.x + dp1_perp * duv2.x;
+	vec3 bitangent = dp2_perp * duv1.y + dp1_perp * duv2.y;
+
+	float scale = inversesqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+	return normalize(mat3(tangent * scale, bitangent * scale, normal) * sampled);
+}
+
+vec3 shade(Light light, vec3 normal, vec3 view_dir, vec3 specular_color) {
+	vec3 light_dir;
+	float attenuation = 1.0;
+
+	if (light.kind == LIGHT_DIRECTIONAL) {
+		light_dir = -light.direction;
+	} else {
+		vec3 to_light = light.position - f_pos;
+		float dist = length(to_light);
+		light_dir = to_light / max(dist, 1e-4);
+		attenuation = 1.0 / max(dot(light.attenuation, vec3(1.0, dist, dist * dist)), 1e-4);
+
+		if (light.kind == LIGHT_SPOT) {
+			float theta = dot(light_dir, -light.direction);
+			float edge = max(light.inner_cone - light.outer_cone, 1e-4);
+			attenuation *= clamp((theta - light.outer_cone) / edge, 0.0, 1.0);
+		}
+	}
+
+	vec3 radiance = light.color * light.intensity;
+
 	// Ambient
-	vec3 ambient = u_ambient * f_color;
-	
+	vec3 ambient = light.ambient * radiance * u_material.ambient;
+
 	// Diffuse
-	vec3 normal = normalize(f_normal);
-	vec3 light_dir = normalize(u_light_pos - f_pos);
 	float diff = max(dot(normal, light_dir), 0.0);
-	vec3 diffuse = diff * f_color;
+	vec3 diffuse = diff * radiance * u_material.diffuse;
 
 	// Specular
-	vec3 view_dir = normalize(u_view_pos - f_pos);
 	vec3 reflect_dir = reflect(-light_dir, normal);
-	float spec = pow(max(dot(view_dir, reflect_dir), 0.0), 32.0);
-	vec3 specular = u_specular * spec * f_color;
+	float spec = pow(max(dot(view_dir, reflect_dir), 0.0), u_material.shininess);
+	vec3 specular = light.specular * spec * radiance * specular_color;
+
+	return ambient + (diffuse + specular) * attenuation * shadow(light, diff);
+}
+
+void main(void) {
+	vec4 tex_color = texture(u_texture, f_uv);
+	vec3 emissive = u_material.emissive * texture(u_emissive_map, f_uv).rgb;
+
+	if (!u_material.lit) {
+		o_color = vec4(u_material.diffuse, u_material.opacity) * f_color * tex_color;
+		o_color.rgb += emissive;
+		return;
+	}
+
+	vec3 normal = normalize(f_normal);
+	if (u_material.has_normal_map) {
+		normal = perturb_normal(normal);
+	}
+	vec3 view_dir = normalize(u_view_pos - f_pos);
+	vec3 specular_color = u_material.specular * texture(u_specular_map, f_uv).rgb;
 
-	vec3 result = ambient + diffuse + specular;
+	vec3 result = vec3(0.0);
+	for (int i = 0; i < MAX_LIGHTS; i++) {
+		if (i >= u_light_count) {
+			break;
+		}
+		result += shade(u_lights[i], normal, view_dir, specular_color);
+	}
 
-	vec4 color = vec4(result, 1.0) * vec4(f_color, 1.0);
+	vec4 color = vec4(result, u_material.opacity) * f_color;
 	
-	o_color = color * texture(u_texture, f_uv);
+	o_color = color * tex_color;
+	o_color.rgb += emissive;
 }
\ No newline at end of file
@@ -2,22 +2,30 @@
 
 uniform mat4 pv;
 uniform mat4 model;
+uniform mat3 u_uv_transform;
 
-in vec3 i_position;
-in vec3 i_color;
-in vec3 i_normal;
-in vec2 i_uv;
+// Fixed locations so the shadow pass can draw the same vertex arrays
+layout(location = 0) in vec3 i_position;
+layout(location = 1) in vec4 i_color; // alpha is 1 for RGB colors
+layout(location = 2) in vec3 i_normal;
+layout(location = 3) in vec2 i_uv;
+layout(location = 4) in vec4 i_tangent; // handedness in w
 
 out vec3 f_pos;
-out vec3 f_color;
+out vec4 f_color;
 out vec3 f_normal;
 out vec2 f_uv;
+out vec4 f_tangent;
 
 void main(void) {
     f_pos = vec3(model * vec4(i_position, 1.0));
     f_color = i_color;
     f_normal = mat3(transpose(inverse(model))) * i_normal;
-    f_uv = i_uv;
+    f_uv = (u_uv_transform * vec3(i_uv, 1.0)).xy;
+
+    // A mirroring model matrix flips the bitangent too
+    mat3 linear = mat3(model);
+    f_tangent = vec4(linear * i_tangent.xyz, i_tangent.w * sign(determinant(linear)));
 
     gl_Position = pv * vec4(f_pos, 1.0);
 }
\ No newline at end of file
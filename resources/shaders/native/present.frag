@@ -0,0 +1,12 @@
+#version 330
+
+in vec2 f_uv;
+
+uniform sampler2D u_color;
+
+out vec4 o_color;
+
+// Copies an offscreen image to the window as it is
+void main(void) {
+	o_color = texture(u_color, f_uv);
+}
@@ -0,0 +1,23 @@
+#version 330
+
+in vec2 f_uv;
+
+uniform sampler2D u_depth;
+uniform bool u_perspective;
+uniform float u_near;
+uniform float u_far;
+
+out vec4 o_color;
+
+void main(void) {
+	float depth = texture(u_depth, f_uv).r;
+
+	// Perspective depth bunches up near 1, show the distance instead
+	if (u_perspective) {
+		float z = depth * 2.0 - 1.0;
+		float distance = 2.0 * u_near * u_far / (u_far + u_near - z * (u_far - u_near));
+		depth = (distance - u_near) / (u_far - u_near);
+	}
+
+	o_color = vec4(vec3(depth), 1.0);
+}
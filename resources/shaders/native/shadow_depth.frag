@@ -0,0 +1,5 @@
+#version 330
+
+// Depth only, the depth buffer is written without any color output
+void main(void) {
+}
@@ -0,0 +1,10 @@
+#version 330
+
+uniform mat4 u_light_space;
+uniform mat4 model;
+
+layout(location = 0) in vec3 i_position;
+
+void main(void) {
+    gl_Position = u_light_space * model * vec4(i_position, 1.0);
+}
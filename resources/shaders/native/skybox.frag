@@ -0,0 +1,21 @@
+#version 330
+
+in vec3 f_dir;
+
+uniform samplerCube u_environment;
+uniform mat3 u_environment_rotation;
+uniform float u_environment_intensity;
+uniform bool u_srgb; // LDR images, the HDR environment is linear already
+
+out vec4 o_color;
+
+void main(void) {
+	vec3 color = texture(u_environment, u_environment_rotation * f_dir).rgb;
+	if (u_srgb) {
+		color = pow(color, vec3(2.2));
+	}
+	color *= u_environment_intensity;
+
+	// Same output as the object shaders, linear to sRGB without tonemapping
+	o_color = vec4(pow(color, vec3(1.0 / 2.2)), 1.0);
+}
@@ -0,0 +1,30 @@
+#version 300 es
+
+// Corners of a cube around the origin, and the two triangles of each face
+const vec3 CORNERS[8] = vec3[8](
+	vec3(-1.0, -1.0, -1.0), vec3(1.0, -1.0, -1.0), vec3(-1.0, 1.0, -1.0), vec3(1.0, 1.0, -1.0),
+	vec3(-1.0, -1.0, 1.0), vec3(1.0, -1.0, 1.0), vec3(-1.0, 1.0, 1.0), vec3(1.0, 1.0, 1.0)
+);
+const int INDICES[36] = int[36](
+	0, 2, 1, 1, 2, 3, // -Z
+	4, 5, 6, 5, 7, 6, // +Z
+	0, 4, 2, 2, 4, 6, // -X
+	1, 3, 5, 3, 7, 5, // +X
+	0, 1, 4, 1, 5, 4, // -Y
+	2, 6, 3, 3, 6, 7  // +Y
+);
+
+uniform mat4 u_view_projection; // without the view's translation
+uniform float u_far_depth; // clip space depth of the far plane, 1 or 0 with reverse-Z
+
+out vec3 f_dir;
+
+// The inside of a cube, drawn as 36 vertices without buffers. It lands on the
+// far plane, so anything else drawn is in front of it.
+void main(void) {
+	vec3 corner = CORNERS[INDICES[gl_VertexID]];
+	f_dir = corner;
+
+	vec4 clip = u_view_projection * vec4(corner, 1.0);
+	gl_Position = vec4(clip.xy, clip.w * u_far_depth, clip.w);
+}
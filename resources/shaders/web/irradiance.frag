@@ -0,0 +1,37 @@
+#version 300 es
+// HDR values don't fit in mediump
+precision highp float;
+precision highp samplerCube;
+
+#define PI 3.14159265359
+
+in vec3 f_dir;
+
+uniform samplerCube u_environment;
+uniform float u_source_lod; // mip of the environment that is sampled
+
+out vec4 o_color;
+
+// Cosine weighted average of the light reaching a surface facing `f_dir`
+void main(void) {
+	vec3 normal = normalize(f_dir);
+	vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+	vec3 right = normalize(cross(up, normal));
+	up = cross(normal, right);
+
+	const float STEP = 0.05;
+	vec3 irradiance = vec3(0.0);
+	float samples = 0.0;
+
+	for (float phi = 0.0; phi < 2.0 * PI; phi += STEP) {
+		for (float theta = 0.0; theta < 0.5 * PI; theta += STEP) {
+			vec3 tangent = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+			vec3 dir = tangent.x * right + tangent.y * up + tangent.z * normal;
+
+			irradiance += textureLod(u_environment, dir, u_source_lod).rgb * cos(theta) * sin(theta);
+			samples += 1.0;
+		}
+	}
+
+	o_color = vec4(PI * irradiance / samples, 1.0);
+}
@@ -0,0 +1,258 @@
+#version 300 es
+// The GGX terms need more than mediump
+precision highp float;
+precision highp sampler2DShadow;
+precision highp samplerCube;
+
+in vec3 f_pos;
+in vec4 f_color;
+in vec3 f_normal;
+in vec2 f_uv;
+in vec4 f_tangent;
+
+// glTF's metallic-roughness model
+struct Material {
+	vec3 base_color;
+	float opacity;
+	float metallic;
+	float roughness;
+	float occlusion_strength;
+	float normal_scale;
+	bool has_normal_map;
+	vec3 emissive;
+};
+
+#ifndef MAX_LIGHTS
+#define MAX_LIGHTS 8
+#endif
+
+// Matches MAX_SHADOW_MAPS in shadow_manager.rs
+#define MAX_SHADOWS 4
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+#define PI 3.14159265359
+
+struct Light {
+	int kind;
+	vec3 position;
+	vec3 direction;
+	vec3 color;
+	float intensity;
+	float ambient;
+	float specular; // Phong only
+	vec3 attenuation; // constant, linear, quadratic
+	float inner_cone; // cosines of the cone angles
+	float outer_cone;
+	int shadow; // index into u_shadow_maps, or -1 for none
+	float shadow_bias;
+};
+
+uniform sampler2D u_texture; // base color
+uniform sampler2D u_metallic_roughness_map; // roughness in green, metallic in blue
+uniform sampler2D u_normal_map;
+uniform sampler2D u_occlusion_map; // red channel
+uniform sampler2D u_emissive_map;
+uniform Material u_material;
+
+// Lighting
+uniform Light u_lights[MAX_LIGHTS];
+uniform int u_light_count;
+uniform vec3 u_view_pos;
+
+// Shadows
+uniform sampler2DShadow u_shadow_maps[MAX_SHADOWS];
+uniform mat4 u_light_space[MAX_SHADOWS];
+
+// Image based lighting, in place of the lights' ambient term when it's on
+uniform bool u_ibl;
+uniform samplerCube u_irradiance_map;
+uniform samplerCube u_prefiltered_map;
+uniform sampler2D u_brdf_lut;
+uniform float u_prefiltered_lod; // mip of u_prefiltered_map for roughness 1
+uniform mat3 u_environment_rotation; // world to environment directions
+uniform float u_environment_intensity;
+
+out vec4 o_color;
+
+// 3x3 PCF, on top of the 2x2 the depth comparison filters already
+float pcf(sampler2DShadow map, vec3 coord) {
+	vec2 texel = 1.0 / vec2(textureSize(map, 0));
+	float lit = 0.0;
+	for (int x = -1; x <= 1; x++) {
+		for (int y = -1; y <= 1; y++) {
+			lit += texture(map, vec3(coord.xy + vec2(x, y) * texel, coord.z));
+		}
+	}
+	return lit / 9.0;
+}
+
+// How much of `light` reaches the fragment, 0 in full shadow
+float shadow(Light light, float n_dot_l) {
+	if (light.shadow < 0) {
+		return 1.0;
+	}
+
+	vec4 clip = u_light_space[light.shadow] * vec4(f_pos, 1.0);
+	vec3 coord = clip.xyz / clip.w * 0.5 + 0.5;
+	if (any(lessThan(coord, vec3(0.0))) || any(greaterThan(coord, vec3(1.0)))) {
+		return 1.0;
+	}
+
+	// Surfaces facing away from the light need more bias to avoid acne
+	coord.z -= light.shadow_bias * (2.0 - n_dot_l);
+
+	// Sampler arrays can only be indexed with constants
+	if (light.shadow == 0) return pcf(u_shadow_maps[0], coord);
+	if (light.shadow == 1) return pcf(u_shadow_maps[1], coord);
+	if (light.shadow == 2) return pcf(u_shadow_maps[2], coord);
+	return pcf(u_shadow_maps[3], coord);
+}
+
+vec3 srgb_to_linear(vec3 color) {
+	return pow(color, vec3(2.2));
+}
+
+// Normal map in the mesh's tangent frame, or in one built from screen space
+// derivatives when the mesh has no tangents
+vec3 perturb_normal(vec3 normal) {
+	vec3 sampled = texture(u_normal_map, f_uv).xyz * 2.0 - 1.0;
+	sampled.xy *= u_material.normal_scale;
+
+	// MikkTSpace wants the interpolated tangent as it is, not renormalized
+	if (dot(f_tangent.xyz, f_tangent.xyz) > 1e-12) {
+		vec3 bitangent = (f_tangent.w < 0.0 ? -1.0 : 1.0) * cross(normal, f_tangent.xyz);
+		return normalize(mat3(f_tangent.xyz, bitangent, normal) * sampled);
+	}
+
+	vec3 dp1 = dFdx(f_pos);
+	vec3 dp2 = dFdy(f_pos);
+	vec2 duv1 = dFdx(f_uv);
+	vec2 duv2 = dFdy(f_uv);
+
+	vec3 dp2_perp = cross(dp2, normal);
+	vec3 dp1_perp = cross(normal, dp1);
+	vec3 tangent = dp2_perp * duv1.x + dp1_perp * duv2.x;
+	vec3 bitangent = dp2_perp * duv1.y + dp1_perp * duv2.y;
+
+	float scale = inversesqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+	return normalize(mat3(tangent * scale, bitangent * scale, normal) * sampled);
+}
+
+// Trowbridge-Reitz GGX normal distribution
+float distribution(float n_dot_h, float roughness) {
+	float a = roughness * roughness;
+	float a2 = a * a;
+	float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+	return a2 / max(PI * d * d, 1e-6);
+}
+
+// Smith's method with Schlick-GGX for both the view and the light direction
+float geometry(float n_dot_v, float n_dot_l, float roughness) {
+	float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+	float view = n_dot_v / (n_dot_v * (1.0 - k) + k);
+	float light = n_dot_l / (n_dot_l * (1.0 - k) + k);
+	return view * light;
+}
+
+vec3 fresnel(float cos_theta, vec3 f0) {
+	return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Fresnel for light from all directions, rough surfaces reflect less of it
+vec3 fresnel_roughness(float cos_theta, vec3 f0, float roughness) {
+	return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Light from the environment, with the split sum approximation for the highlights
+vec3 ambient_ibl(vec3 normal, vec3 view_dir, vec3 albedo, float metallic, float roughness) {
+	float n_dot_v = max(dot(normal, view_dir), 1e-4);
+	vec3 f0 = mix(vec3(0.04), albedo, metallic);
+	vec3 f = fresnel_roughness(n_dot_v, f0, roughness);
+
+	vec3 irradiance = texture(u_irradiance_map, u_environment_rotation * normal).rgb;
+	vec3 diffuse = (1.0 - f) * (1.0 - metallic) * irradiance * albedo;
+
+	vec3 reflected = u_environment_rotation * reflect(-view_dir, normal);
+	vec3 prefiltered = textureLod(u_prefiltered_map, reflected, roughness * u_prefiltered_lod).rgb;
+	vec2 brdf = texture(u_brdf_lut, vec2(n_dot_v, roughness)).rg;
+	vec3 specular = prefiltered * (f * brdf.x + brdf.y);
+
+	return (diffuse + specular) * u_environment_intensity;
+}
+
+vec3 shade(Light light, vec3 normal, vec3 view_dir, vec3 albedo, float metallic, float roughness, float occlusion) {
+	vec3 light_dir;
+	float attenuation = 1.0;
+
+	if (light.kind == LIGHT_DIRECTIONAL) {
+		light_dir = -light.direction;
+	} else {
+		vec3 to_light = light.position - f_pos;
+		float dist = length(to_light);
+		light_dir = to_light / max(dist, 1e-4);
+		attenuation = 1.0 / max(dot(light.attenuation, vec3(1.0, dist, dist * dist)), 1e-4);
+
+		if (light.kind == LIGHT_SPOT) {
+			float theta = dot(light_dir, -light.direction);
+			float edge = max(light.inner_cone - light.outer_cone, 1e-4);
+			attenuation *= clamp((theta - light.outer_cone) / edge, 0.0, 1.0);
+		}
+	}
+
+	vec3 radiance = light.color * light.intensity;
+	vec3 ambient = u_ibl ? vec3(0.0) : light.ambient * radiance * albedo * occlusion;
+
+	float n_dot_l = max(dot(normal, light_dir), 0.0);
+	float n_dot_v = max(dot(normal, view_dir), 1e-4);
+	vec3 halfway = normalize(view_dir + light_dir);
+	float n_dot_h = max(dot(normal, halfway), 0.0);
+
+	vec3 f0 = mix(vec3(0.04), albedo, metallic);
+	vec3 f = fresnel(max(dot(halfway, view_dir), 0.0), f0);
+	float d = distribution(n_dot_h, roughness);
+	float g = geometry(n_dot_v, n_dot_l, roughness);
+
+	vec3 specular = d * g * f / max(4.0 * n_dot_v * n_dot_l, 1e-4);
+	vec3 diffuse = (1.0 - f) * (1.0 - metallic) * albedo / PI;
+
+	// Scaled by PI so a light has the same strength as in the Phong shader
+	vec3 direct = (diffuse + specular) * radiance * n_dot_l * PI;
+
+	return ambient + direct * attenuation * shadow(light, n_dot_l);
+}
+
+void main(void) {
+	vec4 base = texture(u_texture, f_uv);
+	vec3 albedo = u_material.base_color * srgb_to_linear(base.rgb) * f_color.rgb;
+	float opacity = u_material.opacity * base.a * f_color.a;
+
+	vec4 metallic_roughness = texture(u_metallic_roughness_map, f_uv);
+	float metallic = clamp(u_material.metallic * metallic_roughness.b, 0.0, 1.0);
+	// Perfectly smooth surfaces would have an infinitely small highlight
+	float roughness = clamp(u_material.roughness * metallic_roughness.g, 0.04, 1.0);
+	float occlusion = mix(1.0, texture(u_occlusion_map, f_uv).r, u_material.occlusion_strength);
+
+	vec3 normal = normalize(f_normal);
+	if (u_material.has_normal_map) {
+		normal = perturb_normal(normal);
+	}
+	vec3 view_dir = normalize(u_view_pos - f_pos);
+
+	vec3 result = vec3(0.0);
+	for (int i = 0; i < MAX_LIGHTS; i++) {
+		if (i >= u_light_count) {
+			break;
+		}
+		result += shade(u_lights[i], normal, view_dir, albedo, metallic, roughness, occlusion);
+	}
+	if (u_ibl) {
+		result += ambient_ibl(normal, view_dir, albedo, metallic, roughness) * occlusion;
+	}
+	result += u_material.emissive * srgb_to_linear(texture(u_emissive_map, f_uv).rgb);
+
+	// Lighting is worked out in linear space, the screen wants sRGB
+	o_color = vec4(pow(result, vec3(1.0 / 2.2)), opacity);
+}
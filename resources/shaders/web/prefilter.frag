@@ -0,0 +1,76 @@
+#version 300 es
+// HDR values don't fit in mediump
+precision highp float;
+precision highp samplerCube;
+
+#define PI 3.14159265359
+#define SAMPLES 256u
+
+in vec3 f_dir;
+
+uniform samplerCube u_environment;
+uniform float u_roughness;
+uniform float u_resolution; // size of an environment face in texels
+
+out vec4 o_color;
+
+// Low discrepancy sequence, spreads samples more evenly than random ones
+vec2 hammersley(uint i, uint count) {
+	uint bits = i;
+	bits = (bits << 16u) | (bits >> 16u);
+	bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+	bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+	bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+	bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+	return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// Halfway vector around `normal`, distributed like the GGX lobe
+vec3 importance_sample(vec2 xi, vec3 normal, float roughness) {
+	float a = roughness * roughness;
+	float phi = 2.0 * PI * xi.x;
+	float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+	float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+	vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+	vec3 tangent = normalize(cross(up, normal));
+	vec3 bitangent = cross(normal, tangent);
+	return normalize(tangent * cos(phi) * sin_theta + bitangent * sin(phi) * sin_theta + normal * cos_theta);
+}
+
+float distribution(float n_dot_h, float roughness) {
+	float a = roughness * roughness;
+	float a2 = a * a;
+	float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+	return a2 / max(PI * d * d, 1e-6);
+}
+
+// The environment blurred by a GGX lobe of `u_roughness`, assuming the view
+// is along the normal
+void main(void) {
+	vec3 normal = normalize(f_dir);
+	vec3 view = normal;
+
+	vec3 color = vec3(0.0);
+	float weight = 0.0;
+
+	for (uint i = 0u; i < SAMPLES; i++) {
+		vec3 halfway = importance_sample(hammersley(i, SAMPLES), normal, u_roughness);
+		vec3 light = normalize(2.0 * dot(view, halfway) * halfway - view);
+
+		float n_dot_l = dot(normal, light);
+		if (n_dot_l > 0.0) {
+			// Unlikely directions read a blurrier mip, which hides the noise
+			float n_dot_h = max(dot(normal, halfway), 0.0);
+			float pdf = distribution(n_dot_h, u_roughness) / 4.0 + 1e-4;
+			float sample_angle = 1.0 / (float(SAMPLES) * pdf);
+			float texel_angle = 4.0 * PI / (6.0 * u_resolution * u_resolution);
+			float lod = u_roughness == 0.0 ? 0.0 : 0.5 * log2(sample_angle / texel_angle) + 1.0;
+
+			color += textureLod(u_environment, light, lod).rgb * n_dot_l;
+			weight += n_dot_l;
+		}
+	}
+
+	o_color = vec4(color / max(weight, 1e-4), 1.0);
+}
@@ -0,0 +1,12 @@
+#version 300 es
+precision mediump float;
+
+out vec2 f_uv;
+
+// A quad covering the viewport, drawn as a 4 vertex strip without buffers
+void main(void) {
+    vec2 corner = vec2(gl_VertexID & 1, gl_VertexID >> 1);
+    f_uv = corner;
+
+    gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,6 @@
+#version 300 es
+precision mediump float;
+
+// Depth only, the depth buffer is written without any color output
+void main(void) {
+}